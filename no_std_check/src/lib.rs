@@ -0,0 +1,22 @@
+//! Makes sure ``pretty_dtoa`` builds without ``std``. Run
+//! ``cargo build --manifest-path no_std_check/Cargo.toml`` to check.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use pretty_dtoa::{dtoa, ftoa, FmtFloatConfig};
+
+pub fn format_f64(value: f64) -> String {
+    dtoa(value, FmtFloatConfig::default())
+}
+
+pub fn format_f32(value: f32) -> String {
+    ftoa(value, FmtFloatConfig::auto())
+}
+
+pub fn config_problem(config: FmtFloatConfig) -> Option<String> {
+    config.validate().err().map(|error| format!("{}", error))
+}
@@ -12,6 +12,22 @@
 //! assert_eq!(dtoa(123123.0, config), "123000.0");
 //! assert_eq!(dtoa(99999.0, config), "99000.0");
 //! ```
+//!
+//! The crate works without ``std`` (only needing ``alloc``) when the default
+//! ``std`` feature is disabled. The ``serde`` feature implements ``Serialize``
+//! and ``Deserialize`` for ``FmtFloatConfig``, for loading configs from files.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", test)))]
+extern crate alloc;
+#[cfg(not(any(feature = "std", test)))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 
 // Testing macros, to make sure edge cases are hit
 
@@ -25,7 +41,6 @@ macro_rules! hit {
 macro_rules! hit {
     ($ident:ident) => {{
         extern "C" {
-            #[no_mangle]
             static $ident: $crate::__rt::AtomicUsize;
         }
         unsafe {
@@ -63,19 +78,161 @@ mod __rt {
             let value_on_exit = self.mark.load(Ordering::Relaxed);
             assert!(
                 value_on_exit > self.value_on_entry,
-                format!("mark was not hit: {}", self.name)
+                "mark was not hit: {}",
+                self.name
             )
         }
     }
 }
 
+use core::char;
+use core::cmp::Ordering;
+use core::fmt::{self, Write};
+use core::ops::{Deref, DerefMut, RangeInclusive};
 use ryu_floating_decimal::{d2d, f2d};
-use std::char;
 
+// Custom functions are compared by their addresses
+#[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RoundMode {
     Round,
     Truncate,
+    /// Round like ``Round``, but when the float's exact value is exactly
+    /// halfway, round to the even digit
+    HalfEven,
+    /// Round like ``Round``, but when the float's exact value is exactly
+    /// halfway, round down
+    HalfDown,
+    /// Decide whether to round up with a custom function. It's given the
+    /// digits (in ascii) before any are removed, and the index of the
+    /// first digit that's removed. This can't be serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(fn(&[u8], usize) -> bool),
+}
+
+/// When to write a trailing ".0" after whole numbers, and after single
+/// digit mantissas in scientific notation
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrailingPoint {
+    /// Always write the ".0" (example: 12.0 and 1.0e5)
+    Always,
+    /// Never write the ".0", or the radix point (example: 12 and 1e5)
+    Never,
+    /// Only write the ".0" where it's needed to read the string back as
+    /// a float instead of an integer, which is after whole numbers
+    /// without scientific notation (example: 12.0 and 1e5)
+    RoundTripMinimal,
+    /// Only write the ".0" after single digit mantissas, unless ``simplify``
+    /// is set. This is what ``add_point_zero(false)`` sets (example: 12 and
+    /// 1.0e5)
+    MantissasOnly,
+}
+
+/// Whether to write the exponent marker in scientific notation as "e" or "E".
+/// Exponents of 0 count as positive
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExpCase {
+    /// Always write "e" (example: 1.5e3 and 1.5e-3)
+    Lower,
+    /// Always write "E" (example: 1.5E3 and 1.5E-3)
+    Upper,
+    /// Write "e" for positive exponents and "E" for negative exponents
+    /// (example: 1.5e3 and 1.5E-3)
+    LowerForPositive,
+    /// Write "E" for positive exponents and "e" for negative exponents
+    /// (example: 1.5E3 and 1.5e-3)
+    UpperForPositive,
+}
+
+/// A problem with a configuration, found by ``FmtFloatConfig::validate``
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The radix point and group separator are the same character, or one
+    /// of them is a digit or a minus sign, so the output can't be parsed
+    AmbiguousSeparators,
+    /// ``upper_e_break`` is less than or equal to ``lower_e_break``, so
+    /// every value uses scientific notation
+    CrossedEBreaks,
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ConfigError::AmbiguousSeparators => write!(
+                f,
+                "radix_point and group_separator must be different, and can't be digits or '-'"
+            ),
+            ConfigError::CrossedEBreaks => {
+                write!(f, "upper_e_break must be greater than lower_e_break")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+/// A high level description of how to format floats, which can be turned
+/// into a ``FmtFloatConfig`` with ``From``
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// Exactly this many digits after the decimal point, without scientific
+    /// notation (example: 1234.5 -> 1234.50 with 2 decimals)
+    Fixed { decimals: u8 },
+    /// Scientific notation with exactly this many significant digits
+    /// (example: 1234.5 -> 1.23e3 with 3 significant digits)
+    Scientific { sig: u8 },
+    /// At most this many significant digits, using scientific notation when
+    /// the exponent is at least ``sig`` or less than -4, like C's ``%g``
+    /// (example: 1234.5 -> 1.23e3 and 0.5 -> 0.5 with 3 significant digits)
+    General { sig: u8 },
+    /// The shortest digits that read back as the same float, with the
+    /// default configuration (example: 1234.5 -> 1234.5)
+    Shortest,
+    /// Like ``Fixed``, with a "%" after the value. The value isn't scaled,
+    /// so 12.5 is 12.5% (example: 12.5 -> 12.50% with 2 decimals)
+    Percent { decimals: u8 },
+}
+
+/// How ``dtoa_checked`` computes its check digit from the digits in a string
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum CheckDigit {
+    /// The Luhn algorithm: starting from the last digit, every other digit
+    /// is doubled (subtracting 9 if that's more than 9), and the check digit
+    /// is what brings the total up to a multiple of 10
+    /// (example: 7992739871 -> 3)
+    Luhn,
+    /// The sum of the digits, mod 10 (example: 7992739871 -> 2)
+    DigitSum,
+}
+
+/// A formatted float, along with the positions of its parts. See
+/// ``dtoa_structured``.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FormattedFloat {
+    /// The formatted string
+    pub text: String,
+    /// The byte offset of the radix point in the text, if there is one
+    pub radix_index: Option<usize>,
+    /// The byte offset of the exponent marker in the text, if
+    /// scientific notation is used
+    pub exponent_index: Option<usize>,
+    /// The exponent written in scientific notation, if there is one
+    pub exponent: Option<i32>,
+    /// The length in bytes of the sign at the start of the text
+    pub sign_len: usize,
+    /// The number of significant digits written, which doesn't include
+    /// leading zeros, or zeros left of the radix point after the last
+    /// nonzero digit. Zero counts its written digits. This is 0 for NaN,
+    /// infinities, and strings that don't fit max_width
+    pub significant_digits: usize,
+    /// Whether digits of the shortest round-trip representation were
+    /// removed, which is when ``truncation_marker`` is written. This is
+    /// true for strings that don't fit max_width
+    pub precision_lost: bool,
 }
 
 /// Configuration for formatting floats into strings. Look at the associated methods for this type
@@ -92,6 +249,13 @@ pub enum RoundMode {
 ///
 /// assert_eq!(dtoa(123.4567, config), "123.46");
 /// ```
+///
+/// With the ``serde`` feature, the string fields of deserialized configs
+/// (``unit``, ``spoken_exponent_template`` and the templates) are leaked to
+/// get ``&'static str``s. Each distinct string is only leaked once, so
+/// reloading a config doesn't use more memory unless its strings change.
+// Custom functions in configs are compared by their addresses
+#[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct FmtFloatConfig {
     /// A max number of significant digits to include
@@ -102,37 +266,190 @@ pub struct FmtFloatConfig {
     /// in the formatted string (after the first non-zero digit).
     /// None means no minimum
     pub min_sig_digits: Option<u8>,
+    /// Let min_sig_digits pad zeros past the decimal point of
+    /// whole numbers
+    pub min_sig_digits_in_fraction: bool,
+    /// Never remove digits left of the decimal point because
+    /// of max_sig_digits, unless using scientific notation
+    pub keep_integer_digits: bool,
     /// A max number of digits after the decimal point to include.
-    pub max_decimal_digits: Option<i8>,
+    pub max_decimal_digits: Option<i16>,
     /// A min number of digits after the decimal point to include.
-    pub min_decimal_digits: Option<i8>,
-    /// How many digits left of the decimal point there can be
-    /// using scientific notation
+    pub min_decimal_digits: Option<i16>,
+    /// A min number of digits before the decimal point to include,
+    /// padding with leading zeros, when not using scientific notation
+    pub min_integer_digits: Option<u8>,
+    /// Apply max_decimal_digits to the digits after the mantissa's
+    /// decimal point when using scientific notation
+    pub decimal_digits_in_e_notation: bool,
+    /// Use scientific notation for values with an exponent
+    /// greater than or equal to this
     pub upper_e_break: i8,
-    /// Lower equivelent of upper_e_break
+    /// Use scientific notation for values with an exponent
+    /// less than this
     pub lower_e_break: i8,
     /// Ignore digits after (and including) a certain number of
     /// consecutive 9's or 0's
     pub ignore_extremes: Option<u8>,
     /// Round or truncate
     pub round_mode: RoundMode,
+    /// Break ties in the digits with the exact value of the float
+    /// when rounding
+    pub precise_tie_detection: bool,
     /// Force scientific e notation
     pub force_e_notation: bool,
     /// Force no scientific e notation. Overrides force_e_notation
     pub force_no_e_notation: bool,
-    /// Capitalize the e in scientific notation
-    pub capitalize_e: bool,
-    /// Add a .0 at the end of integers
-    pub add_point_zero: bool,
+    /// Use whichever notation gives a shorter string, instead
+    /// of upper_e_break and lower_e_break
+    pub prefer_shortest_notation: bool,
+    /// Use scientific notation only when it is shorter than regular
+    /// notation by at least this many characters, instead of upper_e_break
+    /// and lower_e_break. Overrides prefer_shortest_notation
+    pub e_notation_if_shorter_by: Option<u8>,
+    /// Leave out the exponent marker and exponent in scientific
+    /// notation when the exponent is 0
+    pub suppress_zero_exponent: bool,
+    /// The largest exponent magnitude to use in scientific notation.
+    /// Larger magnitudes are moved into the mantissa
+    pub max_exponent_magnitude: Option<u8>,
+    /// Write exponents that are multiples of 3 from -24 to 24 as SI
+    /// prefixes in scientific notation. Overrides max_exponent_magnitude
+    /// for those exponents
+    pub si_prefixes: bool,
+    /// A max number of significant digits in the mantissa, only
+    /// when using scientific notation
+    pub max_mantissa_digits: Option<u8>,
+    /// The number of characters in the mantissa (without the sign) in
+    /// scientific notation, which is filled by rounding or padding with 0's
+    pub mantissa_width: Option<u8>,
+    /// The number of characters to pad the mantissa (with its sign) to
+    /// with spaces in scientific notation, so exponents line up
+    pub mantissa_field_width: Option<u8>,
+    /// The number of characters to fill in scientific notation, counting
+    /// the sign, mantissa and exponent, by rounding or padding the mantissa
+    /// and padding the exponent with 0's. Overrides mantissa_width
+    pub scientific_total_width: Option<u8>,
+    /// Write every digit as an integer mantissa, followed by the
+    /// exponent. Overrides the other notation options
+    pub integer_mantissa: bool,
+    /// Whether to write the e in scientific notation as "e" or "E"
+    pub exp_case: ExpCase,
+    /// A custom function to write the exponent marker and exponent
+    /// in scientific notation. Overrides exp_case
+    pub exponent_formatter: Option<fn(i32) -> String>,
+    /// Write the exponent in words in scientific notation, using
+    /// spoken_exponent_template. Overrides exponent_formatter and
+    /// exp_case, and ignores max_width
+    pub spoken_exponent: bool,
+    /// The words to use for the exponent when spoken_exponent is set.
+    /// "{}" is replaced with the exponent
+    pub spoken_exponent_template: &'static str,
+    /// When to add a .0 at the end of integers and single digit mantissas
+    pub trailing_point: TrailingPoint,
+    /// Remove trailing zeros in the fractional part that aren't
+    /// required by min_sig_digits or min_decimal_digits
+    pub simplify: bool,
     /// The maximum number of characters in the string. This
     /// should be greater than or equal to 7 to guarantee all floats
     /// will print correctly, but can be smaller for certain floats
     pub max_width: Option<u8>,
+    /// The maximum number of bytes in the string's UTF-8 encoding
+    pub max_byte_width: Option<u16>,
+    /// A character to add at the end of the string when digits of the
+    /// exact value were removed
+    pub truncation_marker: Option<char>,
+    /// A character to add after the sign when digits of the exact value
+    /// were removed
+    pub approx_marker: Option<char>,
+    /// Characters to add at the end of the string when digits of the exact
+    /// value were removed, for values shown larger and smaller than they are
+    pub rounding_markers: Option<(char, char)>,
+    /// Write NaNs with their sign bit set as "-NaN"
+    pub show_nan_sign: bool,
+    /// Write the payload (the mantissa bits) of NaNs in hex, like "NaN(0x1)"
+    pub show_nan_payload: bool,
+    /// A unit to write after finite values
+    pub unit: Option<&'static str>,
+    /// Put a space between the value and the unit
+    pub unit_space: bool,
     /// The seperator between the integer and non-integer part
     pub radix_point: char,
+    /// Leave out the radix point, so the digits after it follow the
+    /// integer part directly
+    pub omit_radix_point: bool,
+    /// The seperator between groups of group_size digits in the integer
+    /// part, when not using scientific notation. None means no grouping
+    pub group_separator: Option<char>,
+    /// The number of digits in each group of the integer part
+    pub group_size: u8,
+    /// The seperator between groups of fraction_group_size digits after
+    /// the radix point, when not using scientific notation. None means no
+    /// grouping
+    pub fraction_group_separator: Option<char>,
+    /// The number of digits in each group after the radix point
+    pub fraction_group_size: u8,
+    /// Always start finite values with a sign character, using a
+    /// space for non-negative values
+    pub sign_column: bool,
+    /// A template for positive values, where "{}" is replaced
+    /// with the magnitude
+    pub positive_template: Option<&'static str>,
+    /// A template for negative values, where "{}" is replaced
+    /// with the magnitude
+    pub negative_template: Option<&'static str>,
+    /// A template for zero, where "{}" is replaced with the magnitude.
+    /// Overrides positive_template and negative_template
+    pub zero_template: Option<&'static str>,
 }
 
 impl FmtFloatConfig {
+    /// The same as ``default()``, which always round-trips
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// const CONFIG: FmtFloatConfig = FmtFloatConfig::PRECISE.radix_point(',');
+    ///
+    /// assert_eq!(dtoa(0.1 + 0.2, FmtFloatConfig::PRECISE), "0.30000000000000004");
+    /// assert_eq!(dtoa(1.5, CONFIG), "1,5");
+    /// ```
+    pub const PRECISE: FmtFloatConfig = FmtFloatConfig::default();
+
+    /// The same as ``machine()``
+    pub const MACHINE: FmtFloatConfig = FmtFloatConfig::machine();
+
+    /// The same as ``auto()``
+    pub const AUTO: FmtFloatConfig = FmtFloatConfig::auto();
+
+    /// The same as ``display()``, for showing floats to humans
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// assert_eq!(dtoa(98765.4321, FmtFloatConfig::HUMAN), "98,765");
+    /// ```
+    pub const HUMAN: FmtFloatConfig = FmtFloatConfig::display();
+
+    /// The default configuration, always using scientific notation
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// assert_eq!(dtoa(1500.0, FmtFloatConfig::SCIENTIFIC), "1.5e3");
+    /// assert_eq!(dtoa(0.25, FmtFloatConfig::SCIENTIFIC), "2.5e-1");
+    /// ```
+    pub const SCIENTIFIC: FmtFloatConfig = FmtFloatConfig::default().force_e_notation();
+
+    /// The same as ``pretty_scientific()``
+    pub const PRETTY_SCIENTIFIC: FmtFloatConfig = FmtFloatConfig::pretty_scientific();
+
     /// A default configuration. This will always round-trip, so
     /// using ``str::parse::<f64>`` or ``str::parse:<f32>`` will
     /// give the exact same float.
@@ -140,22 +457,165 @@ impl FmtFloatConfig {
         FmtFloatConfig {
             max_sig_digits: None,
             min_sig_digits: None,
+            min_sig_digits_in_fraction: true,
+            keep_integer_digits: false,
             max_decimal_digits: None,
             min_decimal_digits: None,
+            min_integer_digits: None,
+            decimal_digits_in_e_notation: false,
             upper_e_break: 4,
             lower_e_break: -4,
             ignore_extremes: None,
             round_mode: RoundMode::Round,
+            precise_tie_detection: false,
             force_e_notation: false,
             force_no_e_notation: false,
-            capitalize_e: false,
-            add_point_zero: true,
+            prefer_shortest_notation: false,
+            e_notation_if_shorter_by: None,
+            suppress_zero_exponent: false,
+            max_exponent_magnitude: None,
+            si_prefixes: false,
+            max_mantissa_digits: None,
+            mantissa_width: None,
+            mantissa_field_width: None,
+            scientific_total_width: None,
+            integer_mantissa: false,
+            exp_case: ExpCase::Lower,
+            exponent_formatter: None,
+            spoken_exponent: false,
+            spoken_exponent_template: " times ten to the {}",
+            trailing_point: TrailingPoint::Always,
+            simplify: false,
             max_width: None,
+            max_byte_width: None,
+            truncation_marker: None,
+            approx_marker: None,
+            rounding_markers: None,
+            show_nan_sign: false,
+            show_nan_payload: false,
+            unit: None,
+            unit_space: true,
             radix_point: '.',
+            omit_radix_point: false,
+            group_separator: None,
+            group_size: 3,
+            fraction_group_separator: None,
+            fraction_group_size: 3,
+            sign_column: false,
+            positive_template: None,
+            negative_template: None,
+            zero_template: None,
+        }
+    }
+
+    /// A configuration for showing floats to humans, which picks a
+    /// precision based on the magnitude of each value. This will not
+    /// round-trip. The exact rules are:
+    ///
+    /// * Values are rounded to 4 significant digits, except that digits
+    ///   left of the decimal point are never removed, so values of 1000
+    ///   and above are rounded to integers
+    /// * Scientific notation is used for values of 1e6 and above, and
+    ///   for values below 1e-4 (where 4 significant digits are kept)
+    /// * Runs of 3 or more consecutive 9's or 0's are ignored, which
+    ///   hides floating point noise
+    /// * Integers are shown without a ".0"
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// let config = FmtFloatConfig::auto();
+    ///
+    /// assert_eq!(dtoa(0.1 + 0.2, config), "0.3");
+    /// assert_eq!(dtoa(123.456, config), "123.5");
+    /// assert_eq!(dtoa(98765.4321, config), "98765");
+    /// assert_eq!(dtoa(1234567.0, config), "1.235e6");
+    /// ```
+    pub const fn auto() -> Self {
+        FmtFloatConfig::default()
+            .max_significant_digits(4)
+            .keep_integer_digits(true)
+            .upper_e_break(6)
+            .ignore_extremes(3)
+            .add_point_zero(false)
+    }
+
+    /// A configuration for writing floats that other programs will read.
+    /// Values always round-trip, use a "." radix point and no grouping,
+    /// and scientific notation is only used for values of 1e17 and above
+    /// or below 1e-7
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// let config = FmtFloatConfig::machine();
+    ///
+    /// assert_eq!(dtoa(1234567.5, config), "1234567.5");
+    /// assert_eq!(dtoa(1e20, config), "1.0e20");
+    /// ```
+    pub const fn machine() -> Self {
+        FmtFloatConfig::default()
+            .upper_e_break(17)
+            .lower_e_break(-7)
+    }
+
+    /// A configuration for showing floats to humans. This is the same as
+    /// ``auto()``, with digits left of the decimal point grouped by ","
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// let config = FmtFloatConfig::display();
+    ///
+    /// assert_eq!(dtoa(98765.4321, config), "98,765");
+    /// assert_eq!(dtoa(0.1 + 0.2, config), "0.3");
+    /// ```
+    pub const fn display() -> Self {
+        FmtFloatConfig::auto().group_separator(',')
+    }
+
+    /// A configuration for showing scientific values to humans. Everything
+    /// is written in scientific notation, with SI prefixes between 1e-24 and
+    /// 1e27, and a superscript power of ten (see ``superscript_exponent``)
+    /// outside that range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{dtoa, FmtFloatConfig};
+    ///
+    /// let config = FmtFloatConfig::pretty_scientific();
+    ///
+    /// assert_eq!(dtoa(1500.0, config), "1.5k");
+    /// assert_eq!(dtoa(2.5, config), "2.5");
+    /// assert_eq!(dtoa(1.5e-30, config), "1.5×10⁻³⁰");
+    /// ```
+    pub const fn pretty_scientific() -> Self {
+        FmtFloatConfig::default()
+            .force_e_notation()
+            .si_prefixes(true)
+            .exponent_formatter(superscript_exponent)
+            .trailing_point(TrailingPoint::Never)
+    }
+
+    /// Pick between ``machine()`` (if true) and ``display()`` (if false)
+    pub const fn for_machine(machine: bool) -> Self {
+        if machine {
+            FmtFloatConfig::machine()
+        } else {
+            FmtFloatConfig::display()
         }
     }
 
-    /// The maximum number of non-zero digits to include in the string
+    /// The maximum number of non-zero digits to include in the string. A
+    /// value of 0 is treated as 1, since every value keeps at least one
+    /// significant digit
     pub const fn max_significant_digits(mut self, val: u8) -> Self {
         self.max_sig_digits = Some(val);
         self
@@ -167,26 +627,92 @@ impl FmtFloatConfig {
         self
     }
 
-    /// The maximum number of digits past the decimal point to include in the string
-    pub const fn max_decimal_digits(mut self, val: i8) -> Self {
+    /// Set both ``min_significant_digits`` and ``max_significant_digits``
+    /// (example: 2.0 -> 2.0 and 3.14159 -> 3.142 with ``significant_digits(2..=4)``)
+    pub fn significant_digits(self, range: RangeInclusive<u8>) -> Self {
+        self.min_significant_digits(*range.start())
+            .max_significant_digits(*range.end())
+    }
+
+    /// Pad zeros past the decimal point of whole numbers to reach
+    /// ``min_significant_digits``. If false, whole numbers keep only their
+    /// integer digits, but other values are still padded
+    /// (example: 340.0 -> 340 instead of 340.00 with ``min_significant_digits(5)``
+    /// and ``add_point_zero(false)``)
+    /// (default: true)
+    pub const fn min_sig_digits_in_fraction(mut self, val: bool) -> Self {
+        self.min_sig_digits_in_fraction = val;
+        self
+    }
+
+    /// Never remove digits left of the decimal point when cutting off
+    /// after ``max_significant_digits``, unless using scientific notation.
+    /// (example: 12345.6 -> 12346 with ``max_significant_digits(3)``)
+    /// (default: false)
+    pub const fn keep_integer_digits(mut self, val: bool) -> Self {
+        self.keep_integer_digits = val;
+        self
+    }
+
+    /// The maximum number of digits past the decimal point to include in the string.
+    /// Negative values remove digits left of the decimal point
+    /// (example: 123123 -> 123000 with ``max_decimal_digits(-3)``)
+    pub const fn max_decimal_digits(mut self, val: i16) -> Self {
         self.max_decimal_digits = Some(val);
         self
     }
 
-    /// The minimum number of digits past the decimal point to include in the string
-    pub const fn min_decimal_digits(mut self, val: i8) -> Self {
+    /// The minimum number of digits past the decimal point to include in the string.
+    /// In scientific notation, this is the number of digits past the mantissa's
+    /// decimal point
+    /// (example: 1500 -> 1.5000e3 with ``min_decimal_digits(4)``)
+    pub const fn min_decimal_digits(mut self, val: i16) -> Self {
         self.min_decimal_digits = Some(val);
         self
     }
 
-    /// The upper exponent value that will force using exponent notation
+    /// Set both ``min_decimal_digits`` and ``max_decimal_digits``
+    /// (example: 2.5 -> 2.50 and 1.23456 -> 1.235 with ``decimal_digits(2..=3)``)
+    pub fn decimal_digits(self, range: RangeInclusive<i16>) -> Self {
+        self.min_decimal_digits(*range.start())
+            .max_decimal_digits(*range.end())
+    }
+
+    /// The minimum number of digits before the decimal point to include in the
+    /// string, padding with leading zeros after the sign, like ``%03.1f`` in C.
+    /// This has no effect in scientific notation. Padding zeros are grouped by
+    /// ``group_separator`` like any other digit, and count towards ``max_width``.
+    /// (example: -5.2 -> -005.2 with ``min_integer_digits(3)``)
+    pub const fn min_integer_digits(mut self, val: u8) -> Self {
+        self.min_integer_digits = Some(val);
+        self
+    }
+
+    /// When using scientific notation, apply ``max_decimal_digits`` to the
+    /// digits after the decimal point of the mantissa, instead of the digits
+    /// after the decimal point of the full value.
+    /// (example: 12345 -> 1.23e4 with ``max_decimal_digits(2)``)
+    /// (default: false)
+    pub const fn decimal_digits_in_e_notation(mut self, val: bool) -> Self {
+        self.decimal_digits_in_e_notation = val;
+        self
+    }
+
+    /// The smallest exponent that will force using exponent notation, where
+    /// the exponent is the one the value has in scientific notation. So
+    /// values with more than this many digits left of the decimal point
+    /// use exponent notation. The exponent is checked after rounding.
+    /// (example: 1234.0 -> 1234.0 and 12345.0 -> 1.2345e4)
     /// (default: 4)
     pub const fn upper_e_break(mut self, val: i8) -> Self {
         self.upper_e_break = val;
         self
     }
 
-    /// The lower exponent value that will force using exponent notation
+    /// Exponents less than this (but not equal to it) will force using
+    /// exponent notation, where the exponent is the one the value has in
+    /// scientific notation. The exponent is checked after rounding.
+    /// (example: 0.0001 -> 0.0001 and 0.00001 -> 1.0e-5)
     /// (default: -4)
     pub const fn lower_e_break(mut self, val: i8) -> Self {
         self.lower_e_break = val;
@@ -196,7 +722,9 @@ impl FmtFloatConfig {
     /// Ignore digits after and including a certain number of
     /// consecutive 9's or 0's. This is useful for printing
     /// numbers with floating point errors to humans, even
-    /// if the numbers are technically slightly adjusted.
+    /// if the numbers are technically slightly adjusted. When
+    /// digits are ignored, ``min_significant_digits`` doesn't pad
+    /// them back with zeros.
     /// (example: 3.5999951 -> 3.6)
     pub const fn ignore_extremes(mut self, limit: u8) -> Self {
         self.ignore_extremes = Some(limit);
@@ -221,8 +749,53 @@ impl FmtFloatConfig {
         self
     }
 
-    /// Force all floats to be in scientific notation.
-    /// (example: 31 -> 3.1e1)
+    /// Round like ``round(self)``, but round values exactly halfway
+    /// between two results to the one with an even last digit. Ties are
+    /// found using the exact value of the float.
+    /// (example: 2.5 -> 2 and 3.5 -> 4 with ``max_significant_digits(1)``)
+    pub const fn round_half_even(mut self) -> Self {
+        self.round_mode = RoundMode::HalfEven;
+        self
+    }
+
+    /// Round like ``round(self)``, but round values exactly halfway
+    /// between two results down. Ties are found using the exact value of
+    /// the float.
+    /// (example: 2.5 -> 2 and 2.51 -> 3 with ``max_significant_digits(1)``)
+    pub const fn round_half_down(mut self) -> Self {
+        self.round_mode = RoundMode::HalfDown;
+        self
+    }
+
+    /// When cutting off after a certain number of
+    /// significant digits / decimal digits, call a
+    /// function to decide whether to round up. It's
+    /// given the digits (in ascii) before any are
+    /// removed, and the index of the first digit that's
+    /// removed.
+    /// (example: ``|digits, cut| digits[cut] >= b'5'`` is the same as ``round()``)
+    pub const fn round_with(mut self, round_up: fn(&[u8], usize) -> bool) -> Self {
+        self.round_mode = RoundMode::Custom(round_up);
+        self
+    }
+
+    /// When rounding, decide ties in the digits (where the removed digits are
+    /// exactly a 5 followed by zeros) using the exact value of the float,
+    /// instead of rounding them up. The shortest digits of a float are rarely
+    /// its exact value, so this rounds to the closer result. Rounding after
+    /// the shortest digits aren't a tie is already exact. Only affects
+    /// ``round()``, and ``max_width`` always breaks ties this way.
+    /// (example: 2.675 (slightly below 2.675 as a float) -> 2.67 with
+    /// ``max_decimal_digits(2)``, instead of 2.68)
+    /// (default: false)
+    pub const fn precise_tie_detection(mut self, val: bool) -> Self {
+        self.precise_tie_detection = val;
+        self
+    }
+
+    /// Force all floats to be in scientific notation. Zero is written
+    /// with an exponent of 0.
+    /// (example: 31 -> 3.1e1 and 0 -> 0.0e0)
     pub const fn force_e_notation(mut self) -> Self {
         self.force_e_notation = true;
         self.force_no_e_notation = false;
@@ -237,19 +810,248 @@ impl FmtFloatConfig {
         self
     }
 
-    /// Capitalize the e in e notation.
+    /// Use whichever of scientific notation or regular notation gives the
+    /// shorter string, ignoring ``upper_e_break`` and ``lower_e_break``. Ties
+    /// use regular notation. This has no effect when scientific notation
+    /// is forced on or off.
+    /// (example: 12345 -> 12345.0 and 1000 -> 1.0e3)
+    /// (default: false)
+    pub const fn prefer_shortest_notation(mut self, val: bool) -> Self {
+        self.prefer_shortest_notation = val;
+        self
+    }
+
+    /// Use scientific notation only when it gives a string at least
+    /// ``chars`` characters shorter than regular notation, ignoring
+    /// ``upper_e_break`` and ``lower_e_break``. This overrides
+    /// ``prefer_shortest_notation``, and has no effect when scientific
+    /// notation is forced on or off.
+    /// (example: with 3, 100000 -> 1.0e5 but 12345 -> 12345.0)
+    /// (default: None)
+    pub const fn e_notation_if_shorter_by(mut self, chars: u8) -> Self {
+        self.e_notation_if_shorter_by = Some(chars);
+        self
+    }
+
+    /// Go back to choosing the notation using ``upper_e_break`` and
+    /// ``lower_e_break`` (or ``prefer_shortest_notation``)
+    pub const fn no_e_notation_if_shorter_by(mut self) -> Self {
+        self.e_notation_if_shorter_by = None;
+        self
+    }
+
+    /// Leave out the exponent in scientific notation when it's 0. The
+    /// mantissa is then written like a value in regular notation.
+    /// (example: 1.5e0 -> 1.5)
+    /// (default: false)
+    pub const fn suppress_zero_exponent(mut self, val: bool) -> Self {
+        self.suppress_zero_exponent = val;
+        self
+    }
+
+    /// In scientific notation, write the value with an SI prefix (like
+    /// "k" or "µ") instead of an exponent when it's between 1e-24 and 1e27.
+    /// The exponent is rounded down to a multiple of 3, and the rest of the
+    /// magnitude is moved into the mantissa. Exponents of 0 have no prefix.
+    /// The prefix comes before the truncation marker and unit.
+    /// (example: 1.5e3 -> 1.5k, 1.5e-5 -> 15.0µ, and 1.5e30 -> 1.5e30)
+    /// (default: false)
+    pub const fn si_prefixes(mut self, val: bool) -> Self {
+        self.si_prefixes = val;
+        self
+    }
+
+    /// Limit the magnitude of exponents in scientific notation. When the
+    /// exponent would be larger, the extra magnitude is moved into the
+    /// mantissa, which is written with extra digits before or after the
+    /// radix point.
+    /// (example: with 2, 1.5e3 -> 15.0e2 and 1.5e-3 -> 0.15e-2)
+    /// (default: None)
+    pub const fn max_exponent_magnitude(mut self, val: u8) -> Self {
+        self.max_exponent_magnitude = Some(val);
+        self
+    }
+
+    /// Don't limit the magnitude of exponents in scientific notation
+    pub const fn no_max_exponent_magnitude(mut self) -> Self {
+        self.max_exponent_magnitude = None;
+        self
+    }
+
+    /// The maximum number of significant digits in the mantissa when using
+    /// scientific notation, using the rounding mode. Values written without
+    /// scientific notation aren't affected, and this overrides the minimums
+    /// for the mantissa. A value of 0 is treated as 1.
+    /// (example: 1.23456e3 -> 1.23e3 and 1234.56 -> 1234.56 with 3)
+    /// (default: None)
+    pub const fn max_mantissa_digits(mut self, val: u8) -> Self {
+        self.max_mantissa_digits = Some(val);
+        self
+    }
+
+    /// Don't limit the digits of mantissas in scientific notation
+    pub const fn no_max_mantissa_digits(mut self) -> Self {
+        self.max_mantissa_digits = None;
+        self
+    }
+
+    /// The number of characters in the mantissa when using scientific
+    /// notation, counting its digits and radix point but not the sign. The
+    /// mantissa is rounded using the rounding mode, or padded with 0's, to
+    /// fill the width, so mantissas line up in a column. This overrides the
+    /// other digit limits for the mantissa. A mantissa is always at least
+    /// one digit.
+    /// (example: 1200.0 -> 1.2000e3 and 3.14159 -> 3.1416e0 with 6)
+    /// (default: None)
+    pub const fn mantissa_width(mut self, val: u8) -> Self {
+        self.mantissa_width = Some(val);
+        self
+    }
+
+    /// Don't fix the width of mantissas in scientific notation
+    pub const fn no_mantissa_width(mut self) -> Self {
+        self.mantissa_width = None;
+        self
+    }
+
+    /// Pad the mantissa with spaces before the exponent in scientific
+    /// notation, so the mantissa and its sign take up at least this many
+    /// characters and the exponents of left-aligned values line up. Unlike
+    /// ``mantissa_width``, the digits aren't changed. The padding counts
+    /// towards ``max_width``.
+    /// (example: 1.5e3 -> "1.5  e3" and -12.25e3 -> "-1.225e4" with 5)
+    /// (default: None)
+    pub const fn mantissa_field_width(mut self, val: u8) -> Self {
+        self.mantissa_field_width = Some(val);
+        self
+    }
+
+    /// Don't pad mantissas in scientific notation
+    pub const fn no_mantissa_field_width(mut self) -> Self {
+        self.mantissa_field_width = None;
+        self
+    }
+
+    /// The number of characters in the whole value when using scientific
+    /// notation, counting the sign, mantissa, and exponent (but not units or
+    /// markers after them), so values line up in a column whatever the
+    /// lengths of their mantissas and exponents. The mantissa is given
+    /// whatever width the sign and exponent leave, and is rounded or padded
+    /// with 0's to fill it, like with ``mantissa_width``, which this
+    /// overrides. When the mantissa can't fill its width exactly (like a
+    /// single digit mantissa without a radix point, one short of 2
+    /// characters), the exponent is padded with leading 0's instead. Values
+    /// whose sign, exponent and single digit don't fit are written longer.
+    /// (example: 1500.0 -> 1.5000e3 and -1.5e-7 -> -1.50e-7 with 8)
+    /// (default: None)
+    pub const fn scientific_total_width(mut self, val: u8) -> Self {
+        self.scientific_total_width = Some(val);
+        self
+    }
+
+    /// Don't fix the total width of values in scientific notation
+    pub const fn no_scientific_total_width(mut self) -> Self {
+        self.scientific_total_width = None;
+        self
+    }
+
+    /// Write every digit as an integer mantissa without a radix point,
+    /// followed by the exponent, so the value is exactly the mantissa times
+    /// 10 to the exponent. This overrides the other notation options, and
+    /// ``max_width`` doesn't remove digits, but the exponent is still written
+    /// with ``capitalize_e`` or ``exponent_formatter``.
+    /// (example: 3.14 -> 314e-2 and 1500.0 -> 15e2)
+    /// (default: false)
+    pub const fn integer_mantissa(mut self, val: bool) -> Self {
+        self.integer_mantissa = val;
+        self
+    }
+
+    /// Capitalize the e in e notation. The same as ``exp_case(ExpCase::Upper)``
+    /// if true, and ``exp_case(ExpCase::Lower)`` if false.
     /// (example: 3.1e10 -> 3.1E10)
     /// (default: false)
     pub const fn capitalize_e(mut self, val: bool) -> Self {
-        self.capitalize_e = val;
+        self.exp_case = if val { ExpCase::Upper } else { ExpCase::Lower };
+        self
+    }
+
+    /// Whether to write the e in e notation as "e" or "E", which can
+    /// depend on the sign of the exponent.
+    /// (example: 3.1e10 -> 3.1e10 and 3.1e-10 -> 3.1E-10 with
+    /// ``ExpCase::LowerForPositive``)
+    /// (default: ``ExpCase::Lower``)
+    pub const fn exp_case(mut self, val: ExpCase) -> Self {
+        self.exp_case = val;
+        self
+    }
+
+    /// Use a custom function to write the exponent marker and the exponent
+    /// in scientific notation, given the exponent. This overrides
+    /// ``exp_case``. Since the output of the function can be any length,
+    /// ``max_width`` is not guaranteed to find a representation that fits
+    /// when this is used.
+    /// (example: 1.5e3 -> 1.5x10^3 with ``|e| format!("x10^{}", e)``)
+    /// (default: None)
+    pub const fn exponent_formatter(mut self, formatter: fn(i32) -> String) -> Self {
+        self.exponent_formatter = Some(formatter);
+        self
+    }
+
+    /// Write the exponent in scientific notation using words, which
+    /// is friendlier to screen readers. The words come from
+    /// ``spoken_exponent_template``. This overrides ``exponent_formatter``
+    /// and ``exp_case``, and ``max_width`` is ignored when this is set.
+    /// (example: 1.5e3 -> 1.5 times ten to the 3)
+    /// (default: false)
+    pub const fn spoken_exponent(mut self, val: bool) -> Self {
+        self.spoken_exponent = val;
+        self
+    }
+
+    /// The words used for the exponent when ``spoken_exponent`` is set,
+    /// where ``"{}"`` is replaced with the exponent.
+    /// (default: ``" times ten to the {}"``)
+    pub const fn spoken_exponent_template(mut self, val: &'static str) -> Self {
+        self.spoken_exponent_template = val;
         self
     }
 
-    /// Add a ".0" at the end of integers.
+    /// Add a ".0" at the end of integers. The same as
+    /// ``trailing_point(TrailingPoint::Always)`` if true, and
+    /// ``trailing_point(TrailingPoint::MantissasOnly)`` if false, which still
+    /// writes the ".0" after single digit mantissas unless ``simplify`` is set.
     /// (example: 31 -> 31.0)
     /// (default: true)
     pub const fn add_point_zero(mut self, val: bool) -> Self {
-        self.add_point_zero = val;
+        self.trailing_point = if val {
+            TrailingPoint::Always
+        } else {
+            TrailingPoint::MantissasOnly
+        };
+        self
+    }
+
+    /// When to add a ".0" at the end of integers, and single digit mantissas
+    /// in scientific notation. When a ``max_width`` leaves no room for the
+    /// ".0" after a mantissa, it's left out along with the radix point, the
+    /// same as with ``TrailingPoint::Never``.
+    /// (example: 31 -> 31 and 3e10 -> 3e10 with ``TrailingPoint::Never``)
+    /// (default: ``TrailingPoint::Always``)
+    pub const fn trailing_point(mut self, val: TrailingPoint) -> Self {
+        self.trailing_point = val;
+        self
+    }
+
+    /// After all other digit limits are applied, remove trailing zeros
+    /// in the fractional part that aren't required by ``min_significant_digits``
+    /// or ``min_decimal_digits``. With ``TrailingPoint::MantissasOnly`` (which
+    /// ``add_point_zero(false)`` sets), this also removes the ".0" from single
+    /// digit mantissas in scientific notation.
+    /// (example: 3.0001 -> 3.0 with ``max_significant_digits(3)``)
+    /// (default: false)
+    pub const fn simplify(mut self, val: bool) -> Self {
+        self.simplify = val;
         self
     }
 
@@ -257,7 +1059,10 @@ impl FmtFloatConfig {
     /// should be greater than or equal to 7 to guarantee all floats
     /// will print correctly, but can be smaller for certain floats.
     /// Floats that are impossible to represent in a certain width will
-    /// be represented by pound signs.
+    /// be represented by pound signs. The width always wins over
+    /// ``min_significant_digits`` and ``min_decimal_digits``, which only pad
+    /// as many zeros as fit.
+    /// (example: 1.5 -> 1.500 with ``min_decimal_digits(5)`` and ``max_width(5)``)
     pub const fn max_width(mut self, val: u8) -> Self {
         self.max_width = Some(val);
         self
@@ -269,6 +1074,117 @@ impl FmtFloatConfig {
         self
     }
 
+    /// The maximum number of bytes in the UTF-8 encoding of the string, for
+    /// writing into fixed size buffers. This differs from ``max_width`` when
+    /// the radix point, separators, or other parts of the string are not
+    /// ASCII. The string is narrowed the same way as for ``max_width`` until
+    /// it fits, and floats that can't fit are represented by pound signs.
+    /// (example: 1.25 -> 1·3 with ``radix_point('·')`` and ``max_byte_width(4)``)
+    pub const fn max_byte_width(mut self, val: u16) -> Self {
+        self.max_byte_width = Some(val);
+        self
+    }
+
+    /// Allows any number of bytes in strings. This is set by default
+    pub const fn no_max_byte_width(mut self) -> Self {
+        self.max_byte_width = None;
+        self
+    }
+
+    /// Add a character at the end of the string when precision was lost,
+    /// meaning the string doesn't have every digit of the shortest
+    /// round-trip representation. This counts towards ``max_width``, and
+    /// room is always left for it.
+    /// (example: with '…' and ``max_significant_digits(3)``, 3.14159 -> 3.14…)
+    /// (default: None)
+    pub const fn truncation_marker(mut self, val: char) -> Self {
+        self.truncation_marker = Some(val);
+        self
+    }
+
+    /// Don't mark strings that lost precision. This is set by default
+    pub const fn no_truncation_marker(mut self) -> Self {
+        self.truncation_marker = None;
+        self
+    }
+
+    /// Add a character after the sign when precision was lost, like
+    /// ``truncation_marker``. With a template, it goes right before the
+    /// digits. This counts towards ``max_width``, and room is always left
+    /// for it.
+    /// (example: with '≈' and ``max_significant_digits(3)``, -3.14159 -> -≈3.14)
+    /// (default: None)
+    pub const fn approx_marker(mut self, val: char) -> Self {
+        self.approx_marker = Some(val);
+        self
+    }
+
+    /// Don't mark approximate strings before their digits. This is set by default
+    pub const fn no_approx_marker(mut self) -> Self {
+        self.approx_marker = None;
+        self
+    }
+
+    /// Add ``up`` at the end of the string when precision was lost and the
+    /// string shows a larger value than the float's exact value, and
+    /// ``down`` when it shows a smaller one, for auditing lossy output. This
+    /// goes after the ``truncation_marker``, counts towards ``max_width``,
+    /// and room is always left for it.
+    /// (example: with '^' and 'v' and ``max_significant_digits(3)``, 3.14159 -> 3.14v)
+    /// (default: None)
+    pub const fn rounding_markers(mut self, up: char, down: char) -> Self {
+        self.rounding_markers = Some((up, down));
+        self
+    }
+
+    /// Don't mark which way strings were rounded. This is set by default
+    pub const fn no_rounding_markers(mut self) -> Self {
+        self.rounding_markers = None;
+        self
+    }
+
+    /// Write NaNs that have their sign bit set as "-NaN"
+    /// (example: -f64::NAN -> -NaN)
+    /// (default: false)
+    pub const fn show_nan_sign(mut self, val: bool) -> Self {
+        self.show_nan_sign = val;
+        self
+    }
+
+    /// Write the payload of NaNs after "NaN", which is every bit of the
+    /// mantissa (including the quiet bit) in hex. This helps with finding
+    /// where a NaN came from. Like any other text, NaNs that don't fit
+    /// ``max_width`` are written as pound signs.
+    /// (example: f64::NAN -> NaN(0x8000000000000))
+    /// (default: false)
+    pub const fn show_nan_payload(mut self, val: bool) -> Self {
+        self.show_nan_payload = val;
+        self
+    }
+
+    /// Write a unit after finite values (but not NaN or infinities). This
+    /// counts towards ``max_width``, and room is always left for it.
+    /// (example: with "kg", 5 -> 5.0 kg)
+    /// (default: None)
+    pub const fn unit(mut self, val: &'static str) -> Self {
+        self.unit = Some(val);
+        self
+    }
+
+    /// Don't write a unit after values. This is set by default
+    pub const fn no_unit(mut self) -> Self {
+        self.unit = None;
+        self
+    }
+
+    /// Put a space between values and their unit.
+    /// (example: with "°", 90 -> 90.0 ° if true and 90 -> 90.0° if false)
+    /// (default: true)
+    pub const fn unit_space(mut self, val: bool) -> Self {
+        self.unit_space = val;
+        self
+    }
+
     /// The seperator between the integer and non-integer part
     /// of the float string
     /// (default: `'.'`)
@@ -276,615 +1192,8649 @@ impl FmtFloatConfig {
         self.radix_point = val;
         self
     }
-}
 
-const fn digit_to_u8(val: u8) -> u8 {
-    val + '0' as u8
-}
+    /// Don't write the radix point, for displays where its position is
+    /// implied by the column. The digits after it are still written, and
+    /// ``FormattedFloat::radix_index`` is None.
+    /// (example: 3.14 -> 314 and 0.05 -> 005)
+    /// (default: false)
+    pub const fn omit_radix_point(mut self, val: bool) -> Self {
+        self.omit_radix_point = val;
+        self
+    }
 
-fn digits_to_a(sign: bool, mut digits: Vec<u8>, mut e: i32, config: FmtFloatConfig) -> String {
-    // The main string formatting function. digits is a vector of the digits
-    // found using the ryu backend function. The value of the float is
-    // <- if sign>0.<digits> * 10^<e>
-    // NOTE: digits is ascii, so the digit "5" would be represented as "digit_to_u8(5)"
-    if let Some(limit) = config.max_sig_digits {
-        // Remove extra significant digits
-        let limit = limit as usize;
-        if digits.len() > limit {
-            let removed = digits.drain(limit..).next().unwrap();
-            if config.round_mode == RoundMode::Round && removed >= digit_to_u8(5) {
-                // round up
-                let mut l = digits.len() - 1;
-                digits[l] += 1;
-                while digits[l] == digit_to_u8(10) {
-                    if l == 0 {
-                        digits[0] = digit_to_u8(1);
-                        e += 1;
-                        break;
-                    }
-                    digits.pop();
-                    l -= 1;
-                    digits[l] += 1;
-                }
-            }
-        }
+    /// The seperator between groups of digits in the integer part
+    /// of the float string, counting from the radix point, when not using
+    /// scientific notation. This counts as one character towards
+    /// ``max_width``, even if the character is multiple bytes, like
+    /// ``'\u{2009}'`` (thin space).
+    /// (example: 1234567.5 -> 1,234,567.5 with ``group_separator(',')``)
+    pub const fn group_separator(mut self, val: char) -> Self {
+        self.group_separator = Some(val);
+        self
     }
-    if let Some(limit) = config.max_decimal_digits {
-        // Remove extra decimal digits
-        let adjusted_limit_position = limit as i32 + e;
-        if (0 <= adjusted_limit_position) && (adjusted_limit_position < digits.len() as i32) {
-            let final_char = digits
-                .drain(adjusted_limit_position as usize..)
-                .nth(0)
-                .unwrap();
-            if config.round_mode == RoundMode::Round && final_char >= digit_to_u8(5) {
-                // round up
-                let mut l = digits.len() - 1;
-                digits[l] += 1;
-                while digits[l] == digit_to_u8(10) {
-                    if l == 0 {
-                        digits[0] = digit_to_u8(1);
-                        e += 1;
-                        break;
-                    }
-                    digits.pop();
-                    l -= 1;
-                    digits[l] += 1;
-                }
-            }
-        }
+
+    /// Don't group digits in the integer part. This is set by default
+    pub const fn no_group_separator(mut self) -> Self {
+        self.group_separator = None;
+        self
     }
-    if let Some(limit) = config.ignore_extremes {
-        // Ignore <limit> consecutive 9's or 0's. A copy of digits is made
-        let mut stripped_string: Vec<u8> = Vec::with_capacity(30);
-        let mut nine_counter = 0;
-        let mut zero_counter = 0;
-        for digit in digits.iter() {
-            if *digit != digit_to_u8(9) {
-                nine_counter = 0;
-            } else {
-                nine_counter += 1;
-                if nine_counter >= limit {
-                    // 14999...
-                    stripped_string.drain((stripped_string.len() + 1 - nine_counter as usize)..);
-                    // -> 14
-                    let l = stripped_string.len();
-                    if l == 0 {
-                        // for strings like 999
-                        // 999e3 -> 1e4
-                        stripped_string.push(digit_to_u8(1));
-                        e += 1;
-                    } else {
-                        // Rounding doesn't have to happen here, because what was removed
-                        // was exactly limit 9's
-                        // 15
-                        stripped_string[l - 1] += 1;
-                    }
-                    break;
-                }
-            }
 
-            if *digit != digit_to_u8(0) {
-                zero_counter = 0;
-            } else {
-                zero_counter += 1;
+    /// The number of digits in each group of the integer part, for
+    /// ``group_separator``. A size of 0 turns grouping off.
+    /// (example: 1234567.5 -> 1,2345,67.5 with ``group_separator(',')`` and ``group_size(4)``)
+    /// (default: 3)
+    pub const fn group_size(mut self, val: u8) -> Self {
+        self.group_size = val;
+        self
+    }
+
+    /// The seperator between groups of digits after the radix point,
+    /// counting from the radix point, when not using scientific notation.
+    /// Zeros between the radix point and the first significant digit are
+    /// grouped too. This counts as one character towards ``max_width``,
+    /// like ``group_separator``.
+    /// (example: 0.1234567 -> 0.123 456 7 with ``fraction_group_separator(' ')``)
+    pub const fn fraction_group_separator(mut self, val: char) -> Self {
+        self.fraction_group_separator = Some(val);
+        self
+    }
+
+    /// Don't group digits after the radix point. This is set by default
+    pub const fn no_fraction_group_separator(mut self) -> Self {
+        self.fraction_group_separator = None;
+        self
+    }
+
+    /// The number of digits in each group after the radix point, for
+    /// ``fraction_group_separator``. A size of 0 turns grouping off.
+    /// (default: 3)
+    pub const fn fraction_group_size(mut self, val: u8) -> Self {
+        self.fraction_group_size = val;
+        self
+    }
+
+    /// Reserve a column for the sign at the start of finite values, so
+    /// non-negative values start with a space. The sign stays before any
+    /// zeros added by ``min_integer_digits``, and counts towards ``max_width``.
+    /// (example: 3.5 -> " 3.5" and -3.5 -> "-3.5")
+    /// (default: false)
+    pub const fn sign_column(mut self, val: bool) -> Self {
+        self.sign_column = val;
+        self
+    }
+
+    /// Write finite positive values using a template, where the first "{}"
+    /// is replaced with the value. The template replaces ``sign_column``,
+    /// and counts towards ``max_width``.
+    /// (example: with "+{}", 2.5 -> +2.5)
+    /// (default: None)
+    pub const fn positive_template(mut self, val: &'static str) -> Self {
+        self.positive_template = Some(val);
+        self
+    }
+
+    /// Don't use a template for positive values. This is set by default
+    pub const fn no_positive_template(mut self) -> Self {
+        self.positive_template = None;
+        self
+    }
+
+    /// Write finite negative values using a template, where the first "{}"
+    /// is replaced with the value without its sign. The template replaces
+    /// the sign, and counts towards ``max_width``.
+    /// (example: with "({})", -2.5 -> (2.5))
+    /// (default: None)
+    pub const fn negative_template(mut self, val: &'static str) -> Self {
+        self.negative_template = Some(val);
+        self
+    }
+
+    /// Don't use a template for negative values. This is set by default
+    pub const fn no_negative_template(mut self) -> Self {
+        self.negative_template = None;
+        self
+    }
+
+    /// Write zero (including -0.0, and values rounded to zero) using a
+    /// template, where the first "{}" is replaced with the value without
+    /// its sign. Templates without a "{}" are written as they are.
+    /// (example: with "—", 0.0 -> —)
+    /// (default: None)
+    pub const fn zero_template(mut self, val: &'static str) -> Self {
+        self.zero_template = Some(val);
+        self
+    }
+
+    /// Don't use a template for zero. This is set by default
+    pub const fn no_zero_template(mut self) -> Self {
+        self.zero_template = None;
+        self
+    }
+
+    /// Check for options that would make the output ambiguous, or that
+    /// don't do what they look like they do. Scientific notation is used
+    /// when the exponent is at least ``upper_e_break`` or less than
+    /// ``lower_e_break``, so when ``upper_e_break <= lower_e_break``, every
+    /// value uses it. That's only an error when the breaks pick the notation,
+    /// and not when it's forced or picked by length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pretty_dtoa::{ConfigError, FmtFloatConfig};
+    ///
+    /// let config = FmtFloatConfig::default()
+    ///     .radix_point(',')
+    ///     .group_separator(',');
+    ///
+    /// assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let is_ambiguous = |c: char| c.is_ascii_digit() || c == '-';
+        if is_ambiguous(self.radix_point) {
+            return Err(ConfigError::AmbiguousSeparators);
+        }
+        for separator in [self.group_separator, self.fraction_group_separator]
+            .iter()
+            .flatten()
+        {
+            if *separator == self.radix_point || is_ambiguous(*separator) {
+                return Err(ConfigError::AmbiguousSeparators);
+            }
+        }
+        let uses_breaks = !self.force_e_notation
+            && !self.force_no_e_notation
+            && !self.prefer_shortest_notation
+            && self.e_notation_if_shorter_by.is_none();
+        if uses_breaks && self.upper_e_break <= self.lower_e_break {
+            return Err(ConfigError::CrossedEBreaks);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FmtFloatConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ConfigFields::from(*self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FmtFloatConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ConfigFields::deserialize(deserializer)?
+            .into_config()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// Declare ConfigFields, the (de)serialized form of a config, which owns its
+// strings. The string fields of the config come after the ";", and the rest
+// are copied as they are. Configs are only deserialized through this, so
+// they're always validated
+#[cfg(feature = "serde")]
+macro_rules! config_fields {
+    ($($field:ident: $ty:ty,)* ; $($string:ident: $string_ty:ty,)*) => {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
+        struct ConfigFields {
+            $($field: $ty,)*
+            $($string: <$string_ty as ConfigString>::Owned,)*
+        }
+
+        impl From<FmtFloatConfig> for ConfigFields {
+            fn from(config: FmtFloatConfig) -> Self {
+                // Custom exponent formatters are functions, which can't be
+                // serialized. Every other field has to be listed
+                let FmtFloatConfig {
+                    $($field,)*
+                    $($string,)*
+                    exponent_formatter: _,
+                } = config;
+                ConfigFields {
+                    $($field,)*
+                    $($string: $string.to_owned_string(),)*
+                }
+            }
+        }
+
+        impl ConfigFields {
+            // The config with these fields, if it's valid
+            fn into_config(self) -> Result<FmtFloatConfig, ConfigError> {
+                let ConfigFields {
+                    $($field,)*
+                    $($string,)*
+                } = self;
+                let defaults = FmtFloatConfig::default();
+                let mut config = FmtFloatConfig {
+                    $($field,)*
+                    ..defaults
+                };
+                // Check the config before interning its strings, so invalid
+                // configs don't keep them
+                config.validate()?;
+                $(config.$string = ConfigString::intern($string, defaults.$string);)*
+                Ok(config)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+config_fields! {
+    max_sig_digits: Option<u8>,
+    min_sig_digits: Option<u8>,
+    min_sig_digits_in_fraction: bool,
+    keep_integer_digits: bool,
+    max_decimal_digits: Option<i16>,
+    min_decimal_digits: Option<i16>,
+    min_integer_digits: Option<u8>,
+    decimal_digits_in_e_notation: bool,
+    upper_e_break: i8,
+    lower_e_break: i8,
+    ignore_extremes: Option<u8>,
+    round_mode: RoundMode,
+    precise_tie_detection: bool,
+    force_e_notation: bool,
+    force_no_e_notation: bool,
+    prefer_shortest_notation: bool,
+    e_notation_if_shorter_by: Option<u8>,
+    suppress_zero_exponent: bool,
+    max_exponent_magnitude: Option<u8>,
+    si_prefixes: bool,
+    max_mantissa_digits: Option<u8>,
+    mantissa_width: Option<u8>,
+    mantissa_field_width: Option<u8>,
+    scientific_total_width: Option<u8>,
+    integer_mantissa: bool,
+    exp_case: ExpCase,
+    spoken_exponent: bool,
+    trailing_point: TrailingPoint,
+    simplify: bool,
+    max_width: Option<u8>,
+    max_byte_width: Option<u16>,
+    truncation_marker: Option<char>,
+    approx_marker: Option<char>,
+    rounding_markers: Option<(char, char)>,
+    show_nan_sign: bool,
+    show_nan_payload: bool,
+    unit_space: bool,
+    radix_point: char,
+    omit_radix_point: bool,
+    group_separator: Option<char>,
+    group_size: u8,
+    fraction_group_separator: Option<char>,
+    fraction_group_size: u8,
+    sign_column: bool,
+    ;
+    spoken_exponent_template: &'static str,
+    unit: Option<&'static str>,
+    positive_template: Option<&'static str>,
+    negative_template: Option<&'static str>,
+    zero_template: Option<&'static str>,
+}
+
+#[cfg(feature = "serde")]
+impl Default for ConfigFields {
+    fn default() -> Self {
+        FmtFloatConfig::default().into()
+    }
+}
+
+// The types of the string fields of configs, which are (de)serialized as
+// owned strings
+#[cfg(feature = "serde")]
+trait ConfigString: Sized {
+    type Owned;
+
+    fn to_owned_string(self) -> Self::Owned;
+
+    // The string to keep for a deserialized string, which is <default>
+    // when they're the same
+    fn intern(owned: Self::Owned, default: Self) -> Self;
+}
+
+#[cfg(feature = "serde")]
+impl ConfigString for &'static str {
+    type Owned = String;
+
+    fn to_owned_string(self) -> String {
+        self.to_string()
+    }
+
+    fn intern(owned: String, default: Self) -> Self {
+        if owned == default {
+            default
+        } else {
+            intern(owned)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ConfigString for Option<&'static str> {
+    type Owned = Option<String>;
+
+    fn to_owned_string(self) -> Option<String> {
+        self.map(str::to_string)
+    }
+
+    fn intern(owned: Option<String>, default: Self) -> Self {
+        owned.map(|owned| ConfigString::intern(owned, default.unwrap_or("")))
+    }
+}
+
+// Configs hold &'static strs, so deserialized strings have to be leaked. Each
+// distinct string is only leaked once and reused after that, so reloading
+// configs doesn't grow memory unless their strings change
+#[cfg(all(feature = "serde", any(feature = "std", test)))]
+fn intern(s: String) -> &'static str {
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut interned = INTERNED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(&found) = interned.get(s.as_str()) {
+        return found;
+    }
+    let leaked: &'static str = Box::leak(s.into_boxed_str());
+    interned.insert(leaked);
+    leaked
+}
+
+// Without std there's no Mutex, so the table is guarded by a spin lock
+#[cfg(all(feature = "serde", not(any(feature = "std", test))))]
+fn intern(s: String) -> &'static str {
+    use alloc::boxed::Box;
+    use alloc::collections::BTreeSet;
+    use core::cell::UnsafeCell;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    struct Interned {
+        locked: AtomicBool,
+        strings: UnsafeCell<BTreeSet<&'static str>>,
+    }
+    // The strings are only used while holding the lock
+    unsafe impl Sync for Interned {}
+    static INTERNED: Interned = Interned {
+        locked: AtomicBool::new(false),
+        strings: UnsafeCell::new(BTreeSet::new()),
+    };
+
+    while INTERNED
+        .locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // Safety: the lock is held until the end of this block
+    let found = unsafe {
+        let strings = &mut *INTERNED.strings.get();
+        match strings.get(s.as_str()) {
+            Some(&found) => found,
+            None => {
+                let leaked: &'static str = Box::leak(s.into_boxed_str());
+                strings.insert(leaked);
+                leaked
+            }
+        }
+    };
+    INTERNED.locked.store(false, Ordering::Release);
+    found
+}
+
+impl From<FloatFormat> for FmtFloatConfig {
+    fn from(format: FloatFormat) -> Self {
+        let config = FmtFloatConfig::default();
+        match format {
+            FloatFormat::Fixed { decimals } => config
+                .force_no_e_notation()
+                .min_decimal_digits(decimals as i16)
+                .max_decimal_digits(decimals as i16)
+                .trailing_point(TrailingPoint::Never)
+                .round(),
+            FloatFormat::Scientific { sig } => config
+                .force_e_notation()
+                .min_significant_digits(sig)
+                .max_significant_digits(sig.max(1))
+                .trailing_point(TrailingPoint::Never)
+                .round(),
+            FloatFormat::General { sig } => config
+                .upper_e_break(sig.max(1).min(i8::MAX as u8) as i8)
+                .lower_e_break(-4)
+                .max_significant_digits(sig.max(1))
+                .trailing_point(TrailingPoint::Never)
+                .round(),
+            FloatFormat::Shortest => config,
+            FloatFormat::Percent { decimals } => {
+                FmtFloatConfig::from(FloatFormat::Fixed { decimals })
+                    .unit("%")
+                    .unit_space(false)
+            }
+        }
+    }
+}
+
+const fn digit_to_u8(val: u8) -> u8 {
+    val + b'0'
+}
+
+// The digits (in ascii) of a value being formatted. They're kept in an array
+// while there are few enough to fit, so only configs that pad values with
+// many zeros make formatting allocate
+#[derive(Clone)]
+struct Digits {
+    array: [u8; Digits::INLINE],
+    array_len: usize,
+    // Every digit, once there are too many for the array
+    spilled: Option<Vec<u8>>,
+}
+
+impl Digits {
+    // As many digits as a Buffer holds
+    const INLINE: usize = Buffer::CAPACITY;
+
+    fn new() -> Self {
+        Digits {
+            array: [0; Digits::INLINE],
+            array_len: 0,
+            spilled: None,
+        }
+    }
+
+    // The digits of 0
+    fn zero() -> Self {
+        Digits::from_slice(&[digit_to_u8(0)])
+    }
+
+    fn from_slice(digits: &[u8]) -> Self {
+        let mut result = Digits::new();
+        result.extend_from_slice(digits);
+        result
+    }
+
+    fn push(&mut self, digit: u8) {
+        match &mut self.spilled {
+            Some(spilled) => spilled.push(digit),
+            None if self.array_len < Digits::INLINE => {
+                self.array[self.array_len] = digit;
+                self.array_len += 1;
+            }
+            None => {
+                let mut spilled = self.array.to_vec();
+                spilled.push(digit);
+                self.spilled = Some(spilled);
+            }
+        }
+    }
+
+    fn extend_from_slice(&mut self, digits: &[u8]) {
+        for digit in digits {
+            self.push(*digit);
+        }
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        let last = self.last().copied();
+        self.truncate(self.len().saturating_sub(1));
+        last
+    }
+
+    fn truncate(&mut self, len: usize) {
+        match &mut self.spilled {
+            Some(spilled) => spilled.truncate(len),
+            None => self.array_len = self.array_len.min(len),
+        }
+    }
+
+    // Put <count> zeros before the digits
+    fn prepend_zeros(&mut self, count: usize) {
+        let len = self.len();
+        for _ in 0..count {
+            self.push(digit_to_u8(0));
+        }
+        self.copy_within(0..len, count);
+        for digit in &mut self[..count] {
+            *digit = digit_to_u8(0);
+        }
+    }
+}
+
+impl Deref for Digits {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.spilled {
+            Some(spilled) => spilled,
+            None => &self.array[..self.array_len],
+        }
+    }
+}
+
+impl DerefMut for Digits {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.spilled {
+            Some(spilled) => spilled,
+            None => &mut self.array[..self.array_len],
+        }
+    }
+}
+
+// Text written into a borrowed byte array, for formatting without allocating.
+// Writing more than fits is an error
+struct SliceWriter<'a> {
+    bytes: &'a mut [u8],
+    len: usize,
+}
+
+impl fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+// Where formatted text is written: a String, or a Counter that only keeps the
+// length of the text, for measuring a value without building its string
+trait Sink: fmt::Write + Default {
+    // The length of the text in bytes, for recording where its parts are
+    fn byte_len(&self) -> usize;
+    // The length of the text in characters, for fitting widths
+    fn char_len(&self) -> usize;
+    // Insert <c> at the byte offset <index>
+    fn insert_char(&mut self, index: usize, c: char);
+
+    fn push(&mut self, c: char) {
+        // Neither sink can fail to write
+        let _ = self.write_char(c);
+    }
+
+    fn push_str(&mut self, s: &str) {
+        let _ = self.write_str(s);
+    }
+}
+
+impl Sink for String {
+    fn byte_len(&self) -> usize {
+        self.len()
+    }
+
+    fn char_len(&self) -> usize {
+        self.chars().count()
+    }
+
+    fn insert_char(&mut self, index: usize, c: char) {
+        self.insert(index, c);
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    bytes: usize,
+    chars: usize,
+}
+
+impl fmt::Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.bytes += s.len();
+        self.chars += s.chars().count();
+        Ok(())
+    }
+}
+
+impl Sink for Counter {
+    fn byte_len(&self) -> usize {
+        self.bytes
+    }
+
+    fn char_len(&self) -> usize {
+        self.chars
+    }
+
+    fn insert_char(&mut self, _index: usize, c: char) {
+        self.bytes += c.len_utf8();
+        self.chars += 1;
+    }
+}
+
+// The positions of the parts of formatted text, which make a FormattedFloat
+// along with the text
+struct Layout {
+    radix_index: Option<usize>,
+    exponent_index: Option<usize>,
+    exponent: Option<i32>,
+    sign_len: usize,
+    significant_digits: usize,
+    precision_lost: bool,
+}
+
+impl Layout {
+    fn with_text(self, text: String) -> FormattedFloat {
+        FormattedFloat {
+            text,
+            radix_index: self.radix_index,
+            exponent_index: self.exponent_index,
+            exponent: self.exponent,
+            sign_len: self.sign_len,
+            significant_digits: self.significant_digits,
+            precision_lost: self.precision_lost,
+        }
+    }
+}
+
+// The number of group separators in the integer part of a
+// value with <e> digits left of the decimal point
+fn group_separator_count(e: i32, config: FmtFloatConfig) -> i32 {
+    match config.group_separator {
+        Some(_) if e > 0 && config.group_size > 0 => (e - 1) / config.group_size as i32,
+        _ => 0,
+    }
+}
+
+// The number of group separators in <len> digits after the decimal point
+fn fraction_separator_count(len: i32, config: FmtFloatConfig) -> i32 {
+    match config.fraction_group_separator {
+        Some(_) if len > 0 && config.fraction_group_size > 0 => {
+            (len - 1) / config.fraction_group_size as i32
+        }
+        _ => 0,
+    }
+}
+
+// Push <digits> with <separator> between groups of <size> digits, counted
+// outwards from the radix point: from the right for the integer part, and
+// from the left for the fractional part
+fn push_grouped<S: Sink>(
+    s: &mut S,
+    digits: impl Iterator<Item = u8>,
+    len: usize,
+    separator: Option<char>,
+    size: u8,
+    integer: bool,
+) {
+    let len = len as i32;
+    for (i, digit) in digits.enumerate() {
+        let i = i as i32;
+        // The number of digits between this one and the radix point
+        let distance = if integer { len - i } else { i };
+        if let Some(separator) = separator {
+            if i > 0 && size > 0 && distance % size as i32 == 0 {
+                s.push(separator);
+            }
+        }
+        s.push(digit as char);
+    }
+}
+
+// Remove the digits from index <position> onwards, rounding up the digits that
+// are left if the config rounds. Rounding up can change <e>. If every digit is
+// removed and the value rounds down, the digits become zero. <value> is the
+// magnitude of the float being formatted, if there is one
+fn cut_digits(
+    digits: &mut Digits,
+    e: &mut i32,
+    position: usize,
+    value: Option<f64>,
+    config: FmtFloatConfig,
+) {
+    if position >= digits.len() {
+        return;
+    }
+    let round_up = match config.round_mode {
+        RoundMode::Round => {
+            let value = if config.precise_tie_detection {
+                value
+            } else {
+                None
+            };
+            dropped_remainder(digits, *e, position, value) != Ordering::Less
+        }
+        RoundMode::Truncate => false,
+        mode @ (RoundMode::HalfEven | RoundMode::HalfDown) => {
+            match dropped_remainder(digits, *e, position, value) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                // An exact tie. Cutting every digit keeps a 0, which is even
+                Ordering::Equal => {
+                    mode == RoundMode::HalfEven
+                        && position > 0
+                        && (digits[position - 1] - digit_to_u8(0)) % 2 == 1
+                }
+            }
+        }
+        RoundMode::Custom(round_up) => round_up(digits, position),
+    };
+    digits.truncate(position);
+    if round_up {
+        // round up
+        if digits.is_empty() {
+            digits.push(digit_to_u8(1));
+            *e += 1;
+            return;
+        }
+        let mut l = digits.len() - 1;
+        digits[l] += 1;
+        while digits[l] == digit_to_u8(10) {
+            if l == 0 {
+                digits[0] = digit_to_u8(1);
+                *e += 1;
+                break;
+            }
+            digits.pop();
+            l -= 1;
+            digits[l] += 1;
+        }
+    } else if digits.is_empty() {
+        digits.push(digit_to_u8(0));
+        *e = 1;
+    }
+}
+
+// Whether cutting <digits> at <position> removes exactly half of the
+// last digit kept
+fn is_tie(digits: &[u8], position: usize) -> bool {
+    position < digits.len()
+        && digits[position] == digit_to_u8(5)
+        && digits[position + 1..]
+            .iter()
+            .all(|digit| *digit == digit_to_u8(0))
+}
+
+// How the digits removed by cutting <digits> at <position> compare to half
+// of the last digit kept. Ties in the digits are broken by the exact value
+// of <value> (the magnitude of the float), if it's given
+fn dropped_remainder(digits: &[u8], e: i32, position: usize, value: Option<f64>) -> Ordering {
+    if position >= digits.len() {
+        return Ordering::Less;
+    }
+    match (digits[position].cmp(&digit_to_u8(5)), value) {
+        (Ordering::Equal, Some(value)) if is_tie(digits, position) => {
+            compare_float_to_digits(value, digits, e)
+        }
+        (Ordering::Equal, _) if !is_tie(digits, position) => Ordering::Greater,
+        (ordering, _) => ordering,
+    }
+}
+
+// How the exact value of a (non-negative, finite) float compares to
+// <digits>, with <e> digits left of the decimal point
+fn compare_float_to_digits(value: f64, digits: &[u8], e: i32) -> Ordering {
+    let (digits, e) = match digits.iter().position(|digit| *digit != digit_to_u8(0)) {
+        Some(first) => (trim_zeros(&digits[first..]), e - first as i32),
+        None => return value.partial_cmp(&0.0).unwrap_or(Ordering::Equal),
+    };
+    if value == 0.0 {
+        return Ordering::Less;
+    }
+    let mut exact = [0; EXACT_LEN];
+    let (exact_digits, exact_e) = exact_digits(value, &mut exact);
+    if exact_e != e {
+        return exact_e.cmp(&e);
+    }
+    // Both are followed by zeros
+    let digit_at = |digits: &[u8], i: usize| digits.get(i).copied().unwrap_or(digit_to_u8(0));
+    for i in 0..exact_digits.len().max(digits.len()) {
+        let (exact_digit, digit) = (digit_at(exact_digits, i), digit_at(digits, i));
+        if exact_digit != digit {
+            return exact_digit.cmp(&digit);
+        }
+    }
+    Ordering::Equal
+}
+
+// The most bytes exact_digits writes: a digit, the radix point, 800 more
+// digits, and an exponent of at most "e-324"
+const EXACT_LEN: usize = 807;
+
+// Every digit (in ascii) of the exact value of a non-zero finite float, and
+// the number of digits left of the decimal point, written into <buffer>.
+// Doubles have at most 767 significant digits, so formatting 800 of them
+// never rounds
+fn exact_digits(value: f64, buffer: &mut [u8; EXACT_LEN]) -> (&[u8], i32) {
+    let mut writer = SliceWriter {
+        bytes: buffer,
+        len: 0,
+    };
+    write!(writer, "{:.800e}", value.abs()).expect("the exact digits fit");
+    let len = writer.len;
+    let exponent_start = buffer[..len].iter().position(|c| *c == b'e').unwrap();
+    let exponent: i32 = core::str::from_utf8(&buffer[exponent_start + 1..len])
+        .unwrap()
+        .parse()
+        .unwrap();
+    // Remove the radix point after the first digit
+    buffer.copy_within(2..exponent_start, 1);
+    (trim_zeros(&buffer[..exponent_start - 1]), exponent + 1)
+}
+
+// The text of an exponent (or SI prefix). It's written into an array,
+// unless it comes from an exponent_formatter or is too long to fit
+enum Exponent {
+    Static(&'static str),
+    Array([u8; Exponent::ARRAY], usize),
+    Owned(String),
+}
+
+impl Exponent {
+    const ARRAY: usize = 64;
+
+    // Write an exponent into an array, if it fits
+    fn write(write: impl FnOnce(&mut SliceWriter) -> fmt::Result) -> Option<Exponent> {
+        let mut bytes = [0; Exponent::ARRAY];
+        let mut writer = SliceWriter {
+            bytes: &mut bytes,
+            len: 0,
+        };
+        write(&mut writer).ok()?;
+        let len = writer.len;
+        Some(Exponent::Array(bytes, len))
+    }
+}
+
+impl Deref for Exponent {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Exponent::Static(s) => s,
+            Exponent::Array(bytes, len) => {
+                core::str::from_utf8(&bytes[..*len]).expect("exponent holds whole characters")
+            }
+            Exponent::Owned(s) => s,
+        }
+    }
+}
+
+// The exponent marker and exponent for a value with <e> digits
+// left of the decimal point
+fn exponent_string(e: i32, config: FmtFloatConfig) -> Exponent {
+    if config.suppress_zero_exponent && e == 1 {
+        return Exponent::Static("");
+    }
+    if config.spoken_exponent {
+        let template = config.spoken_exponent_template;
+        let spoken = Exponent::write(|s| {
+            for (i, part) in template.split("{}").enumerate() {
+                if i > 0 {
+                    write!(s, "{}", e - 1)?;
+                }
+                s.write_str(part)?;
+            }
+            Ok(())
+        });
+        return spoken
+            .unwrap_or_else(|| Exponent::Owned(template.replace("{}", &format!("{}", e - 1))));
+    }
+    match config.exponent_formatter {
+        Some(formatter) => Exponent::Owned(formatter(e - 1)),
+        None => {
+            let exponent = e - 1;
+            let upper = match config.exp_case {
+                ExpCase::Lower => false,
+                ExpCase::Upper => true,
+                ExpCase::LowerForPositive => exponent < 0,
+                ExpCase::UpperForPositive => exponent >= 0,
+            };
+            Exponent::write(|s| write!(s, "{}{}", if upper { 'E' } else { 'e' }, exponent))
+                .expect("an exponent fits")
+        }
+    }
+}
+
+// The sign to write at the start of a value
+fn sign_str(sign: bool, config: FmtFloatConfig) -> &'static str {
+    if sign {
+        "-"
+    } else if config.sign_column {
+        " "
+    } else {
+        ""
+    }
+}
+
+// The number of characters needed to write <len> digits with <e> digits
+// left of the decimal point, without e notation
+fn fixed_width(len: usize, e: i32, sign: bool, config: FmtFloatConfig) -> i32 {
+    let len = len as i32;
+    let sign_width = sign_str(sign, config).len() as i32;
+    // Values below 1 have a single 0 left of the decimal point
+    let mut integer_digits = e.max(1);
+    if let Some(limit) = config.min_integer_digits {
+        integer_digits = integer_digits.max(limit as i32);
+    }
+    let integer_width = integer_digits + group_separator_count(integer_digits, config);
+    let radix_width = radix_point_width(config);
+    if e <= 0 {
+        // 0.<zeros><digits>
+        let fraction_len = len - e;
+        sign_width
+            + integer_width
+            + radix_width
+            + fraction_len
+            + fraction_separator_count(fraction_len, config)
+    } else if len > e {
+        let fraction_len = len - e;
+        sign_width
+            + integer_width
+            + radix_width
+            + fraction_len
+            + fraction_separator_count(fraction_len, config)
+    } else {
+        let point_zero_width = if point_zero(false, config) {
+            radix_width + 1
+        } else {
+            0
+        };
+        sign_width + integer_width + point_zero_width
+    }
+}
+
+// The number of characters in the radix point
+fn radix_point_width(config: FmtFloatConfig) -> i32 {
+    if config.omit_radix_point {
+        0
+    } else {
+        1
+    }
+}
+
+// Write the radix point, if there is one, recording where it goes
+fn push_radix_point<S: Sink>(s: &mut S, radix_index: &mut Option<usize>, config: FmtFloatConfig) {
+    if !config.omit_radix_point {
+        *radix_index = Some(s.byte_len());
+        s.push(config.radix_point);
+    }
+}
+
+// The fewest characters needed to write a value without e notation, by removing
+// every digit after the decimal point. At least one significant digit is always
+// kept, so values below 1 can't be shortened to "0.0"
+fn min_width_fixed(digits: &[u8], e: i32, sign: bool, config: FmtFloatConfig) -> i32 {
+    let mut digits = Digits::from_slice(digits);
+    let mut e = e;
+    let position = e.max(1) as usize;
+    cut_digits(&mut digits, &mut e, position, None, config);
+    fixed_width(digits.len(), e, sign, config)
+}
+
+// The fewest characters needed to write a value in e notation, which is the
+// first digit (without a radix point) and the exponent
+fn min_width_scientific(e: i32, sign: bool, config: FmtFloatConfig) -> i32 {
+    let sign_width = sign_str(sign, config).len() as i32;
+    sign_width + 1 + exponent_string(e, config).chars().count() as i32
+}
+
+// Whether a whole number (or a single digit mantissa, if <e_notation>)
+// should be written with a trailing ".0"
+fn point_zero(e_notation: bool, config: FmtFloatConfig) -> bool {
+    match config.trailing_point {
+        TrailingPoint::Always => true,
+        TrailingPoint::Never => false,
+        TrailingPoint::RoundTripMinimal => !e_notation,
+        TrailingPoint::MantissasOnly => e_notation && !config.simplify,
+    }
+}
+
+// Whether a value with <e> digits left of the decimal point should
+// be shown in e notation, ignoring max_width
+fn needs_e_notation(e: i32, config: FmtFloatConfig) -> bool {
+    // <e> is the number of digits left of the decimal point, which is one
+    // more than the exponent, so this checks exponent >= upper_e_break
+    // and exponent < lower_e_break
+    (e > config.upper_e_break as i32 || e <= config.lower_e_break as i32 || config.force_e_notation)
+        && !config.force_no_e_notation
+}
+
+fn digits_to_a<S: Sink>(
+    out: &mut S,
+    sign: bool,
+    digits: Digits,
+    e: i32,
+    value: Option<f64>,
+    config: FmtFloatConfig,
+) -> Layout {
+    // The main string formatting function. digits is a vector of the digits
+    // found using the ryu backend function. The value of the float is
+    // <- if sign>0.<digits> * 10^<e>
+    // NOTE: digits is ascii, so the digit "5" would be represented as "digit_to_u8(5)"
+    let exact = digits.clone();
+    let exact_e = e;
+    let (digits, e, config) = limit_digits(digits, e, value, config);
+    let lost = !same_value(&digits, e, &exact, exact_e);
+    // The number of characters scientific notation must save to be used,
+    // if the notation is picked by length
+    let required_savings = match config.e_notation_if_shorter_by {
+        Some(chars) => Some(chars as usize),
+        None if config.prefer_shortest_notation => Some(1),
+        None => None,
+    };
+    if let Some(required_savings) = required_savings {
+        if !config.force_e_notation && !config.force_no_e_notation {
+            let fixed_config = config.force_no_e_notation();
+            let scientific_config = config.force_e_notation();
+            // Count both lengths, and only write the shorter one
+            let mut fixed = Counter::default();
+            let mut scientific = Counter::default();
+            format_digits(
+                &mut fixed,
+                sign,
+                digits.clone(),
+                e,
+                lost,
+                value,
+                fixed_config,
+            );
+            format_digits(
+                &mut scientific,
+                sign,
+                digits.clone(),
+                e,
+                lost,
+                value,
+                scientific_config,
+            );
+            let config = if scientific.chars + required_savings <= fixed.chars {
+                scientific_config
+            } else {
+                fixed_config
+            };
+            return format_digits(out, sign, digits, e, lost, value, config);
+        }
+    }
+    format_digits(out, sign, digits, e, lost, value, config)
+}
+
+// The digits without trailing zeros
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    match digits.iter().rposition(|digit| *digit != digit_to_u8(0)) {
+        Some(last) => &digits[..=last],
+        None => &[],
+    }
+}
+
+// Whether two sets of digits (with <e> digits left of the
+// decimal point) have the same value
+fn same_value(a: &[u8], a_e: i32, b: &[u8], b_e: i32) -> bool {
+    let (a, b) = (trim_zeros(a), trim_zeros(b));
+    a == b && (a.is_empty() || a_e == b_e)
+}
+
+fn limit_digits(
+    mut digits: Digits,
+    mut e: i32,
+    value: Option<f64>,
+    mut config: FmtFloatConfig,
+) -> (Digits, i32, FmtFloatConfig) {
+    // The lossy stage of formatting: remove digits according to the config.
+    // Returns the config to use for the rest of formatting
+    if let Some(limit) = config.max_sig_digits {
+        // Remove extra significant digits. At least one digit is always kept
+        let limit = limit.max(1);
+        let limit = if config.keep_integer_digits && !needs_e_notation(e, config) {
+            (limit as i32).max(e) as usize
+        } else {
+            limit as usize
+        };
+        cut_digits(&mut digits, &mut e, limit, value, config);
+    }
+    if let Some(limit) = config.max_decimal_digits {
+        // Remove extra decimal digits
+        let adjusted_limit_position =
+            if config.decimal_digits_in_e_notation && needs_e_notation(e, config) {
+                // The decimal digits are the ones after the mantissa's first digit
+                limit.max(0) as i32 + 1
+            } else {
+                limit as i32 + e
+            };
+        if adjusted_limit_position < 0 {
+            if let RoundMode::Custom(_) = config.round_mode {
+                // The cut is left of the first digit, so pad leading zeros
+                // up to it for the rounding function to see
+                let padding = -adjusted_limit_position as usize;
+                digits.prepend_zeros(padding);
+                e += padding as i32;
+            } else {
+                // A 0 would be the first digit removed, so every other
+                // mode rounds down to zero
+                digits = Digits::zero();
+                e = 1;
+            }
+        }
+        cut_digits(
+            &mut digits,
+            &mut e,
+            adjusted_limit_position.max(0) as usize,
+            value,
+            config,
+        );
+    }
+    if let Some(limit) = config.ignore_extremes {
+        // Ignore <limit> consecutive 9's or 0's. A copy of digits is made
+        let mut stripped_string = Digits::new();
+        let mut nine_counter = 0;
+        let mut zero_counter = 0;
+        for digit in digits.iter() {
+            if *digit != digit_to_u8(9) {
+                nine_counter = 0;
+            } else {
+                nine_counter += 1;
+                if nine_counter >= limit {
+                    // 14999...
+                    stripped_string.truncate(stripped_string.len() + 1 - nine_counter as usize);
+                    // -> 14
+                    let l = stripped_string.len();
+                    if l == 0 {
+                        // for strings like 999
+                        // 999e3 -> 1e4
+                        stripped_string.push(digit_to_u8(1));
+                        e += 1;
+                    } else {
+                        // Rounding doesn't have to happen here, because what was removed
+                        // was exactly limit 9's
+                        // 15
+                        stripped_string[l - 1] += 1;
+                    }
+                    break;
+                }
+            }
+
+            if *digit != digit_to_u8(0) {
+                zero_counter = 0;
+            } else {
+                zero_counter += 1;
                 if zero_counter >= limit {
                     // 14000...
-                    stripped_string.drain((stripped_string.len() + 1 - zero_counter as usize)..);
+                    stripped_string.truncate(stripped_string.len() + 1 - zero_counter as usize);
                     // 14
+                    if stripped_string.is_empty() {
+                        // for zero itself, which has no digits left
+                        stripped_string.push(digit_to_u8(0));
+                        e = 1;
+                    }
+                    break;
+                }
+            }
+            stripped_string.push(*digit);
+        }
+        // Don't let min_sig_digits pad back the digits that were ignored,
+        // unless all that was ignored were trailing zeros
+        let only_zeros_ignored = digits.starts_with(&stripped_string)
+            && digits[stripped_string.len()..]
+                .iter()
+                .all(|digit| *digit == digit_to_u8(0));
+        if !only_zeros_ignored {
+            config.min_sig_digits = None;
+        }
+        digits = stripped_string;
+    }
+    (digits, e, config)
+}
+
+// The template for a value with the given sign and digits, if there is one
+fn template_for(sign: bool, digits: &[u8], config: FmtFloatConfig) -> Option<&'static str> {
+    if digits.iter().all(|digit| *digit == digit_to_u8(0)) && config.zero_template.is_some() {
+        config.zero_template
+    } else if sign {
+        config.negative_template
+    } else {
+        config.positive_template
+    }
+}
+
+// The text before and after the "{}" in a template, if it has one
+fn template_parts(template: &str) -> Option<(&str, &str)> {
+    let index = template.find("{}")?;
+    Some((&template[..index], &template[index + 2..]))
+}
+
+// Take the text around the "{}" of a template out of max_width
+fn leave_template_room(config: &mut FmtFloatConfig, before: &str, after: &str) {
+    if let Some(max_width) = config.max_width {
+        let template_width = before.chars().count() + after.chars().count();
+        config.max_width = Some(max_width.saturating_sub(template_width.min(255) as u8));
+    }
+}
+
+// Positions in the returned layouts are byte offsets into <out>, which is
+// empty to begin with everywhere except the nested mantissas of
+// format_magnitude
+fn format_digits<S: Sink>(
+    out: &mut S,
+    sign: bool,
+    digits: Digits,
+    e: i32,
+    lost: bool,
+    value: Option<f64>,
+    config: FmtFloatConfig,
+) -> Layout {
+    // Write the value, and then put the approximation marker after the
+    // sign if precision was lost, leaving room for it either way
+    let marker = match config.approx_marker {
+        Some(marker) => marker,
+        None => return format_templated(out, sign, digits, e, lost, value, config),
+    };
+    let mut config = config;
+    if let Some(max_width) = config.max_width {
+        config.max_width = Some(max_width.saturating_sub(1));
+    }
+    let start = out.byte_len();
+    let mut layout = format_templated(out, sign, digits, e, lost, value, config);
+    if layout.precision_lost {
+        let shift = marker.len_utf8();
+        out.insert_char(start + layout.sign_len, marker);
+        layout.radix_index = layout.radix_index.map(|index| index + shift);
+        layout.exponent_index = layout.exponent_index.map(|index| index + shift);
+    }
+    layout
+}
+
+fn format_templated<S: Sink>(
+    out: &mut S,
+    sign: bool,
+    digits: Digits,
+    e: i32,
+    lost: bool,
+    value: Option<f64>,
+    config: FmtFloatConfig,
+) -> Layout {
+    // Write the value in its template, which replaces the sign
+    let template = match template_for(sign, &digits, config) {
+        Some(template) => template,
+        None => return format_magnitude(out, sign, digits, e, lost, value, config),
+    };
+    let (before, after) = match template_parts(template) {
+        Some(parts) => parts,
+        None => {
+            out.push_str(template);
+            return Layout {
+                radix_index: None,
+                exponent_index: None,
+                exponent: None,
+                sign_len: 0,
+                significant_digits: 0,
+                precision_lost: lost,
+            };
+        }
+    };
+    let mut config = config.sign_column(false);
+    leave_template_room(&mut config, before, after);
+    out.push_str(before);
+    let layout = format_magnitude(out, false, digits, e, lost, value, config);
+    out.push_str(after);
+    Layout {
+        sign_len: before.len(),
+        ..layout
+    }
+}
+
+fn format_magnitude<S: Sink>(
+    out: &mut S,
+    sign: bool,
+    mut digits: Digits,
+    mut e: i32,
+    mut lost: bool,
+    value: Option<f64>,
+    config: FmtFloatConfig,
+) -> Layout {
+    // The final stage of formatting: pad digits, and then write them
+    // out using the config. <lost> is whether digits of the exact value
+    // were already removed, and <value> is the magnitude of the float
+    // being formatted, if there is one
+    let start = out.char_len();
+    let mut config = if config.spoken_exponent {
+        config.no_max_width()
+    } else {
+        config
+    };
+    if let Some(max_width) = config.max_width {
+        // Leave room for the truncation marker and unit
+        let suffix_width = suffix_width(config).min(max_width as usize) as u8;
+        config.max_width = Some(max_width - suffix_width);
+    }
+    if let Some(mut limit) = config.min_sig_digits {
+        if !config.min_sig_digits_in_fraction && digits.len() as i32 <= e {
+            // Whole numbers only get padded up to the decimal point
+            limit = limit.min(e.min(u8::MAX as i32) as u8);
+        }
+        // Pad 0's to get enough significant digits
+        let mut curr = digits.len() as u8;
+        while curr < limit {
+            digits.push(digit_to_u8(0));
+            curr += 1;
+        }
+    }
+    // The digits before padding for min_decimal_digits, which is redone
+    // for the mantissa in e notation
+    let unpadded_len = digits.len();
+    let redone = needs_e_notation(e, config) && !config.integer_mantissa;
+    if let (Some(limit), false) = (config.min_decimal_digits, redone) {
+        // Pad 0's to get enough decimal digits
+        let adjusted_limit_position = limit as i32 + e;
+        while (digits.len() as i32) < adjusted_limit_position {
+            digits.push(digit_to_u8(0));
+        }
+    }
+    if config.integer_mantissa {
+        // <digits><exponent>, where the exponent moves the decimal point
+        // past every digit
+        out.push_str(sign_str(sign, config));
+        for digit in digits.iter() {
+            out.push(*digit as char);
+        }
+        let exponent = e - digits.len() as i32;
+        let exponent_str = exponent_string(exponent + 1, config);
+        let exponent_index = if exponent_str.is_empty() {
+            None
+        } else {
+            Some(out.byte_len())
+        };
+        out.push_str(&exponent_str);
+        let rounded = rounding(sign, &digits, e, lost, value, config);
+        push_suffix(out, lost, rounded, config);
+        return Layout {
+            radix_index: None,
+            exponent_index,
+            exponent: exponent_index.map(|_| exponent),
+            sign_len: sign_str(sign, config).len(),
+            significant_digits: significant_digit_count(&digits),
+            precision_lost: lost,
+        };
+    }
+    let mut use_e_notation = needs_e_notation(e, config);
+    if let Some(max_width) = config.max_width {
+        let max_width = max_width as i32;
+        // Is it impossible to represent the value without e notation?
+        if !use_e_notation
+            && fixed_width(digits.len(), e, sign, config) > max_width
+            && min_width_fixed(&digits, e, sign, config) > max_width
+        {
+            if e > 0 {
+                hit!(e_width_case_a);
+            } else {
+                hit!(e_width_case_b);
+            }
+            use_e_notation = true;
+        } else if !use_e_notation {
+            hit!(e_width_case_c);
+            // Otherwise, remove as few digits as possible to fit. Rounding
+            // up can add a digit, so keep going until the rounded digits fit
+            let mut start = digits.len();
+            while start > 0 && fixed_width(start, e, sign, config) > max_width {
+                start -= 1;
+            }
+            for len in (0..=start).rev() {
+                let mut cut = digits.clone();
+                let mut cut_e = e;
+                // Ties in the digits are rarely ties in the float, so round
+                // towards whichever side the float is on
+                let cut_config = config.precise_tie_detection(true);
+                cut_digits(&mut cut, &mut cut_e, len, value, cut_config);
+                let kept = trim_zeros(&cut).len().max(1);
+                cut.truncate(kept);
+                if needs_e_notation(cut_e, config) {
+                    // Rounding carried past upper_e_break, so the rounded
+                    // value is written in e notation
+                    lost |= !same_value(&cut, cut_e, &digits, e);
+                    digits = cut;
+                    e = cut_e;
+                    use_e_notation = true;
+                    break;
+                }
+                if fixed_width(cut.len(), cut_e, sign, config) <= max_width {
+                    lost |= !same_value(&cut, cut_e, &digits, e);
+                    digits = cut;
+                    e = cut_e;
+                    break;
+                }
+            }
+            // The minimums are best effort: put back as many of the
+            // padding zeros as still fit
+            let mut target = config.min_sig_digits.unwrap_or(0) as i32;
+            if let Some(limit) = config.min_decimal_digits {
+                target = target.max(limit as i32 + e);
+            }
+            while (digits.len() as i32) < target
+                && fixed_width(digits.len() + 1, e, sign, config) <= max_width
+            {
+                digits.push(digit_to_u8(0));
+            }
+        }
+    }
+    if config.simplify {
+        // Remove trailing zeros that aren't needed by a minimum
+        let mut keep = 1;
+        if let Some(limit) = config.min_sig_digits {
+            keep = keep.max(limit as i32);
+        }
+        if let Some(limit) = config.min_decimal_digits {
+            keep = keep.max(limit as i32 + e);
+        }
+        while digits.len() as i32 > keep && digits.last() == Some(&digit_to_u8(0)) {
+            digits.pop();
+        }
+    }
+    // Final formatting stage
+    if use_e_notation {
+        if let Some(limit) = config.min_decimal_digits {
+            // Pad 0's to get enough decimal digits in the mantissa, which
+            // has one digit left of the decimal point, unless the exponent
+            // is an SI prefix or capped
+            let mantissa_e = mantissa_e(e, config);
+            digits.truncate(unpadded_len);
+            while (digits.len() as i32) < limit as i32 + mantissa_e {
+                digits.push(digit_to_u8(0));
+            }
+        }
+        if let Some(limit) = config.max_mantissa_digits {
+            // Rounding can carry into a new digit, changing the exponent
+            let mut cut = digits.clone();
+            let mut cut_e = e;
+            cut_digits(&mut cut, &mut cut_e, limit.max(1) as usize, value, config);
+            lost |= !same_value(&cut, cut_e, &digits, e);
+            digits = cut;
+            e = cut_e;
+        }
+        // The width of the mantissa for a value with <e> digits left of
+        // the decimal point, if it's fixed
+        let mantissa_width = |e| match config.scientific_total_width {
+            Some(total) => Some(
+                total as i32
+                    - sign_str(sign, config).len() as i32
+                    - written_exponent(e, config).chars().count() as i32,
+            ),
+            None => config.mantissa_width.map(i32::from),
+        };
+        if let Some(width) = mantissa_width(e) {
+            // Find the most digits that fit in the width, and then round
+            // or pad to that many. The sign isn't part of the mantissa
+            let sizing = mantissa_config(config).sign_column(false);
+            let fit_len = |width| {
+                let mantissa_e = mantissa_e(e, config);
+                let mut len = 1;
+                while fixed_width(len + 1, mantissa_e, false, sizing) <= width {
+                    len += 1;
+                }
+                len
+            };
+            let fill = |len| {
+                let mut cut = digits.clone();
+                let mut cut_e = e;
+                cut_digits(&mut cut, &mut cut_e, len, value, config);
+                (cut, cut_e, len)
+            };
+            let filled = fill(fit_len(width));
+            // Rounding can carry into a new digit, changing the length of
+            // the exponent, and then it's rounded again to the new width.
+            // If that doesn't carry, the exponent would change back, so the
+            // first rounding is kept
+            let (mut cut, cut_e, len) = match mantissa_width(filled.1) {
+                Some(carried) if carried != width => {
+                    let refilled = fill(fit_len(carried));
+                    if refilled.1 == filled.1 {
+                        refilled
+                    } else {
+                        filled
+                    }
+                }
+                _ => filled,
+            };
+            lost |= !same_value(&cut, cut_e, &digits, e);
+            while cut.len() < len {
+                cut.push(digit_to_u8(0));
+            }
+            digits = cut;
+            e = cut_e;
+        }
+        if let Some(exponent) = si_exponent(e, config) {
+            let rounded = rounding(sign, &digits, e, lost, value, config);
+            // Move the magnitude the prefix doesn't cover into the mantissa
+            let mut layout = format_magnitude(
+                out,
+                sign,
+                digits,
+                e - exponent,
+                false,
+                value,
+                mantissa_config(config),
+            );
+            let prefix = SI_PREFIXES[(exponent / 3 + 8) as usize];
+            if !prefix.is_empty() {
+                pad_mantissa(out, start, config);
+                layout.exponent_index = Some(out.byte_len());
+                layout.exponent = Some(exponent);
+            }
+            out.push_str(prefix);
+            push_suffix(out, lost, rounded, config);
+            layout.precision_lost |= lost;
+            return layout;
+        }
+        if let Some(cap) = config.max_exponent_magnitude {
+            let exponent = (e - 1).max(-(cap as i32)).min(cap as i32);
+            if exponent != e - 1 {
+                let rounded = rounding(sign, &digits, e, lost, value, config);
+                // Move the extra magnitude into the mantissa
+                let mut layout = format_magnitude(
+                    out,
+                    sign,
+                    digits,
+                    e - exponent,
+                    false,
+                    value,
+                    mantissa_config(config),
+                );
+                let exponent_str = exponent_string(exponent + 1, config);
+                let mut padding = 0;
+                if !exponent_str.is_empty() {
+                    padding = exponent_padding(&exponent_str, out.char_len() - start, config);
+                    pad_mantissa(out, start, config);
+                    layout.exponent_index = Some(out.byte_len());
+                    layout.exponent = Some(exponent);
+                }
+                push_exponent(out, &exponent_str, padding);
+                push_suffix(out, lost, rounded, config);
+                layout.precision_lost |= lost;
+                return layout;
+            }
+        }
+        // The number of characters left for the radix point and the digits
+        // after it
+        let mut room = usize::MAX;
+        let radix_width = radix_point_width(config) as usize;
+        if let Some(max_width) = config.max_width {
+            let room_for =
+                |e| (max_width as i32 - min_width_scientific(e, sign, config)).max(0) as usize;
+            let fill = |room: usize| {
+                let mut cut = digits.clone();
+                let mut cut_e = e;
+                // Ties in the digits are rarely ties in the float, so round
+                // towards whichever side the float is on
+                let cut_config = config.precise_tie_detection(true);
+                let kept = (room + 1 - radix_width).max(1);
+                cut_digits(&mut cut, &mut cut_e, kept, value, cut_config);
+                (cut, cut_e)
+            };
+            let filled = fill(room_for(e));
+            // Rounding can carry into a new digit, changing the length of
+            // the exponent, and then it's rounded again to the new room.
+            // If that doesn't carry, the exponent would change back, so the
+            // first rounding is kept
+            let (cut, cut_e) = if filled.1 != e && room_for(filled.1) != room_for(e) {
+                let refilled = fill(room_for(filled.1));
+                if refilled.1 == filled.1 {
+                    refilled
+                } else {
+                    filled
+                }
+            } else {
+                filled
+            };
+            lost |= !same_value(&cut, cut_e, &digits, e);
+            digits = cut;
+            e = cut_e;
+            room = room_for(e);
+        }
+        let exponent = exponent_string(e, config);
+        if let Some(total) = config.scientific_total_width {
+            room = room.min((total as i32 - min_width_scientific(e, sign, config)).max(0) as usize);
+        }
+        let significant_digits = significant_digit_count(&digits);
+        out.push_str(sign_str(sign, config));
+        out.push(digits[0] as char);
+        let mut radix_index = None;
+        // Without an exponent, a single digit mantissa is a whole number
+        let whole_point_zero = point_zero(!exponent.is_empty(), config);
+        // The ".0" after a single digit mantissa is only written when it
+        // fits, and otherwise it's written like with TrailingPoint::Never,
+        // never leaving just the radix point
+        let has_point = digits.len() > 1 || (whole_point_zero && room > radix_width);
+        if has_point {
+            push_radix_point(out, &mut radix_index, config);
+        }
+        if digits.len() > 1 {
+            for c in &digits[1..] {
+                out.push(*c as char);
+            }
+        } else if has_point {
+            out.push('0');
+        }
+        let mut padding = 0;
+        let exponent_index = if exponent.is_empty() {
+            None
+        } else {
+            padding = exponent_padding(&exponent, out.char_len() - start, config);
+            pad_mantissa(out, start, config);
+            Some(out.byte_len())
+        };
+        push_exponent(out, &exponent, padding);
+        let rounded = rounding(sign, &digits, e, lost, value, config);
+        push_suffix(out, lost, rounded, config);
+        return Layout {
+            radix_index,
+            exponent_index,
+            exponent: exponent_index.map(|_| e - 1),
+            sign_len: sign_str(sign, config).len(),
+            significant_digits,
+            precision_lost: lost,
+        };
+    }
+    // Non-e-notation case
+    let significant_digits = significant_digit_count(&digits);
+    if let Some(limit) = config.min_integer_digits {
+        // Pad 0's to get enough integer digits
+        let limit = limit as i32;
+        if e < limit {
+            digits.prepend_zeros((limit - e) as usize);
+            e = limit;
+        }
+    }
+    let rounded = rounding(sign, &digits, e, lost, value, config);
+    out.push_str(sign_str(sign, config));
+    let mut radix_index = None;
+    // Split the digits at the radix point, with zeros filling in between
+    // the digits and the radix point on either side
+    let zeros = |count: usize| core::iter::repeat_n(digit_to_u8(0), count);
+    let split = e.max(0) as usize;
+    let integer_zeros = split.saturating_sub(digits.len());
+    let fraction_zeros = (-e).max(0) as usize;
+    let integer = &digits[..split.min(digits.len())];
+    let fraction = &digits[split.min(digits.len())..];
+    if e <= 0 {
+        out.push('0');
+    } else {
+        push_grouped(
+            out,
+            integer.iter().copied().chain(zeros(integer_zeros)),
+            e as usize,
+            config.group_separator,
+            config.group_size,
+            true,
+        );
+    }
+    if !fraction.is_empty() {
+        push_radix_point(out, &mut radix_index, config);
+        push_grouped(
+            out,
+            zeros(fraction_zeros).chain(fraction.iter().copied()),
+            fraction_zeros + fraction.len(),
+            config.fraction_group_separator,
+            config.fraction_group_size,
+            false,
+        );
+    } else if point_zero(false, config) {
+        push_radix_point(out, &mut radix_index, config);
+        out.push('0');
+    }
+    push_suffix(out, lost, rounded, config);
+
+    Layout {
+        radix_index,
+        exponent_index: None,
+        exponent: None,
+        sign_len: sign_str(sign, config).len(),
+        significant_digits,
+        precision_lost: lost,
+    }
+}
+
+// The most characters that can be written after the digits and exponent
+fn suffix_width(config: FmtFloatConfig) -> usize {
+    let marker_width =
+        config.truncation_marker.is_some() as usize + config.rounding_markers.is_some() as usize;
+    let unit_width = match config.unit {
+        Some(unit) if config.unit_space => unit.chars().count() + 1,
+        Some(unit) => unit.chars().count(),
+        None => 0,
+    };
+    marker_width + unit_width
+}
+
+// The SI prefixes for exponents from -24 to 24, in steps of 3
+const SI_PREFIXES: [&str; 17] = [
+    "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y",
+];
+
+// The exponent to write as an SI prefix for a value with <e> digits left
+// of the decimal point, if it has one
+fn si_exponent(e: i32, config: FmtFloatConfig) -> Option<i32> {
+    let exponent = (e - 1).div_euclid(3) * 3;
+    if config.si_prefixes && (-24..=24).contains(&exponent) {
+        Some(exponent)
+    } else {
+        None
+    }
+}
+
+// The exponent (or SI prefix) written after the mantissa of a value with
+// <e> digits left of the decimal point in scientific notation
+fn written_exponent(e: i32, config: FmtFloatConfig) -> Exponent {
+    match si_exponent(e, config) {
+        Some(exponent) => Exponent::Static(SI_PREFIXES[(exponent / 3 + 8) as usize]),
+        None => exponent_string(e - mantissa_e(e, config) + 1, config),
+    }
+}
+
+// The number of leading 0's to pad the digits of an <exponent> written after
+// a mantissa <mantissa_width> characters wide with, to fill
+// scientific_total_width when the mantissa couldn't fill it
+fn exponent_padding(exponent: &str, mantissa_width: usize, config: FmtFloatConfig) -> usize {
+    let total = match (config.scientific_total_width, config.max_width) {
+        (Some(total), Some(max_width)) => total.min(max_width),
+        (Some(total), None) => total,
+        (None, _) => return 0,
+    };
+    if config.exponent_formatter.is_some() || config.spoken_exponent {
+        return 0;
+    }
+    if !exponent.contains(|c: char| c.is_ascii_digit()) {
+        return 0;
+    }
+    let width = mantissa_width + exponent.chars().count();
+    (total as usize).saturating_sub(width)
+}
+
+// Write <exponent> with <padding> 0's before its digits
+fn push_exponent<S: Sink>(out: &mut S, exponent: &str, padding: usize) {
+    let index = exponent
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(exponent.len());
+    out.push_str(&exponent[..index]);
+    for _ in 0..padding {
+        out.push('0');
+    }
+    out.push_str(&exponent[index..]);
+}
+
+// Pad a mantissa (with its sign), which starts <start> characters into the
+// text, with spaces to fill mantissa_field_width
+fn pad_mantissa<S: Sink>(s: &mut S, start: usize, config: FmtFloatConfig) {
+    if let Some(width) = config.mantissa_field_width {
+        for _ in s.char_len() - start..width as usize {
+            s.push(' ');
+        }
+    }
+}
+
+// The number of digits left of the decimal point in the mantissa, for a
+// value with <e> digits left of the decimal point in scientific notation
+fn mantissa_e(e: i32, config: FmtFloatConfig) -> i32 {
+    match (si_exponent(e, config), config.max_exponent_magnitude) {
+        (Some(exponent), _) => e - exponent,
+        (None, Some(cap)) => e - (e - 1).max(-(cap as i32)).min(cap as i32),
+        (None, None) => 1,
+    }
+}
+
+// The config for a mantissa that some of the magnitude was moved into,
+// which is written without e notation, and without the parts of the
+// string that go around the whole value
+fn mantissa_config(config: FmtFloatConfig) -> FmtFloatConfig {
+    FmtFloatConfig {
+        min_sig_digits: None,
+        min_decimal_digits: None,
+        min_integer_digits: None,
+        max_width: None,
+        max_byte_width: None,
+        group_separator: None,
+        fraction_group_separator: None,
+        truncation_marker: None,
+        rounding_markers: None,
+        unit: None,
+        trailing_point: if point_zero(true, config) {
+            TrailingPoint::Always
+        } else {
+            TrailingPoint::Never
+        },
+        ..config
+    }
+    .force_no_e_notation()
+}
+
+// Write the truncation marker (if digits were <lost>) and unit
+fn push_suffix<S: Sink>(s: &mut S, lost: bool, rounded: Option<Ordering>, config: FmtFloatConfig) {
+    if let (true, Some(marker)) = (lost, config.truncation_marker) {
+        s.push(marker);
+    }
+    if let (Some(rounded), Some((up, down))) = (rounded, config.rounding_markers) {
+        match rounded {
+            Ordering::Greater => s.push(up),
+            Ordering::Less => s.push(down),
+            Ordering::Equal => {}
+        }
+    }
+    if let Some(unit) = config.unit {
+        if config.unit_space {
+            s.push(' ');
+        }
+        s.push_str(unit);
+    }
+}
+
+// How the value shown by <digits>, with <e> digits left of the decimal
+// point, compares to the exact value of the float, when it lost precision
+// and rounding_markers needs to know
+fn rounding(
+    sign: bool,
+    digits: &[u8],
+    e: i32,
+    lost: bool,
+    value: Option<f64>,
+    config: FmtFloatConfig,
+) -> Option<Ordering> {
+    match (lost, value, config.rounding_markers) {
+        (true, Some(value), Some(_)) => {
+            let shown = compare_float_to_digits(value, digits, e).reverse();
+            Some(if sign { shown.reverse() } else { shown })
+        }
+        _ => None,
+    }
+}
+
+// The number of significant digits in <digits>, skipping leading zeros.
+// If every digit is zero, they all count
+fn significant_digit_count(digits: &[u8]) -> usize {
+    match digits.iter().position(|digit| *digit != digit_to_u8(0)) {
+        Some(first) => digits.len() - first,
+        None => digits.len(),
+    }
+}
+
+// The shortest round-trip digits (in ascii) of a finite value, and
+// the number of digits left of the decimal point
+fn f64_digits(value: f64) -> (Digits, i32) {
+    if value == 0.0 {
+        (Digits::zero(), 1)
+    } else {
+        let rad_10 = d2d(value);
+        let digits = mantissa_to_digits(rad_10.mantissa);
+        let exp = rad_10.exponent + digits.len() as i32;
+        (digits, exp)
+    }
+}
+
+fn f32_digits(value: f32) -> (Digits, i32) {
+    if value == 0.0 {
+        (Digits::zero(), 1)
+    } else {
+        let rad_10 = f2d(value);
+        let digits = mantissa_to_digits(rad_10.mantissa as u64);
+        let exp = rad_10.exponent + digits.len() as i32;
+        (digits, exp)
+    }
+}
+
+// The decimal digits (in ascii) of a non-zero mantissa
+fn mantissa_to_digits(mut mantissa: u64) -> Digits {
+    let mut digits = Digits::new();
+    while mantissa > 0 {
+        digits.push(digit_to_u8((mantissa % 10) as u8));
+        mantissa /= 10;
+    }
+    digits.reverse();
+    digits
+}
+
+// Write a NaN with a sign bit and the bits of its mantissa
+fn write_nan<S: Sink>(out: &mut S, negative: bool, payload: u64, config: FmtFloatConfig) {
+    if negative && config.show_nan_sign {
+        out.push('-');
+    }
+    out.push_str("NaN");
+    if config.show_nan_payload {
+        let _ = write!(out, "(0x{:x})", payload);
+    }
+}
+
+// Write a NaN (with the bits of its mantissa as <payload>) or an infinity
+// (without a payload), fitted to the config like any other value
+fn fit_special<S: Sink>(
+    negative: bool,
+    payload: Option<u64>,
+    config: FmtFloatConfig,
+) -> (S, Layout) {
+    // The text has no exponent, so it's always fitted to max_width
+    let config = FmtFloatConfig {
+        spoken_exponent: false,
+        ..config
+    };
+    fit_sink(Digits::new(), config, |out: &mut S, _, config| {
+        let sign_len = match payload {
+            Some(payload) => {
+                write_nan(out, negative, payload, config);
+                (negative && config.show_nan_sign) as usize
+            }
+            None => {
+                out.push_str(if negative { "-inf" } else { "inf" });
+                negative as usize
+            }
+        };
+        Layout {
+            radix_index: None,
+            exponent_index: None,
+            exponent: None,
+            sign_len,
+            significant_digits: 0,
+            precision_lost: false,
+        }
+    })
+}
+
+// fit_special for a NaN or infinite double
+fn fit_special_f64<S: Sink>(value: f64, config: FmtFloatConfig) -> (S, Layout) {
+    let bits = value.to_bits();
+    let payload = if value.is_nan() {
+        Some(bits & ((1 << 52) - 1))
+    } else {
+        None
+    };
+    fit_special(bits >> 63 == 1, payload, config)
+}
+
+// Write a value with <format>, replacing text that is too wide for the
+// config with pound signs
+fn fit_width<S: Sink>(
+    digits: Digits,
+    config: FmtFloatConfig,
+    format: &impl Fn(&mut S, Digits, FmtFloatConfig) -> Layout,
+) -> (S, Layout) {
+    let mut out = S::default();
+    let layout = format(&mut out, digits, config);
+    if config.spoken_exponent {
+        return (out, layout);
+    }
+    if let Some(limit) = config.max_width {
+        if out.char_len() > limit as usize {
+            return pounds(limit as usize);
+        }
+    }
+    (out, layout)
+}
+
+// The text for a float that doesn't fit in <width>
+fn pounds<S: Sink>(width: usize) -> (S, Layout) {
+    let mut out = S::default();
+    for _ in 0..width {
+        out.push('#');
+    }
+    let layout = Layout {
+        radix_index: None,
+        exponent_index: None,
+        exponent: None,
+        sign_len: 0,
+        significant_digits: 0,
+        precision_lost: true,
+    };
+    (out, layout)
+}
+
+// Write <digits> with <format> and fit the result to max_width, and then
+// to max_byte_width by lowering max_width until the UTF-8 encoding fits
+fn fit_sink<S: Sink>(
+    digits: Digits,
+    config: FmtFloatConfig,
+    format: impl Fn(&mut S, Digits, FmtFloatConfig) -> Layout,
+) -> (S, Layout) {
+    let limit = match config.max_byte_width {
+        Some(limit) => limit as usize,
+        None => return fit_width(digits, config, &format),
+    };
+    // Pound signs are one byte each, so they fill whichever cap is smaller
+    let pounds_width = match config.max_width {
+        Some(max_width) => limit.min(max_width as usize),
+        None => limit,
+    };
+    let mut config = config;
+    loop {
+        let (out, layout) = fit_width(digits.clone(), config, &format);
+        if out.byte_len() <= limit {
+            return (out, layout);
+        }
+        let width = out.char_len();
+        // The width can't be lowered any further
+        if config.spoken_exponent || width <= 1 {
+            return pounds(pounds_width);
+        }
+        config.max_width = Some((width - 1).min(255) as u8);
+    }
+}
+
+// fit_sink, writing to a string
+fn fit_bytes(
+    digits: Digits,
+    config: FmtFloatConfig,
+    format: impl Fn(&mut String, Digits, FmtFloatConfig) -> Layout,
+) -> FormattedFloat {
+    let (text, layout) = fit_sink(digits, config, format);
+    layout.with_text(text)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default()
+///     .force_no_e_notation()      // Don't use scientific notation
+///     .add_point_zero(true)       // Add .0 to the end of integers
+///     .max_significant_digits(4)  // Stop after the first 4 non-zero digits
+///     .radix_point(',')           // Use a ',' instead of a '.'
+///     .round();                   // Round after removing non-significant digits
+///
+/// assert_eq!(dtoa(12459000.0, config), "12460000,0");
+/// ```
+pub fn dtoa(value: f64, config: FmtFloatConfig) -> String {
+    if !value.is_finite() {
+        return fit_special_f64::<String>(value, config).0;
+    }
+    let sign = value.is_sign_negative();
+    let (digits, exp) = f64_digits(value);
+    fit_bytes(digits, config, |out, digits, config| {
+        digits_to_a(out, sign, digits, exp, Some(value.abs()), config)
+    })
+    .text
+}
+
+/// Convert a single-precision floating point value (``f32``) to a string
+/// using a given configuration
+pub fn ftoa(value: f32, config: FmtFloatConfig) -> String {
+    if !value.is_finite() {
+        let bits = value.to_bits();
+        let payload = if value.is_nan() {
+            Some((bits & ((1 << 23) - 1)) as u64)
+        } else {
+            None
+        };
+        return fit_special::<String>(bits >> 31 == 1, payload, config).0;
+    }
+    let (digits, exp) = f32_digits(value);
+    let sign = value.is_sign_negative();
+    fit_bytes(digits, config, |out, digits, config| {
+        digits_to_a(out, sign, digits, exp, Some((value as f64).abs()), config)
+    })
+    .text
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, but with its ``round_mode`` replaced by
+/// ``mode``
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_with_mode, FmtFloatConfig, RoundMode};
+///
+/// let config = FmtFloatConfig::default().max_significant_digits(2);
+///
+/// assert_eq!(dtoa_with_mode(1.57, config, RoundMode::Round), "1.6");
+/// assert_eq!(dtoa_with_mode(1.57, config, RoundMode::Truncate), "1.5");
+/// ```
+pub fn dtoa_with_mode(value: f64, config: FmtFloatConfig, mode: RoundMode) -> String {
+    dtoa(
+        value,
+        FmtFloatConfig {
+            round_mode: mode,
+            ..config
+        },
+    )
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, followed by ``separator`` and a check digit.
+/// The check digit is computed with ``algorithm`` from every ASCII digit in
+/// the string, in order, including the digits of the exponent. Infinities
+/// and NaN have no digits, so they're written without a check digit.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_checked, CheckDigit, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default()
+///     .force_no_e_notation()
+///     .add_point_zero(false);
+///
+/// assert_eq!(
+///     dtoa_checked(7992739871.0, CheckDigit::Luhn, '-', config),
+///     "7992739871-3"
+/// );
+/// assert_eq!(dtoa_checked(12.5, CheckDigit::DigitSum, '/', config), "12.5/8");
+/// ```
+pub fn dtoa_checked(
+    value: f64,
+    algorithm: CheckDigit,
+    separator: char,
+    config: FmtFloatConfig,
+) -> String {
+    let mut s = dtoa(value, config);
+    if !value.is_finite() {
+        return s;
+    }
+    let digits = s.chars().filter_map(|c| match c {
+        '0'..='9' => Some(c as u32 - '0' as u32),
+        _ => None,
+    });
+    let check = match algorithm {
+        CheckDigit::Luhn => {
+            // The check digit goes at the end, so the last digit is doubled
+            let sum: u32 = digits
+                .rev()
+                .enumerate()
+                .map(|(i, digit)| match (i % 2 == 0, digit * 2) {
+                    (true, doubled) if doubled > 9 => doubled - 9,
+                    (true, doubled) => doubled,
+                    (false, _) => digit,
+                })
+                .sum();
+            (10 - sum % 10) % 10
+        }
+        CheckDigit::DigitSum => digits.sum::<u32>() % 10,
+    };
+    s.push(separator);
+    s.push(char::from_digit(check, 10).unwrap());
+    s
+}
+
+/// Convert the absolute value of a double-precision floating point value
+/// (``f64``) to a string using a given configuration. The sign is dropped
+/// for every value, including negative zero and negative infinity
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_abs, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_abs(-1.5, config), "1.5");
+/// assert_eq!(dtoa_abs(-0.0, config), "0.0");
+/// assert_eq!(dtoa_abs(f64::NEG_INFINITY, config), "inf");
+/// ```
+pub fn dtoa_abs(value: f64, config: FmtFloatConfig) -> String {
+    dtoa(value.abs(), config)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, along with the positions of the sign, radix
+/// point, and exponent in the string. This is useful for aligning
+/// formatted floats on their radix points or exponents. The output text is
+/// the same as ``dtoa``.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_structured, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().force_e_notation();
+/// let formatted = dtoa_structured(-12.5, config);
+///
+/// assert_eq!(formatted.text, "-1.25e1");
+/// assert_eq!(formatted.sign_len, 1);
+/// assert_eq!(formatted.radix_index, Some(2));
+/// assert_eq!(formatted.exponent_index, Some(5));
+/// ```
+pub fn dtoa_structured(value: f64, config: FmtFloatConfig) -> FormattedFloat {
+    if !value.is_finite() {
+        let (text, layout) = fit_special_f64(value, config);
+        return layout.with_text(text);
+    }
+    let sign = value.is_sign_negative();
+    let (digits, exp) = f64_digits(value);
+    fit_bytes(digits, config, |out, digits, config| {
+        digits_to_a(out, sign, digits, exp, Some(value.abs()), config)
+    })
+}
+
+/// Write an exponent as a power of ten with superscript digits, like
+/// ``"×10⁻³⁰"``, for use with ``FmtFloatConfig::exponent_formatter``.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa, superscript_exponent, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().exponent_formatter(superscript_exponent);
+///
+/// assert_eq!(dtoa(1.5e-30, config), "1.5×10⁻³⁰");
+/// assert_eq!(dtoa(2.0e12, config), "2.0×10¹²");
+/// ```
+pub fn superscript_exponent(exponent: i32) -> String {
+    const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    let mut res = String::from("×10");
+    if exponent < 0 {
+        res.push('⁻');
+    }
+    for digit in format!("{}", exponent.unsigned_abs()).bytes() {
+        res.push(SUPERSCRIPTS[(digit - b'0') as usize]);
+    }
+    res
+}
+
+/// Convert a double-precision floating point value (``f64``) to LaTeX for
+/// math mode. Scientific notation is written as the mantissa, ``times``, and a
+/// power of ten with the exponent in braces, like ``1.5 \\times 10^{3}`` (with
+/// ``"\\times"``) or ``1.5 \\cdot 10^{3}`` (with ``"\\cdot"``). The radix
+/// point is always ".", and ``exponent_formatter``, ``spoken_exponent``, and
+/// ``si_prefixes`` are ignored. Infinities are written as ``\\infty`` and ``-\\infty``, and
+/// NaN as ``\\text{NaN}``.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_latex, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_latex(1.5e10, "\\times", config), "1.5 \\times 10^{10}");
+/// assert_eq!(dtoa_latex(-2.5e-7, "\\cdot", config), "-2.5 \\cdot 10^{-7}");
+/// assert_eq!(dtoa_latex(12.5, "\\times", config), "12.5");
+/// assert_eq!(dtoa_latex(f64::NEG_INFINITY, "\\times", config), "-\\infty");
+/// ```
+pub fn dtoa_latex(value: f64, times: &str, config: FmtFloatConfig) -> String {
+    if value.is_nan() {
+        return "\\text{NaN}".to_string();
+    } else if value.is_infinite() {
+        let sign = if value.is_sign_negative() { "-" } else { "" };
+        return format!("{}\\infty", sign);
+    }
+    let mut config = config
+        .radix_point('.')
+        .omit_radix_point(false)
+        .spoken_exponent(false)
+        .si_prefixes(false);
+    config.exponent_formatter = None;
+    let formatted = dtoa_structured(value, config);
+    match (formatted.exponent_index, formatted.exponent) {
+        (Some(index), Some(exponent)) => {
+            // Skip the "e" and the exponent, keeping anything after them
+            let suffix = &formatted.text[index + 1 + format!("{}", exponent).len()..];
+            format!(
+                "{} {} 10^{{{}}}{}",
+                &formatted.text[..index],
+                times,
+                exponent,
+                suffix
+            )
+        }
+        _ => formatted.text,
+    }
+}
+
+/// The exponent that ``dtoa`` would write for a value in scientific notation,
+/// or None if it wouldn't use scientific notation (or the exponent is left
+/// out). This includes adjustments like ``max_exponent_magnitude``, and
+/// rounding that changes the exponent.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{printed_exponent, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(printed_exponent(1.5e10, config), Some(10));
+/// assert_eq!(printed_exponent(1.5e-10, config), Some(-10));
+/// assert_eq!(printed_exponent(150.0, config), None);
+/// ```
+pub fn printed_exponent(value: f64, config: FmtFloatConfig) -> Option<i32> {
+    dtoa_structured(value, config).exponent
+}
+
+/// Describe how a value is represented before formatting, for debugging and
+/// bug reports: its sign, its shortest round-trip digits, the number of digits
+/// left of the decimal point (``e``), the exponent it has in scientific notation
+/// (``e - 1``), and the notation and string ``dtoa`` gives with the default config.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::debug_decompose;
+///
+/// assert_eq!(
+///     debug_decompose(-1234.5),
+///     "sign: -, digits: 12345, e: 4, exponent: 3, notation: fixed, default: -1234.5"
+/// );
+/// ```
+pub fn debug_decompose(value: f64) -> String {
+    if !value.is_finite() {
+        return format!("non-finite: {}", dtoa(value, FmtFloatConfig::default()));
+    }
+    let (digits, e) = f64_digits(value);
+    let formatted = dtoa_structured(value, FmtFloatConfig::default());
+    format!(
+        "sign: {}, digits: {}, e: {}, exponent: {}, notation: {}, default: {}",
+        if value.is_sign_negative() { '-' } else { '+' },
+        String::from_utf8(digits.to_vec()).unwrap(),
+        e,
+        e - 1,
+        if formatted.exponent_index.is_some() {
+            "scientific"
+        } else {
+            "fixed"
+        },
+        formatted.text
+    )
+}
+
+/// The shortest round-trip significant digits of a value, as ASCII bytes,
+/// without the sign or exponent. These are the digits every config starts
+/// from, so they never have trailing zeros (except for zero itself, which is
+/// ``b"0"``). Infinities and NaN have no digits.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::mantissa_digits;
+///
+/// assert_eq!(mantissa_digits(-1234.5), b"12345");
+/// assert_eq!(mantissa_digits(0.1 + 0.2), b"30000000000000004");
+/// assert_eq!(mantissa_digits(1500.0), b"15");
+/// ```
+pub fn mantissa_digits(value: f64) -> Vec<u8> {
+    if !value.is_finite() {
+        return Vec::new();
+    }
+    f64_digits(value).0.to_vec()
+}
+
+/// The number of shortest round-trip significant digits of a value, the
+/// same as ``mantissa_digits(value).len()`` but without formatting anything,
+/// for estimating the work or buffer space a batch will need. Zero has one
+/// digit, and infinities and NaN have none.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::estimated_digits;
+///
+/// assert_eq!(estimated_digits(-1234.5), 5);
+/// assert_eq!(estimated_digits(0.1 + 0.2), 17);
+/// assert_eq!(estimated_digits(1500.0), 2);
+/// ```
+pub fn estimated_digits(value: f64) -> u8 {
+    if !value.is_finite() {
+        return 0;
+    }
+    if value == 0.0 {
+        return 1;
+    }
+    let mut mantissa = d2d(value).mantissa;
+    let mut count = 1;
+    while mantissa >= 10 {
+        mantissa /= 10;
+        count += 1;
+    }
+    count
+}
+
+/// The number of characters in ``dtoa(value, config)``, for comparing how
+/// long different configs make a value, such as scientific notation against
+/// fixed notation. The characters are counted as they're formatted, without
+/// building the string.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{shortest_len, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(shortest_len(1e-7, config.force_no_e_notation()), 9);
+/// assert_eq!(shortest_len(1e-7, config.force_e_notation()), 6);
+/// ```
+pub fn shortest_len(value: f64, config: FmtFloatConfig) -> usize {
+    if !value.is_finite() {
+        return fit_special_f64::<Counter>(value, config).0.chars;
+    }
+    let sign = value.is_sign_negative();
+    let (digits, exp) = f64_digits(value);
+    let (count, _) = fit_sink(digits, config, |out: &mut Counter, digits, config| {
+        digits_to_a(out, sign, digits, exp, Some(value.abs()), config)
+    });
+    count.chars
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, along with the number of significant digits
+/// in the string (see ``FormattedFloat::significant_digits``). Zeros right of
+/// the last nonzero digit only count if they come after the radix point.
+/// The count saturates at 255.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_with_sig_count, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().max_significant_digits(3);
+///
+/// assert_eq!(dtoa_with_sig_count(3.14159, config), ("3.14".to_string(), 3));
+/// assert_eq!(dtoa_with_sig_count(1200.0, config), ("1200.0".to_string(), 2));
+/// ```
+pub fn dtoa_with_sig_count(value: f64, config: FmtFloatConfig) -> (String, u8) {
+    let formatted = dtoa_structured(value, config);
+    let count = formatted.significant_digits.min(u8::MAX as usize) as u8;
+    (formatted.text, count)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// with exactly ``sig_figs`` significant digits (at least 1), rounded using
+/// the config's rounding mode, and padded with trailing zeros to show the
+/// precision. The decimal digit limits, ``ignore_extremes``, ``simplify``, and
+/// ``keep_integer_digits`` are ignored, and integers aren't written with ".0".
+///
+/// The notation is chosen using the config, except that scientific notation
+/// is used when the rounded value has more integer digits than ``sig_figs``,
+/// so that trailing zeros are always significant (unless
+/// ``force_no_e_notation`` is set).
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_precision, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_precision(0.1, 4, config), "0.1000");
+/// assert_eq!(dtoa_precision(1234.0, 4, config), "1234");
+/// assert_eq!(dtoa_precision(1234.0, 4, config.upper_e_break(3)), "1.234e3");
+/// assert_eq!(dtoa_precision(12345.0, 3, config), "1.23e4");
+/// ```
+pub fn dtoa_precision(value: f64, sig_figs: u8, config: FmtFloatConfig) -> String {
+    let sig_figs = sig_figs.max(1);
+    let config = FmtFloatConfig {
+        max_decimal_digits: None,
+        min_decimal_digits: None,
+        ignore_extremes: None,
+        ..config
+    }
+    .significant_digits(sig_figs..=sig_figs)
+    .min_sig_digits_in_fraction(true)
+    .keep_integer_digits(false)
+    .simplify(false)
+    .trailing_point(TrailingPoint::Never);
+    if !value.is_finite() {
+        return dtoa(value, config);
+    }
+    // Rounding can carry into a new integer digit
+    let (mut digits, mut e) = f64_digits(value.abs());
+    cut_digits(
+        &mut digits,
+        &mut e,
+        sig_figs as usize,
+        Some(value.abs()),
+        config,
+    );
+    let config = if e > sig_figs as i32 && !config.force_no_e_notation {
+        config.force_e_notation()
+    } else {
+        config
+    };
+    dtoa(value, config)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, the same as ``dtoa``, but panic if the
+/// string doesn't round-trip back to the same value. This is meant for
+/// catching lossy configurations in tests, where precision must be kept.
+///
+/// Only the digits are checked: the radix point, group separator, sign
+/// column, exponent style, and unit don't need to be parseable. NaN and infinite
+/// values are never checked.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_strict, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().radix_point(',');
+///
+/// assert_eq!(dtoa_strict(0.1 + 0.2, config), "0,30000000000000004");
+/// ```
+///
+/// ```should_panic
+/// use pretty_dtoa::{dtoa_strict, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().max_significant_digits(3);
+///
+/// dtoa_strict(0.1 + 0.2, config); // 0.300 loses precision
+/// ```
+pub fn dtoa_strict(value: f64, config: FmtFloatConfig) -> String {
+    let formatted = dtoa(value, config);
+    assert!(
+        !value.is_finite() || round_trips(value, config),
+        "{:?} doesn't round-trip to {:?}",
+        formatted,
+        value
+    );
+    formatted
+}
+
+// Whether the string written by dtoa parses back to the same (finite)
+// value. Only its sign, digits, radix point and exponent are read, so the
+// separators, markers, unit and exponent style don't need to be parseable.
+// Values written with a template only check their magnitude
+fn round_trips(value: f64, config: FmtFloatConfig) -> bool {
+    let (digits, _) = f64_digits(value);
+    let template = template_for(value.is_sign_negative(), &digits, config);
+    if let Some(template) = template {
+        if template_parts(template).is_none() {
+            return true;
+        }
+    }
+    let formatted = dtoa_structured(value, config);
+    let text = &formatted.text;
+    let mut parseable = String::with_capacity(text.len());
+    if template.is_none() && text[..formatted.sign_len].contains('-') {
+        parseable.push('-');
+    }
+    // Read the mantissa, which starts at the first digit (after the sign
+    // and any approximation marker) and ends at the exponent or at the
+    // first character after it that isn't part of the number
+    let end = formatted.exponent_index.unwrap_or(text.len());
+    let separators = [config.group_separator, config.fraction_group_separator];
+    let mut has_digits = false;
+    for (index, c) in text[formatted.sign_len..end].char_indices() {
+        if Some(index + formatted.sign_len) == formatted.radix_index {
+            parseable.push('.');
+        } else if c.is_ascii_digit() {
+            parseable.push(c);
+            has_digits = true;
+        } else if has_digits && !separators.contains(&Some(c)) {
+            break;
+        }
+    }
+    if !has_digits {
+        return false;
+    }
+    if let Some(exponent) = formatted.exponent {
+        parseable.push_str(&format!("e{}", exponent));
+    }
+    match parseable.parse::<f64>() {
+        Ok(parsed) if template.is_some() => parsed.abs() == value.abs(),
+        Ok(parsed) => parsed == value,
+        Err(_) => false,
+    }
+}
+
+/// Whether ``dtoa`` would remove any digits of the shortest round-trip
+/// representation of ``value`` under the config, so the string no longer
+/// reads back as the same float. This is the same condition as
+/// ``FormattedFloat::precision_lost``, and doesn't depend on whether the
+/// string can be parsed (with a custom radix point, for example). NaN and
+/// infinities never lose precision.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{will_lose_precision, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().max_significant_digits(3);
+///
+/// assert!(will_lose_precision(3.24159, config));
+/// assert!(!will_lose_precision(2.5, config));
+/// ```
+pub fn will_lose_precision(value: f64, config: FmtFloatConfig) -> bool {
+    dtoa_structured(value, config).precision_lost
+}
+
+/// The smallest ``max_width`` for which ``dtoa`` still round-trips
+/// ``value`` under the config, ignoring the config's own ``max_width``.
+/// This is the narrowest column a value can be written in without losing
+/// precision. For NaN, infinite values, and configs that don't round-trip
+/// the value at any width, this is the width of the string written without
+/// a ``max_width``. Widths are counted in characters, up to 255, and can
+/// be wider than the string itself, since ``max_width`` leaves room for a
+/// truncation marker even when none is written.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa, min_round_trip_width, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(min_round_trip_width(0.1 + 0.2, config), 19);
+/// assert_eq!(min_round_trip_width(1e-10, config), 5);
+/// assert_eq!(dtoa(1e-10, config.max_width(5)), "1e-10");
+/// ```
+pub fn min_round_trip_width(value: f64, config: FmtFloatConfig) -> u8 {
+    let config = config.no_max_width();
+    let full_width = dtoa(value, config).chars().count().min(u8::MAX as usize) as u8;
+    if !value.is_finite() || !round_trips(value, config) {
+        return full_width;
+    }
+    // Each width can pick a different notation, so a narrower width can
+    // round-trip when a wider one doesn't, and every width is tried. The
+    // room left for markers can make the width wider than the string
+    (1..=u8::MAX)
+        .find(|width| round_trips(value, config.max_width(*width)))
+        .unwrap_or(full_width)
+}
+
+/// Convert a double-precision floating point value (``f64``) to the most
+/// precise string that fits in ``target_width`` characters. Unlike
+/// ``max_width``, which cuts digits from the notation the config picked,
+/// this tries both plain and scientific notation at every number of
+/// significant digits, and keeps whichever fitting string is closest to
+/// the value (the shorter one on a tie, and plain notation after that).
+/// Whole numbers are written without a ".0". Values that can't fit are represented by pound signs.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::dtoa_fit;
+///
+/// assert_eq!(dtoa_fit(1234567.0, 7), "1234567");
+/// assert_eq!(dtoa_fit(1234567.0, 5), "1.2e6");
+/// assert_eq!(dtoa_fit(0.000123456, 8), "1.235e-4");
+/// assert_eq!(dtoa_fit(1.5, 12), "1.5");
+/// ```
+pub fn dtoa_fit(value: f64, target_width: u8) -> String {
+    // A ".0" takes up room without adding precision
+    let config = FmtFloatConfig::default().trailing_point(TrailingPoint::Never);
+    if !value.is_finite() {
+        return dtoa(value, config.max_width(target_width));
+    }
+    let mut best: Option<(f64, String)> = None;
+    for notation in [config.force_no_e_notation(), config.force_e_notation()] {
+        // The first number of significant digits that fits is the most
+        // precise one for this notation
+        for sig_digits in (1..=17).rev() {
+            let text = dtoa(value, notation.max_significant_digits(sig_digits).round());
+            if text.chars().count() > target_width as usize {
+                continue;
+            }
+            let error = match text.parse::<f64>() {
+                Ok(parsed) => (parsed - value).abs(),
+                Err(_) => continue,
+            };
+            let better = match &best {
+                Some((best_error, best_text)) => {
+                    error < *best_error || (error == *best_error && text.len() < best_text.len())
+                }
+                None => true,
+            };
+            if better {
+                best = Some((error, text));
+            }
+            break;
+        }
+    }
+    match best {
+        Some((_, text)) => text,
+        None => "#".repeat(target_width as usize),
+    }
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, split into the mantissa (with the sign and
+/// radix point) and the exponent (with the exponent marker and sign), so
+/// each can be aligned in its own table column. The exponent is empty if
+/// the value isn't written in scientific notation. Joining the two gives
+/// the same text as ``dtoa``.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_columns, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     dtoa_columns(-1.25e-10, config),
+///     ("-1.25".to_string(), "e-10".to_string())
+/// );
+/// assert_eq!(dtoa_columns(12.5, config), ("12.5".to_string(), String::new()));
+/// ```
+pub fn dtoa_columns(value: f64, config: FmtFloatConfig) -> (String, String) {
+    let mut formatted = dtoa_structured(value, config);
+    let exponent = match formatted.exponent_index {
+        Some(index) => formatted.text.split_off(index),
+        None => String::new(),
+    };
+    (formatted.text, exponent)
+}
+
+// Format a value and split it where its radix point goes (or would go)
+fn split_at_point(value: f64, config: FmtFloatConfig) -> (String, String) {
+    let mut formatted = dtoa_structured(value, config);
+    let index = formatted
+        .radix_index
+        .or(formatted.exponent_index)
+        .unwrap_or(formatted.text.len());
+    let fraction = formatted.text.split_off(index);
+    (formatted.text, fraction)
+}
+
+/// Convert a batch of double-precision floating point values (``f64``) to
+/// strings using a given configuration, padded with spaces so their radix
+/// points line up in a monospace column. Every string has the same number
+/// of characters before the radix point, and the same number from the radix
+/// point onwards. Values without a radix point are aligned as if it came
+/// right after their integer part (or before their exponent).
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_aligned, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     dtoa_aligned(&[1.5, -12.25, 100.0], config),
+///     vec!["  1.5 ", "-12.25", "100.0 "]
+/// );
+/// ```
+pub fn dtoa_aligned(values: &[f64], config: FmtFloatConfig) -> Vec<String> {
+    // Split each string where its radix point goes
+    let split: Vec<(String, String)> = values
+        .iter()
+        .map(|&value| split_at_point(value, config))
+        .collect();
+    let integer_width = split
+        .iter()
+        .map(|(i, _)| i.chars().count())
+        .max()
+        .unwrap_or(0);
+    let fraction_width = split
+        .iter()
+        .map(|(_, f)| f.chars().count())
+        .max()
+        .unwrap_or(0);
+    split
+        .into_iter()
+        .map(|(integer, fraction)| {
+            format!(
+                "{:>iw$}{:<fw$}",
+                integer,
+                fraction,
+                iw = integer_width,
+                fw = fraction_width
+            )
+        })
+        .collect()
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// with a fixed layout around its radix point, so a column of these strings
+/// lines up without looking at the other values. The integer part is right
+/// justified in ``int_width`` characters, so the radix point always lands at
+/// column ``int_width``. After it come ``frac_width`` characters, left
+/// justified and filled with spaces (use ``min_decimal_digits`` to fill with
+/// zeros instead). The value is rounded to at most ``frac_width`` decimal
+/// digits. Anything that still doesn't fit, like a large integer part or an
+/// exponent, gives a string of ``#``'s of the full width instead.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_point_aligned, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_point_aligned(1.5, 3, 2, config), "  1.5 ");
+/// assert_eq!(dtoa_point_aligned(-12.345, 3, 2, config), "-12.35");
+/// assert_eq!(dtoa_point_aligned(1.5, 3, 2, config.min_decimal_digits(2)), "  1.50");
+/// assert_eq!(dtoa_point_aligned(12345.0, 3, 2, config), "######");
+/// ```
+pub fn dtoa_point_aligned(
+    value: f64,
+    int_width: u8,
+    frac_width: u8,
+    config: FmtFloatConfig,
+) -> String {
+    let max_decimals = match config.max_decimal_digits {
+        Some(max) => max.min(frac_width as i16),
+        None => frac_width as i16,
+    };
+    let mut config = config.max_decimal_digits(max_decimals);
+    if frac_width == 0 {
+        // There's no room for a ".0"
+        config = config.trailing_point(TrailingPoint::Never);
+    }
+    let (integer, fraction) = split_at_point(value, config);
+    // The fraction part starts with the radix point
+    let (int_width, frac_width) = (int_width as usize, frac_width as usize + 1);
+    if integer.chars().count() > int_width || fraction.chars().count() > frac_width {
+        return "#".repeat(int_width + frac_width);
+    }
+    format!(
+        "{:>iw$}{:<fw$}",
+        integer,
+        fraction,
+        iw = int_width,
+        fw = frac_width
+    )
+}
+
+/// Convert a batch of double-precision floating point values (``f64``) to
+/// strings using a given configuration, all in the same notation. If any
+/// finite, nonzero value would be written in scientific notation, every value
+/// is, otherwise none are. Values may still use scientific notation if they
+/// can't fit ``max_width`` otherwise.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_uniform_notation, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_uniform_notation(&[1.5, 25.0], config), vec!["1.5", "25.0"]);
+/// assert_eq!(
+///     dtoa_uniform_notation(&[1.5, 2.5e10], config),
+///     vec!["1.5e0", "2.5e10"]
+/// );
+/// ```
+pub fn dtoa_uniform_notation(values: &[f64], config: FmtFloatConfig) -> Vec<String> {
+    let scientific = values
+        .iter()
+        .filter(|value| value.is_finite() && **value != 0.0)
+        .any(|value| needs_e_notation(f64_digits(*value).1, config));
+    let config = if scientific {
+        config.force_e_notation()
+    } else {
+        config.force_no_e_notation()
+    };
+    values.iter().map(|value| dtoa(*value, config)).collect()
+}
+
+/// Convert two double-precision floating point values (``f64``) to strings
+/// that can be compared character by character, for diffing tools. Both are
+/// written in the same notation (like ``dtoa_uniform_notation``), padded
+/// with 0's to the same number of digits after the radix point, and then
+/// aligned like ``dtoa_aligned``, so they have the same length and their
+/// radix points line up.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_diff, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     dtoa_diff(1.5, 12.25, config),
+///     (" 1.50".to_string(), "12.25".to_string())
+/// );
+/// assert_eq!(
+///     dtoa_diff(1.5, 2.5e10, config),
+///     ("1.5e0 ".to_string(), "2.5e10".to_string())
+/// );
+/// ```
+pub fn dtoa_diff(a: f64, b: f64, config: FmtFloatConfig) -> (String, String) {
+    let scientific = [a, b]
+        .iter()
+        .filter(|value| value.is_finite() && **value != 0.0)
+        .any(|value| needs_e_notation(f64_digits(*value).1, config));
+    let config = if scientific {
+        config.force_e_notation()
+    } else {
+        config.force_no_e_notation()
+    };
+    // The most digits either value has after the radix point
+    let decimals = [a, b]
+        .iter()
+        .map(|value| {
+            let formatted = dtoa_structured(*value, config);
+            match formatted.radix_index {
+                Some(index) => {
+                    let end = formatted.exponent_index.unwrap_or(formatted.text.len());
+                    formatted.text[index..end]
+                        .chars()
+                        .filter(|c| c.is_ascii_digit())
+                        .count()
+                }
+                None => 0,
+            }
+        })
+        .max()
+        .unwrap_or(0);
+    let config = config.min_decimal_digits(decimals.min(i16::MAX as usize) as i16);
+    let mut aligned = dtoa_aligned(&[a, b], config);
+    let b = aligned.pop().unwrap();
+    let a = aligned.pop().unwrap();
+    (a, b)
+}
+
+/// Convert two double-precision floating point values (``f64``) to strings
+/// with the fewest significant digits that make the strings differ, like
+/// labels for clustered points on a chart. The significant digit limit is
+/// raised from 1 until the strings differ by more than trailing zeros (so
+/// 1.0 and 1.0000000000000002 aren't told apart as "1.0" and "1.00"),
+/// replacing the config's ``max_significant_digits``. If the values are equal
+/// (or the rest of the config keeps them from differing), both are converted
+/// with the config as is, giving identical strings.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_distinguish, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     dtoa_distinguish(1.2345, 1.2391, config),
+///     ("1.23".to_string(), "1.24".to_string())
+/// );
+/// assert_eq!(
+///     dtoa_distinguish(1.5, 1.5, config),
+///     ("1.5".to_string(), "1.5".to_string())
+/// );
+/// ```
+pub fn dtoa_distinguish(a: f64, b: f64, config: FmtFloatConfig) -> (String, String) {
+    if a.to_bits() != b.to_bits() {
+        // 17 significant digits is enough to tell any two f64s apart
+        for sig_digits in 1..=17 {
+            let limited = config.max_significant_digits(sig_digits);
+            let simplified = limited.simplify(true);
+            if dtoa(a, simplified) != dtoa(b, simplified) {
+                return (dtoa(a, limited), dtoa(b, limited));
+            }
+        }
+    }
+    (dtoa(a, config), dtoa(b, config))
+}
+
+/// Convert a double-precision floating point value (``f64``) to two strings,
+/// one with each configuration, like a compact value with a full precision
+/// tooltip. The digits of the value are only extracted once, and shared by
+/// both conversions.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_short_long, FmtFloatConfig};
+///
+/// let short = FmtFloatConfig::default().max_significant_digits(3).round();
+/// let long = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     dtoa_short_long(3.24159, short, long),
+///     ("3.24".to_string(), "3.24159".to_string())
+/// );
+/// ```
+pub fn dtoa_short_long(
+    value: f64,
+    short_config: FmtFloatConfig,
+    long_config: FmtFloatConfig,
+) -> (String, String) {
+    if !value.is_finite() {
+        return (dtoa(value, short_config), dtoa(value, long_config));
+    }
+    let sign = value.is_sign_negative();
+    let (digits, exp) = f64_digits(value);
+    hit!(short_long_shared_digits);
+    let format = |out: &mut String, digits, config| {
+        digits_to_a(out, sign, digits, exp, Some(value.abs()), config)
+    };
+    (
+        fit_bytes(digits.clone(), short_config, format).text,
+        fit_bytes(digits, long_config, format).text,
+    )
+}
+
+/// Convert a batch of double-precision floating point values (``f64``) to
+/// strings with a shared power of ten factored out, for displaying vectors and
+/// matrices. The shared exponent is the one the largest finite value has in
+/// scientific notation (or 0 if there are no finite nonzero values), and each
+/// value is written without scientific notation, divided by 10 to that
+/// exponent. The digits are shifted exactly, so ``value`` equals the string
+/// times 10 to the exponent, up to the config's precision.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_factored, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     dtoa_factored(&[1500.0, -250.0, 3.0], config),
+///     (3, vec!["1.5".to_string(), "-0.25".to_string(), "0.003".to_string()])
+/// );
+/// ```
+pub fn dtoa_factored(values: &[f64], config: FmtFloatConfig) -> (i32, Vec<String>) {
+    let exponent = values
+        .iter()
+        .filter(|value| value.is_finite() && **value != 0.0)
+        .map(|value| f64_digits(*value).1 - 1)
+        .max()
+        .unwrap_or(0);
+    let config = config.force_no_e_notation();
+    let strings = values
+        .iter()
+        .map(|value| {
+            if !value.is_finite() {
+                return dtoa(*value, config);
+            }
+            let (digits, e) = f64_digits(*value);
+            // Moving the decimal point doesn't change zero
+            let e = if *value == 0.0 { e } else { e - exponent };
+            fit_bytes(digits, config, |out, digits, config| {
+                digits_to_a(out, value.is_sign_negative(), digits, e, None, config)
+            })
+            .text
+        })
+        .collect();
+    (exponent, strings)
+}
+
+/// Pick "nice" tick values for a plot axis from ``min`` to ``max``, and format
+/// them. The step between ticks is 1, 2, or 5 times a power of ten, chosen
+/// to give about ``count`` ticks (at least 2), and the ticks start at or below
+/// ``min`` and end at or above ``max``. The ticks are written in the same
+/// notation (see ``dtoa_uniform_notation``), and without scientific notation,
+/// they all have as many decimal digits as the step.
+///
+/// If ``min`` and ``max`` are equal, that value is the only tick, and if
+/// either isn't finite, there are no ticks. If the range is too narrow for
+/// its magnitude to have distinct ticks, the ticks are just ``min`` and ``max``.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{nice_ticks, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(
+///     nice_ticks(0.0, 1.0, 5, config),
+///     vec!["0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]
+/// );
+/// assert_eq!(
+///     nice_ticks(-3.7, 12.2, 4, config),
+///     vec!["-5.0", "0.0", "5.0", "10.0", "15.0"]
+/// );
+/// ```
+pub fn nice_ticks(min: f64, max: f64, count: usize, config: FmtFloatConfig) -> Vec<String> {
+    if !min.is_finite() || !max.is_finite() {
+        return Vec::new();
+    }
+    let (min, max) = if min <= max { (min, max) } else { (max, min) };
+    if min == max {
+        return vec![dtoa(min, config)];
+    }
+    let raw_step = (max - min) / (count.max(2) - 1) as f64;
+    if !raw_step.is_finite() {
+        return dtoa_uniform_notation(&[min, max], config);
+    }
+    // Round the first two digits of the raw step to 10, 20, 50, or 100,
+    // so the step is <multiple> * 10^<power>
+    let (digits, e) = f64_digits(raw_step);
+    let digit = |i: usize| match digits.get(i) {
+        Some(digit) => (digit - digit_to_u8(0)) as i64,
+        None => 0,
+    };
+    let leading = digit(0) * 10 + digit(1);
+    let (multiple, power) = match leading {
+        0..=14 => (1, e - 1),
+        15..=29 => (2, e - 1),
+        30..=69 => (5, e - 1),
+        _ => (1, e),
+    };
+    // Parse each tick from its decimal digits, so errors don't add up
+    let tick = |i: i64| {
+        format!("{}e{}", i.saturating_mul(multiple), power)
+            .parse::<f64>()
+            .unwrap()
+    };
+    let step = tick(1);
+    // Past 2^52 steps from zero, neighbouring ticks can round to the same
+    // float, and the tick indices could overflow, so the range is too narrow
+    // for its magnitude to have distinct ticks
+    let index_limit = (1u64 << 52) as f64;
+    if (min / step).abs() >= index_limit || (max / step).abs() >= index_limit {
+        return dtoa_uniform_notation(&[min, max], config);
+    }
+    // The last tick at or below min and the first at or above max. Dividing
+    // can be off by one, so check against the ticks themselves
+    let mut first = (min / step) as i64;
+    while tick(first) > min {
+        first -= 1;
+    }
+    while tick(first + 1) <= min {
+        first += 1;
+    }
+    let mut last = (max / step) as i64;
+    while tick(last) < max {
+        last += 1;
+    }
+    while tick(last - 1) >= max {
+        last -= 1;
+    }
+    let ticks: Vec<f64> = (first..=last).map(tick).collect();
+    let scientific = ticks
+        .iter()
+        .filter(|tick| **tick != 0.0)
+        .any(|tick| needs_e_notation(f64_digits(*tick).1, config));
+    let config = if !scientific && power < 0 {
+        config.min_decimal_digits(-power as i16)
+    } else {
+        config
+    };
+    dtoa_uniform_notation(&ticks, config)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string with
+/// exactly ``decimals`` digits after the decimal point, the same way as C's
+/// ``printf("%.*f", decimals, value)``. The exact binary value is rounded, with
+/// ties going to the even digit, so ``2.675`` (which is slightly below 2.675
+/// as a float) gives ``"2.67"`` with 2 decimals, and ``0.125`` gives ``"0.12"``.
+///
+/// Negative values that round to zero keep their sign, and special values are
+/// written as ``"nan"``, ``"-nan"``, ``"inf"``, and ``"-inf"``, matching glibc.
+/// The digits are generated and rounded by this crate, so the output doesn't
+/// depend on the version of the standard library. ``decimals`` is capped at
+/// ``MAX_FIXED_DECIMALS``, where every double is already exact.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::format_fixed;
+///
+/// assert_eq!(format_fixed(2.5, 0), "2");
+/// assert_eq!(format_fixed(0.1, 20), "0.10000000000000000555");
+/// assert_eq!(format_fixed(-0.01, 1), "-0.0");
+/// ```
+pub fn format_fixed(value: f64, decimals: u32) -> String {
+    if value.is_nan() {
+        if value.is_sign_negative() {
+            return "-nan".to_string();
+        }
+        return "nan".to_string();
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            return "inf".to_string();
+        }
+        return "-inf".to_string();
+    }
+    // Every double is exact with this many decimals, so more would only
+    // add zeros
+    let decimals = decimals.min(MAX_FIXED_DECIMALS) as i32;
+    let mut exact = [0; EXACT_LEN];
+    let (mut digits, mut e) = if value == 0.0 {
+        (Digits::zero(), 1)
+    } else {
+        let (digits, e) = exact_digits(value, &mut exact);
+        (Digits::from_slice(digits), e)
+    };
+    // Round the exact digits at the last decimal, ties going to even
+    let mut position = decimals + e;
+    if position < 0 {
+        // The cut is left of the first digit, so pad leading zeros up to it
+        let padding = -position as usize;
+        digits.prepend_zeros(padding);
+        e += padding as i32;
+        position = 0;
+    }
+    let half_even = FmtFloatConfig::default().round_half_even();
+    cut_digits(&mut digits, &mut e, position as usize, None, half_even);
+    let digit_at = |i: i32| match digits.get(i as usize) {
+        Some(digit) if i >= 0 => *digit as char,
+        _ => '0',
+    };
+    let mut res = String::new();
+    if value.is_sign_negative() {
+        res.push('-');
+    }
+    if e <= 0 {
+        res.push('0');
+    }
+    for i in 0..e {
+        res.push(digit_at(i));
+    }
+    if decimals > 0 {
+        res.push('.');
+        for i in e..e + decimals {
+            res.push(digit_at(i));
+        }
+    }
+    res
+}
+
+/// The most digits after the decimal point that ``format_fixed`` writes.
+/// Every double is exact with this many decimals.
+pub const MAX_FIXED_DECIMALS: u32 = 1074;
+
+/// Format a change in a value as a signed delta. Positive values are
+/// written with a "+", and if ``arrows`` is set, nonzero values are
+/// prefixed with "▲ " or "▼ ". Zero (including -0.0) is written the
+/// same as ``dtoa(0.0, config)``, with no sign or arrow. ``max_width``
+/// only applies to the number, not the sign or arrow.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_delta, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_delta(1.5, false, config), "+1.5");
+/// assert_eq!(dtoa_delta(-1.5, true, config), "▼ -1.5");
+/// assert_eq!(dtoa_delta(0.0, true, config), "0.0");
+/// ```
+pub fn dtoa_delta(value: f64, arrows: bool, config: FmtFloatConfig) -> String {
+    if value == 0.0 {
+        return dtoa(0.0, config);
+    }
+    let arrow = if !arrows || value.is_nan() {
+        ""
+    } else if value > 0.0 {
+        "▲ "
+    } else {
+        "▼ "
+    };
+    let plus = if value > 0.0 { "+" } else { "" };
+    // The sign is always written, so there's no need for a sign column
+    format!(
+        "{}{}{}",
+        arrow,
+        plus,
+        dtoa(value, config.sign_column(false))
+    )
+}
+
+/// Convert a series of double-precision floating point values (``f64``) to
+/// strings for a change log, like ``5, +2, -1``: the first value is the
+/// starting point and is written with ``dtoa``, and the rest are changes,
+/// written with ``dtoa_delta`` (without arrows).
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_signed_series, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().add_point_zero(false);
+///
+/// assert_eq!(dtoa_signed_series(&[5.0, 2.0, -1.0], config), vec!["5", "+2", "-1"]);
+/// ```
+pub fn dtoa_signed_series(values: &[f64], config: FmtFloatConfig) -> Vec<String> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| match i {
+            0 => dtoa(value, config),
+            _ => dtoa_delta(value, false, config),
+        })
+        .collect()
+}
+
+/// Format the percent change from ``old`` to ``new`` (``(new - old) / old * 100``)
+/// as a signed delta with a "%", the same as ``dtoa_delta`` without arrows.
+/// When the change is undefined or infinite, which happens when ``old`` is zero
+/// and ``new`` isn't, or either value isn't finite, ``undefined`` is returned
+/// instead.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{format_percent_change, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::auto();
+///
+/// assert_eq!(format_percent_change(80.0, 100.0, "n/a", config), "+25%");
+/// assert_eq!(format_percent_change(100.0, 80.0, "n/a", config), "-20%");
+/// assert_eq!(format_percent_change(0.0, 5.0, "n/a", config), "n/a");
+/// ```
+pub fn format_percent_change(
+    old: f64,
+    new: f64,
+    undefined: &str,
+    config: FmtFloatConfig,
+) -> String {
+    if old == new && old.is_finite() {
+        return format!("{}%", dtoa_delta(0.0, false, config));
+    }
+    let change = (new - old) / old * 100.0;
+    if !change.is_finite() {
+        return undefined.to_string();
+    }
+    format!("{}%", dtoa_delta(change, false, config))
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, the same as ``dtoa``, and quote it so it
+/// can be used as a CSV field. The string is wrapped in double quotes when it
+/// contains the ``delimiter``, a double quote, or a line break (from a unit or
+/// template), and double quotes inside it are doubled.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_csv_field, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_csv_field(1.5, ',', config), "1.5");
+/// assert_eq!(dtoa_csv_field(1.5, ',', config.radix_point(',')), "\"1,5\"");
+/// assert_eq!(dtoa_csv_field(1.5, ';', config.radix_point(',')), "1,5");
+/// ```
+pub fn dtoa_csv_field(value: f64, delimiter: char, config: FmtFloatConfig) -> String {
+    let s = dtoa(value, config);
+    if s.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+/// Format a count compactly with a "K", "M", "B", or "T" suffix (base 1000),
+/// like ``"1.5K"`` or ``"2.3M"``. The value is rounded to the config's
+/// ``max_significant_digits`` (default: 2), without rounding away integer
+/// digits, and trailing zeros are kept up to that many digits unless
+/// ``min_significant_digits`` is set. Values that round up to 1000 of a
+/// suffix use the next one (``999_500 -> "1.0M"``), and values that round
+/// to less than 1000 are written without a suffix or padding. The unit, if
+/// any, goes after the suffix. If ``lowercase`` is set, the suffixes are
+/// written as "k", "m", "b", and "t". The suffix and unit count towards
+/// ``max_width``, and if they don't fit, the string is all pound signs.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{format_compact, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().add_point_zero(false);
+///
+/// assert_eq!(format_compact(1500.0, false, config), "1.5K");
+/// assert_eq!(format_compact(-2_300_000.0, false, config), "-2.3M");
+/// assert_eq!(format_compact(999_500.0, true, config), "1.0m");
+/// assert_eq!(format_compact(42.0, false, config), "42");
+/// ```
+pub fn format_compact(value: f64, lowercase: bool, config: FmtFloatConfig) -> String {
+    const SUFFIXES: [char; 4] = ['K', 'M', 'B', 'T'];
+    if !value.is_finite() {
+        return dtoa(value, config);
+    }
+    let sig_digits = match config.max_sig_digits {
+        Some(digits) => digits.max(1),
+        None => 2,
+    };
+    let rounded = config
+        .max_significant_digits(sig_digits)
+        .keep_integer_digits(true)
+        .force_no_e_notation()
+        .no_unit();
+    // Move to the next suffix while the value would round to 1000 or more
+    let mut scale = 0;
+    let mut divisor = 1.0;
+    let mut scaled = value.abs();
+    while scale < SUFFIXES.len() {
+        let (mut digits, mut e) = f64_digits(scaled);
+        let position = e.max(sig_digits as i32) as usize;
+        cut_digits(&mut digits, &mut e, position, None, rounded);
+        if e <= 3 {
+            break;
+        }
+        scale += 1;
+        divisor *= 1000.0;
+        scaled = value.abs() / divisor;
+    }
+    let (shown, mut formatting) = if scale == 0 {
+        (value, rounded)
+    } else {
+        let padded = match config.min_sig_digits {
+            Some(_) => rounded,
+            None => rounded.min_significant_digits(sig_digits),
+        };
+        (scaled.copysign(value), padded)
+    };
+    // The text after the value: the suffix and the unit
+    let mut tail = String::new();
+    if scale > 0 {
+        let suffix = SUFFIXES[scale - 1];
+        tail.push(if lowercase {
+            suffix.to_ascii_lowercase()
+        } else {
+            suffix
+        });
+    }
+    if let Some(unit) = config.unit {
+        if config.unit_space {
+            tail.push(' ');
+        }
+        tail.push_str(unit);
+    }
+    // Leave room for the tail in max_width and max_byte_width, and write
+    // pound signs if the tail alone doesn't fit or the value doesn't fit
+    // beside it
+    let pounds = match (config.max_width, config.max_byte_width) {
+        (Some(width), Some(bytes)) => Some((width as usize).min(bytes as usize)),
+        (Some(width), None) => Some(width as usize),
+        (None, Some(bytes)) => Some(bytes as usize),
+        (None, None) => None,
+    };
+    if let Some(max_width) = config.max_width {
+        match (max_width as usize).checked_sub(tail.chars().count()) {
+            Some(room) => formatting = formatting.max_width(room as u8),
+            None => return "#".repeat(pounds.unwrap_or(0)),
+        }
+    }
+    if let Some(max_byte_width) = config.max_byte_width {
+        match (max_byte_width as usize).checked_sub(tail.len()) {
+            Some(room) => formatting = formatting.max_byte_width(room as u16),
+            None => return "#".repeat(pounds.unwrap_or(0)),
+        }
+    }
+    let mut s = dtoa(shown, formatting);
+    // The value is all pound signs (or empty, with no room) when it doesn't fit
+    if pounds.is_some() && s.bytes().all(|c| c == b'#') {
+        return "#".repeat(pounds.unwrap_or(0));
+    }
+    s.push_str(&tail);
+    s
+}
+
+/// Names for powers of ten from 1e-24 to 1e24 in steps of 3, in the short
+/// scale (where a billion is 1e9), for ``format_named_scale``
+pub const SHORT_SCALE: [&str; 17] = [
+    "yocto",
+    "zepto",
+    "atto",
+    "femto",
+    "pico",
+    "nano",
+    "micro",
+    "milli",
+    "",
+    "thousand",
+    "million",
+    "billion",
+    "trillion",
+    "quadrillion",
+    "quintillion",
+    "sextillion",
+    "septillion",
+];
+
+/// Names for powers of ten from 1e-24 to 1e24 in steps of 3, in the long
+/// scale (where a billion is 1e12), for ``format_named_scale``
+pub const LONG_SCALE: [&str; 17] = [
+    "yocto",
+    "zepto",
+    "atto",
+    "femto",
+    "pico",
+    "nano",
+    "micro",
+    "milli",
+    "",
+    "thousand",
+    "million",
+    "milliard",
+    "billion",
+    "billiard",
+    "trillion",
+    "trilliard",
+    "quadrillion",
+];
+
+/// Format a value with its power of ten written as a word after the
+/// mantissa, like ``"1.5 million"`` or ``"1.5 milli"``, using ``names`` (such
+/// as ``SHORT_SCALE`` or ``LONG_SCALE``) for powers from 1e-24 to 1e24 in
+/// steps of 3. This works like ``si_prefixes``: the power is rounded down to
+/// a multiple of 3, the rest of the magnitude is moved into the mantissa, and
+/// values from 1 to 1000 have no name. Values outside of the names' range,
+/// and infinities and NaN, are formatted with ``dtoa`` instead. The name and
+/// the space before it count towards ``max_width``: the mantissa is rounded to
+/// fewer significant digits until the string fits, and values that can't fit
+/// are represented by pound signs.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{format_named_scale, FmtFloatConfig, LONG_SCALE, SHORT_SCALE};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(format_named_scale(1_500_000.0, &SHORT_SCALE, config), "1.5 million");
+/// assert_eq!(format_named_scale(0.0015, &SHORT_SCALE, config), "1.5 milli");
+/// assert_eq!(format_named_scale(2e9, &SHORT_SCALE, config), "2.0 billion");
+/// assert_eq!(format_named_scale(2e9, &LONG_SCALE, config), "2.0 milliard");
+/// ```
+pub fn format_named_scale(value: f64, names: &[&str; 17], config: FmtFloatConfig) -> String {
+    let max_width = match config.max_width {
+        Some(max_width) => max_width,
+        None => return named_scale(value, names, config).unwrap_or_else(|| dtoa(value, config)),
+    };
+    // max_width would be counted with a one character prefix, so fewer
+    // significant digits are tried until the string with the name fits
+    let most = config.max_sig_digits.unwrap_or(17).max(1);
+    for sig_digits in (1..=most).rev() {
+        let limited = config.no_max_width().max_significant_digits(sig_digits);
+        let s = match named_scale(value, names, limited) {
+            Some(s) => s,
+            None => return dtoa(value, config),
+        };
+        if s.chars().count() <= max_width as usize {
+            return s;
+        }
+    }
+    "#".repeat(max_width as usize)
+}
+
+// The string for a value with its power of ten written using <names>, or
+// None if it's out of their range
+fn named_scale(value: f64, names: &[&str; 17], config: FmtFloatConfig) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+    let formatted = dtoa_structured(value, config.si_prefixes(true).force_e_notation());
+    let (index, exponent) = match (formatted.exponent_index, formatted.exponent) {
+        (Some(index), Some(exponent)) => (index, exponent),
+        _ => return Some(formatted.text),
+    };
+    if !(-24..=24).contains(&exponent) {
+        return None;
+    }
+    let slot = (exponent / 3 + 8) as usize;
+    let rest = &formatted.text[index + SI_PREFIXES[slot].len()..];
+    Some(format!(
+        "{} {}{}",
+        formatted.text[..index].trim_end(),
+        names[slot],
+        rest
+    ))
+}
+
+/// Format a measurement and its uncertainty as ``"(value ± uncertainty)"``,
+/// following the scientific convention: the uncertainty is rounded to
+/// ``unc_sig`` significant digits (at least 1), and the value is rounded to the
+/// same decimal place as the last digit of the uncertainty. Both are rounded
+/// using the config's rounding mode, and written without scientific notation.
+/// Trailing zeros are kept up to that decimal place, since they're significant.
+///
+/// If the uncertainty is zero, NaN, or infinite, or the value isn't finite,
+/// both are formatted with ``dtoa`` instead.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_sigfig_uncertainty, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_sigfig_uncertainty(3.14159, 0.0031, 1, config), "(3.142 ± 0.003)");
+/// assert_eq!(dtoa_sigfig_uncertainty(2.0, 0.05, 1, config), "(2.00 ± 0.05)");
+/// assert_eq!(dtoa_sigfig_uncertainty(1234.5678, 31.0, 1, config), "(1230 ± 30)");
+/// ```
+pub fn dtoa_sigfig_uncertainty(
+    value: f64,
+    uncertainty: f64,
+    unc_sig: u8,
+    config: FmtFloatConfig,
+) -> String {
+    if !value.is_finite() || !uncertainty.is_finite() || uncertainty == 0.0 {
+        return format!("({} ± {})", dtoa(value, config), dtoa(uncertainty, config));
+    }
+    let (mut unc_digits, mut unc_e) = f64_digits(uncertainty.abs());
+    let unc_value = Some(uncertainty.abs());
+    cut_digits(
+        &mut unc_digits,
+        &mut unc_e,
+        unc_sig.max(1) as usize,
+        unc_value,
+        config,
+    );
+    // The number of decimal places the uncertainty has
+    let decimals = unc_sig.max(1) as i32 - unc_e;
+    let (mut digits, mut e) = f64_digits(value);
+    let position = decimals + e;
+    if position >= 0 {
+        cut_digits(
+            &mut digits,
+            &mut e,
+            position as usize,
+            Some(value.abs()),
+            config,
+        );
+    } else {
+        // The value is too small to have any digits at that decimal place
+        digits = Digits::zero();
+        e = 1;
+    }
+    let config = config
+        .force_no_e_notation()
+        .no_max_width()
+        .min_decimal_digits(decimals.max(0) as i16)
+        .trailing_point(if decimals > 0 {
+            config.trailing_point
+        } else {
+            TrailingPoint::Never
+        });
+    let sign = value.is_sign_negative();
+    let mut res = String::from("(");
+    format_digits(&mut res, sign, digits, e, false, None, config);
+    res.push_str(" ± ");
+    format_digits(&mut res, false, unc_digits, unc_e, false, None, config);
+    res.push(')');
+    res
+}
+
+/// Write a double-precision floating point value (``f64``) as a simple fraction
+/// like ``"22/7"``, if there is a fraction with a denominator of at most
+/// ``max_denominator`` that is within ``tolerance`` of the value. The fraction
+/// is the first continued fraction convergent of the value that is close
+/// enough, so simpler fractions are preferred. Values
+/// that are within ``tolerance`` of an integer are written as an integer. If
+/// there is no such fraction, the value is formatted with ``dtoa`` instead.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_fraction, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_fraction(0.5, 100, 1e-9, config), "1/2");
+/// assert_eq!(dtoa_fraction(-0.75, 100, 1e-9, config), "-3/4");
+/// assert_eq!(dtoa_fraction(2.0f64.sqrt(), 100, 1e-9, config), "1.4142135623730951");
+/// ```
+pub fn dtoa_fraction(
+    value: f64,
+    max_denominator: u64,
+    tolerance: f64,
+    config: FmtFloatConfig,
+) -> String {
+    match best_fraction(value.abs(), max_denominator, tolerance) {
+        Some((numerator, 1)) => {
+            format!("{}{}", if value < 0.0 { "-" } else { "" }, numerator)
+        }
+        Some((numerator, denominator)) => format!(
+            "{}{}/{}",
+            if value < 0.0 { "-" } else { "" },
+            numerator,
+            denominator
+        ),
+        None => dtoa(value, config),
+    }
+}
+
+/// Write a double-precision floating point value (``f64``) in English words
+/// if it's a whole number from 0 up to (but not including) ``spell_below``,
+/// like ``"three"`` or ``"twenty-one"``. Other values, including negative
+/// numbers and negative zero, are formatted with ``dtoa``. Style guides often
+/// spell out numbers below 10.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_words, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default().add_point_zero(false);
+///
+/// assert_eq!(dtoa_words(3.0, 10, config), "three");
+/// assert_eq!(dtoa_words(11.0, 10, config), "11");
+/// assert_eq!(dtoa_words(3.5, 10, config), "3.5");
+/// assert_eq!(dtoa_words(342.0, 1000, config), "three hundred forty-two");
+/// ```
+pub fn dtoa_words(value: f64, spell_below: u64, config: FmtFloatConfig) -> String {
+    // Values at least 2^64 aren't below any u64
+    if value.is_sign_positive() && value < spell_below as f64 && value as u64 as f64 == value {
+        number_words(value as u64)
+    } else {
+        dtoa(value, config)
+    }
+}
+
+// A whole number in English words
+fn number_words(n: u64) -> String {
+    const ONES: [&str; 20] = [
+        "zero",
+        "one",
+        "two",
+        "three",
+        "four",
+        "five",
+        "six",
+        "seven",
+        "eight",
+        "nine",
+        "ten",
+        "eleven",
+        "twelve",
+        "thirteen",
+        "fourteen",
+        "fifteen",
+        "sixteen",
+        "seventeen",
+        "eighteen",
+        "nineteen",
+    ];
+    const TENS: [&str; 10] = [
+        "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+    ];
+    const SCALES: [(u64, &str); 6] = [
+        (1_000_000_000_000_000_000, "quintillion"),
+        (1_000_000_000_000_000, "quadrillion"),
+        (1_000_000_000_000, "trillion"),
+        (1_000_000_000, "billion"),
+        (1_000_000, "million"),
+        (1_000, "thousand"),
+    ];
+    if n < 20 {
+        return ONES[n as usize].to_string();
+    }
+    if n < 100 {
+        return match n % 10 {
+            0 => TENS[(n / 10) as usize].to_string(),
+            ones => format!("{}-{}", TENS[(n / 10) as usize], ONES[ones as usize]),
+        };
+    }
+    let (scale, name) = match SCALES.iter().find(|(scale, _)| n >= *scale) {
+        Some(&(scale, name)) => (scale, name),
+        None => (100, "hundred"),
+    };
+    let mut res = format!("{} {}", number_words(n / scale), name);
+    let rest = n % scale;
+    if rest > 0 {
+        res.push(' ');
+        res.push_str(&number_words(rest));
+    }
+    res
+}
+
+/// Write a double-precision floating point value (``f64``) as a repeating
+/// decimal, with the repeating digits in parentheses, like ``"0.(3)"`` for
+/// 1/3. This is used when the value is exactly the float closest to a
+/// fraction whose decimal repeats every ``max_period`` digits or fewer.
+/// Only the sign and ``radix_point`` of the config are used for repeating
+/// decimals. Values without such a fraction, including values whose decimal
+/// ends, are formatted with ``dtoa`` instead.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_repeating, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_repeating(1.0 / 3.0, 6, config), "0.(3)");
+/// assert_eq!(dtoa_repeating(-1.0 / 6.0, 6, config), "-0.1(6)");
+/// assert_eq!(dtoa_repeating(1.0 / 7.0, 6, config), "0.(142857)");
+/// assert_eq!(dtoa_repeating(0.25, 6, config), "0.25");
+/// ```
+pub fn dtoa_repeating(value: f64, max_period: u8, config: FmtFloatConfig) -> String {
+    let (numerator, denominator) = match best_fraction(value.abs(), u64::MAX, 0.0) {
+        Some(fraction) => fraction,
+        None => return dtoa(value, config),
+    };
+    // The digits before the repeating part come from factors of 2 and 5
+    // in the denominator, and the rest decides the period
+    let mut rest = denominator;
+    let (mut twos, mut fives) = (0, 0);
+    while rest % 2 == 0 {
+        rest /= 2;
+        twos += 1;
+    }
+    while rest % 5 == 0 {
+        rest /= 5;
+        fives += 1;
+    }
+    if rest == 1 {
+        return dtoa(value, config);
+    }
+    // The period is the smallest p where 10^p is 1 more than a multiple of rest
+    let mut period = 1;
+    let mut power = 10 % rest as u128;
+    while power != 1 && period <= max_period as usize {
+        power = power * 10 % rest as u128;
+        period += 1;
+    }
+    if period > max_period as usize {
+        return dtoa(value, config);
+    }
+    let mut res = String::new();
+    if value.is_sign_negative() {
+        res.push('-');
+    }
+    res.push_str(&format!("{}", numerator / denominator));
+    res.push(config.radix_point);
+    // Long division, with the repeating digits in parentheses
+    let mut remainder = (numerator % denominator) as u128;
+    let pre_period = twos.max(fives);
+    for i in 0..pre_period + period {
+        if i == pre_period {
+            res.push('(');
+        }
+        remainder *= 10;
+        let digit = (remainder / denominator as u128) as u32;
+        res.push(char::from_digit(digit, 10).unwrap());
+        remainder %= denominator as u128;
+    }
+    res.push(')');
+    res
+}
+
+// Multiply a little-endian big integer by <m> and add <add>
+fn big_mul_add(n: &mut Vec<u32>, m: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in n.iter_mut() {
+        let x = *limb as u64 * m as u64 + carry;
+        *limb = x as u32;
+        carry = x >> 32;
+    }
+    if carry > 0 {
+        n.push(carry as u32);
+    }
+}
+
+// Divide a little-endian big integer by <d> in place, giving the remainder
+fn big_div_rem(n: &mut Vec<u32>, d: u32) -> u32 {
+    let mut rem = 0u64;
+    for limb in n.iter_mut().rev() {
+        let x = (rem << 32) | *limb as u64;
+        *limb = (x / d as u64) as u32;
+        rem = x % d as u64;
+    }
+    while n.last() == Some(&0) {
+        n.pop();
+    }
+    rem as u32
+}
+
+// Add 2^<bit> to a little-endian big integer
+fn big_add_pow2(n: &mut Vec<u32>, bit: usize) {
+    let mut index = bit / 32;
+    if n.len() <= index {
+        n.resize(index + 1, 0);
+    }
+    let mut carry = 1u64 << (bit % 32);
+    while carry > 0 {
+        if index == n.len() {
+            n.push(0);
+        }
+        let x = n[index] as u64 + carry;
+        n[index] = x as u32;
+        carry = x >> 32;
+        index += 1;
+    }
+}
+
+// Shift a little-endian big integer right by <bits>
+fn big_shr(n: &[u32], bits: usize) -> Vec<u32> {
+    let (limbs, bits) = (bits / 32, bits % 32);
+    let mut res: Vec<u32> = Vec::new();
+    for i in limbs..n.len() {
+        let high = n.get(i + 1).map_or(0, |&x| (x as u64) << 32);
+        res.push(((high | n[i] as u64) >> bits) as u32);
+    }
+    while res.last() == Some(&0) {
+        res.pop();
+    }
+    res
+}
+
+// The number of bits in a little-endian big integer
+fn big_bits(n: &[u32]) -> usize {
+    match n.last() {
+        Some(&last) => n.len() * 32 - last.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+// The digits in <base> of n / (2^d * base^last) truncated, or rounded up
+// from half if <half>, which is (2n + 2^d * base^last) / (2^(d + 1) * base^last)
+// truncated, and whether the division was exact
+fn base_quotient(n: &[u32], d: usize, base: u32, last: i32, half: bool) -> (Vec<u32>, bool) {
+    let mut scaled = n.to_vec();
+    big_mul_add(&mut scaled, 2, 0);
+    let mut exact = true;
+    if last >= 0 {
+        if half {
+            let mut half = vec![1];
+            for _ in 0..last {
+                big_mul_add(&mut half, base, 0);
+            }
+            for _ in 0..d {
+                big_mul_add(&mut half, 2, 0);
+            }
+            let mut carry = 0u64;
+            for i in 0..half.len().max(scaled.len()) {
+                if i == scaled.len() {
+                    scaled.push(0);
+                }
+                let x = scaled[i] as u64 + half.get(i).copied().unwrap_or(0) as u64 + carry;
+                scaled[i] = x as u32;
+                carry = x >> 32;
+            }
+            if carry > 0 {
+                scaled.push(carry as u32);
+            }
+        }
+        for _ in 0..last {
+            exact &= big_div_rem(&mut scaled, base) == 0;
+        }
+    } else {
+        for _ in 0..-last {
+            big_mul_add(&mut scaled, base, 0);
+        }
+        if half {
+            big_add_pow2(&mut scaled, d);
+        }
+    }
+    // The bits shifted out
+    let (limbs, bits) = ((d + 1) / 32, (d + 1) % 32);
+    exact &= scaled.iter().take(limbs).all(|&limb| limb == 0);
+    exact &= scaled.get(limbs).copied().unwrap_or(0) & ((1 << bits) - 1) == 0;
+    let mut rounded = big_shr(&scaled, d + 1);
+    let mut digits = Vec::new();
+    while !rounded.is_empty() {
+        digits.push(big_div_rem(&mut rounded, base));
+    }
+    digits.reverse();
+    (digits, exact)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string of
+/// positional digits in another base, from 2 to 36, like ``"0.6"`` for 0.5 in
+/// base 12. Digits past 9 are the letters ``a`` to ``z``. The digits are
+/// those of the exact value of the float, rounded following ``round_mode``
+/// to ``max_significant_digits``, since most values never end in other
+/// bases. A ``RoundMode::Custom`` function is given digits in the base, with
+/// as many digits past the cut as are kept. Without a limit, enough digits are kept to tell any
+/// two floats apart. Trailing zeros after the radix point are removed, and
+/// only the sign, ``radix_point``, and ``trailing_point`` of the config are
+/// used otherwise. Infinities and NaNs are formatted with ``dtoa``.
+///
+/// # Panics
+///
+/// Panics if ``base`` is less than 2 or greater than 36.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_base, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+///
+/// assert_eq!(dtoa_base(0.5, 12, config), "0.6");
+/// assert_eq!(dtoa_base(255.75, 16, config), "ff.c");
+/// assert_eq!(dtoa_base(-10.0, 2, config), "-1010.0");
+/// assert_eq!(dtoa_base(0.1, 12, config.max_significant_digits(6)), "0.124972");
+/// ```
+pub fn dtoa_base(value: f64, base: u32, config: FmtFloatConfig) -> String {
+    assert!((2..=36).contains(&base), "base must be from 2 to 36");
+    if !value.is_finite() {
+        return dtoa(value, config);
+    }
+    let mut res = String::new();
+    if value.is_sign_negative() {
+        res.push('-');
+    }
+    // The value is exactly n / 2^d
+    let bits = value.to_bits();
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    let (m, k) = match biased {
+        0 => (bits & ((1 << 52) - 1), -1074),
+        _ => ((bits & ((1 << 52) - 1)) | (1 << 52), biased - 1075),
+    };
+    let mut n = vec![m as u32, (m >> 32) as u32];
+    while n.last() == Some(&0) {
+        n.pop();
+    }
+    let d = (-k).max(0) as usize;
+    for _ in 0..k.max(0) {
+        big_mul_add(&mut n, 2, 0);
+    }
+    let mut digits: Vec<u32> = Vec::new();
+    // The power of <base> of the last digit
+    let mut last = 0;
+    if !n.is_empty() {
+        // The power of <base> of the first digit
+        let mut first = -1;
+        let mut integer = big_shr(&n, d);
+        if integer.is_empty() {
+            first = 0;
+            let mut scaled = n.clone();
+            while big_bits(&scaled) <= d {
+                big_mul_add(&mut scaled, base, 0);
+                first -= 1;
+            }
+        } else {
+            while !integer.is_empty() {
+                big_div_rem(&mut integer, base);
+                first += 1;
+            }
+        }
+        let sig_digits = match config.max_sig_digits {
+            Some(sig_digits) => sig_digits.max(1) as i32,
+            None => {
+                // Enough digits to tell apart floats with 53 bit mantissas
+                let mut sig_digits = 1;
+                let mut power = 1u128;
+                while power < 1 << 53 {
+                    power *= base as u128;
+                    sig_digits += 1;
+                }
+                sig_digits
+            }
+        };
+        last = first - sig_digits + 1;
+        // Compare the remainder of the cut with half of the last digit by
+        // also cutting halfway past it
+        digits = base_quotient(&n, d, base, last, false).0;
+        let (halfway, exact) = base_quotient(&n, d, base, last, true);
+        let remainder = if halfway == digits {
+            Ordering::Less
+        } else if exact {
+            Ordering::Equal
+        } else {
+            Ordering::Greater
+        };
+        let round_up = match config.round_mode {
+            RoundMode::Round => remainder != Ordering::Less,
+            RoundMode::Truncate => false,
+            mode @ (RoundMode::HalfEven | RoundMode::HalfDown) => match remainder {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                // An exact tie, which rounds to an even last digit
+                Ordering::Equal => {
+                    mode == RoundMode::HalfEven
+                        && matches!(digits.last(), Some(digit) if digit % 2 == 1)
+                }
+            },
+            RoundMode::Custom(round_up) => {
+                // Give it as many digits again past the cut, which is
+                // truncated where the value doesn't end
+                let extra = (first - last + 1) as usize;
+                let longer = base_quotient(&n, d, base, last - extra as i32, false).0;
+                let ascii: Vec<u8> = longer
+                    .iter()
+                    .map(|&digit| char::from_digit(digit, base).unwrap() as u8)
+                    .collect();
+                round_up(&ascii, ascii.len() - extra)
+            }
+        };
+        if round_up {
+            // Add one to the last digit, carrying into a new digit
+            let mut index = digits.len();
+            loop {
+                if index == 0 {
+                    digits.insert(0, 1);
+                    break;
+                }
+                index -= 1;
+                digits[index] += 1;
+                if digits[index] < base {
                     break;
                 }
+                digits[index] = 0;
+            }
+        }
+    }
+    // Split the digits around the radix point
+    let fraction_len = (-last).max(0) as usize;
+    if digits.len() <= fraction_len {
+        let mut padded = vec![0; fraction_len + 1 - digits.len()];
+        padded.extend(digits);
+        digits = padded;
+    }
+    digits.resize(digits.len() + last.max(0) as usize, 0);
+    let point = digits.len() - fraction_len;
+    let mut end = digits.len();
+    while end > point && digits[end - 1] == 0 {
+        end -= 1;
+    }
+    for &digit in &digits[..point] {
+        res.push(char::from_digit(digit, base).unwrap());
+    }
+    if end > point {
+        res.push(config.radix_point);
+        for &digit in &digits[point..end] {
+            res.push(char::from_digit(digit, base).unwrap());
+        }
+    } else if point_zero(false, config) {
+        res.push(config.radix_point);
+        res.push('0');
+    }
+    res
+}
+
+// The continued fraction convergent of a non-negative value with the smallest
+// denominator that is within <tolerance> of the value
+fn best_fraction(value: f64, max_denominator: u64, tolerance: f64) -> Option<(u64, u64)> {
+    if !value.is_finite() || value >= u64::MAX as f64 {
+        return None;
+    }
+    // The previous two convergents
+    let (mut h1, mut h2) = (1u64, 0u64);
+    let (mut k1, mut k2) = (0u64, 1u64);
+    let mut x = value;
+    loop {
+        if x >= u64::MAX as f64 {
+            return None;
+        }
+        // x is non-negative, so this rounds down
+        let a = x as u64;
+        let h = a.checked_mul(h1)?.checked_add(h2)?;
+        let k = a.checked_mul(k1)?.checked_add(k2)?;
+        if k > max_denominator {
+            return None;
+        }
+        if (value - h as f64 / k as f64).abs() <= tolerance {
+            return Some((h, k));
+        }
+        let remainder = x - a as f64;
+        if remainder == 0.0 {
+            return None;
+        }
+        x = 1.0 / remainder;
+        h2 = h1;
+        h1 = h;
+        k2 = k1;
+        k1 = k;
+    }
+}
+
+/// Format a value both for other programs to read and for humans to read,
+/// using ``FmtFloatConfig::machine()`` and ``FmtFloatConfig::display()``.
+/// Returns (machine, display)
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::format_both;
+///
+/// assert_eq!(
+///     format_both(12345.678901),
+///     ("12345.678901".to_string(), "12,346".to_string())
+/// );
+/// ```
+pub fn format_both(value: f64) -> (String, String) {
+    (
+        dtoa(value, FmtFloatConfig::machine()),
+        dtoa(value, FmtFloatConfig::display()),
+    )
+}
+
+/// A reusable number formatter, which formats floats and integers
+/// consistently using one configuration.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{FmtFloatConfig, NumberFormat};
+///
+/// let format = NumberFormat::new(
+///     FmtFloatConfig::default()
+///         .radix_point(',')
+///         .group_separator('.')
+///         .force_no_e_notation(),
+/// );
+///
+/// assert_eq!(format.format_f64(1234.5), "1.234,5");
+/// assert_eq!(format.format_i64(-1234567), "-1.234.567");
+/// ```
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct NumberFormat {
+    config: FmtFloatConfig,
+}
+
+impl NumberFormat {
+    /// A formatter using a given configuration
+    pub const fn new(config: FmtFloatConfig) -> Self {
+        NumberFormat { config }
+    }
+
+    /// The configuration used by this formatter
+    pub const fn config(&self) -> FmtFloatConfig {
+        self.config
+    }
+
+    /// Format a double-precision float, the same as ``dtoa``
+    pub fn format_f64(&self, value: f64) -> String {
+        dtoa(value, self.config)
+    }
+
+    /// Format a single-precision float, the same as ``ftoa``
+    pub fn format_f32(&self, value: f32) -> String {
+        ftoa(value, self.config)
+    }
+
+    /// Format an integer. Integers are written like floats, but without
+    /// scientific notation (unless needed for ``max_width``) and without a
+    /// trailing ".0". Options like grouping, the radix point, and digit
+    /// limits are applied the same way as for floats.
+    pub fn format_i64(&self, value: i64) -> String {
+        let digits = Digits::from_slice(value.unsigned_abs().to_string().as_bytes());
+        let e = digits.len() as i32;
+        let config = self.config.force_no_e_notation().add_point_zero(false);
+        fit_bytes(digits, config, |out, digits, config| {
+            digits_to_a(out, value < 0, digits, e, None, config)
+        })
+        .text
+    }
+}
+
+impl From<FmtFloatConfig> for NumberFormat {
+    fn from(config: FmtFloatConfig) -> Self {
+        NumberFormat::new(config)
+    }
+}
+
+/// A buffer to format floats into, with the same API as ``ryu::Buffer``
+/// but taking a configuration. The text is written into a fixed array of
+/// ``Buffer::CAPACITY`` bytes instead of a ``String``, and can be borrowed
+/// until the next value is formatted. Text longer than the buffer is handled
+/// like with ``max_byte_width``, so it's the same as ``dtoa`` for anything
+/// that fits.
+///
+/// Like ``ryu::Buffer``, formatting doesn't allocate. The only exceptions
+/// are an ``exponent_formatter`` (which returns a ``String``), a
+/// spoken exponent longer than 64 bytes, and configs that pad a
+/// value with more zeros than fit in the buffer (such as a large
+/// ``min_decimal_digits`` without e notation).
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{Buffer, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default();
+/// let mut buffer = Buffer::new();
+///
+/// assert_eq!(buffer.format(1.234, config), "1.234");
+/// assert_eq!(buffer.format(f64::NAN, config), "NaN");
+/// ```
+#[derive(Clone, Copy)]
+pub struct Buffer {
+    bytes: [u8; Buffer::CAPACITY],
+    // The length of the text, which can be more than the capacity while
+    // formatting, when the text didn't fit and is going to be shortened
+    len: usize,
+    chars: usize,
+}
+
+impl Buffer {
+    /// The most bytes of text the buffer holds
+    pub const CAPACITY: usize = 64;
+
+    /// An empty buffer
+    pub fn new() -> Self {
+        Buffer {
+            bytes: [0; Buffer::CAPACITY],
+            len: 0,
+            chars: 0,
+        }
+    }
+
+    /// Format a double-precision float into the buffer, the same as ``dtoa``,
+    /// and borrow the text
+    pub fn format(&mut self, value: f64, config: FmtFloatConfig) -> &str {
+        let limit = match config.max_byte_width {
+            Some(limit) => limit.min(Buffer::CAPACITY as u16),
+            None => Buffer::CAPACITY as u16,
+        };
+        let config = config.max_byte_width(limit);
+        *self = if !value.is_finite() {
+            fit_special_f64(value, config).0
+        } else {
+            let sign = value.is_sign_negative();
+            let (digits, exp) = f64_digits(value);
+            fit_sink(digits, config, |out: &mut Buffer, digits, config| {
+                digits_to_a(out, sign, digits, exp, Some(value.abs()), config)
+            })
+            .0
+        };
+        self.as_str()
+    }
+
+    /// Format a finite double-precision float into the buffer, the same as
+    /// ``dtoa``, and borrow the text. Like ``ryu::Buffer::format_finite``, the
+    /// text for NaN and infinite values is unspecified (and this panics in
+    /// debug builds)
+    pub fn format_finite(&mut self, value: f64, config: FmtFloatConfig) -> &str {
+        debug_assert!(value.is_finite());
+        self.format(value, config)
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole characters are written when the text fits
+        core::str::from_utf8(&self.bytes[..self.len.min(Buffer::CAPACITY)])
+            .expect("buffer holds whole characters")
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Buffer::new()
+    }
+}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Buffer").field(&self.as_str()).finish()
+    }
+}
+
+// Bytes written past the end of the buffer are dropped, but still counted, so
+// fit_sink sees that the text is too long
+impl fmt::Write for Buffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() <= Buffer::CAPACITY {
+            self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        }
+        self.len += s.len();
+        self.chars += s.chars().count();
+        Ok(())
+    }
+}
+
+impl Sink for Buffer {
+    fn byte_len(&self) -> usize {
+        self.len
+    }
+
+    fn char_len(&self) -> usize {
+        self.chars
+    }
+
+    fn insert_char(&mut self, index: usize, c: char) {
+        let width = c.len_utf8();
+        if self.len + width <= Buffer::CAPACITY {
+            self.bytes.copy_within(index..self.len, index + width);
+            c.encode_utf8(&mut self.bytes[index..index + width]);
+        }
+        self.len += width;
+        self.chars += 1;
+    }
+}
+
+// Change the digit at <position> to the nearest digit in <allowed>, carrying
+// into or borrowing from the digits before it as needed. Ties go to the
+// larger value
+fn snap_last_digit(
+    mut digits: Vec<u8>,
+    mut e: i32,
+    mut position: i32,
+    allowed: &[u8],
+) -> (Vec<u8>, i32) {
+    // Make sure there is a digit at <position>
+    while position < 1 {
+        digits.insert(0, digit_to_u8(0));
+        e += 1;
+        position += 1;
+    }
+    digits.resize(digits.len().max(position as usize), digit_to_u8(0));
+    digits.truncate(position as usize);
+    let l = digits.len() - 1;
+    let last = (digits[l] - digit_to_u8(0)) as i32;
+    // Borrowing is only possible if there is a non-zero digit before the last
+    let can_borrow = digits[..l].iter().any(|&d| d != digit_to_u8(0));
+    let mut delta: Option<i32> = None;
+    for &a in allowed.iter().filter(|&&a| a <= 9) {
+        for candidate in &[a as i32 - 10, a as i32, a as i32 + 10] {
+            if *candidate < 0 && !can_borrow {
+                continue;
+            }
+            let new_delta = candidate - last;
+            delta = match delta {
+                Some(d) if d.abs() < new_delta.abs() => Some(d),
+                Some(d) if d.abs() == new_delta.abs() && d > new_delta => Some(d),
+                _ => Some(new_delta),
+            };
+        }
+    }
+    let delta = match delta {
+        Some(delta) => delta,
+        None => return (digits, e),
+    };
+    let new_last = last + delta;
+    if new_last >= 10 {
+        // carry
+        digits[l] = digit_to_u8((new_last - 10) as u8);
+        let mut i = l;
+        loop {
+            if i == 0 {
+                digits.insert(0, digit_to_u8(1));
+                e += 1;
+                break;
+            }
+            i -= 1;
+            digits[i] += 1;
+            if digits[i] != digit_to_u8(10) {
+                break;
+            }
+            digits[i] = digit_to_u8(0);
+        }
+    } else if new_last < 0 {
+        // borrow
+        digits[l] = digit_to_u8((new_last + 10) as u8);
+        let mut i = l - 1;
+        while digits[i] == digit_to_u8(0) {
+            digits[i] = digit_to_u8(9);
+            i -= 1;
+        }
+        digits[i] -= 1;
+    } else {
+        digits[l] = digit_to_u8(new_last as u8);
+    }
+    // Remove the leading and trailing zeros that may have been created
+    let leading_zeros = digits.iter().take_while(|&&d| d == digit_to_u8(0)).count();
+    if leading_zeros == digits.len() {
+        return (vec![digit_to_u8(0)], 1);
+    }
+    digits.drain(..leading_zeros);
+    e -= leading_zeros as i32;
+    while digits.last() == Some(&digit_to_u8(0)) {
+        digits.pop();
+    }
+    (digits, e)
+}
+
+/// Convert a double-precision floating point value (``f64``) to a string
+/// using a given configuration, with the last significant digit rounded
+/// to the nearest digit in ``allowed``, carrying into earlier digits if
+/// needed. The last significant digit is the one at the precision set
+/// by ``max_decimal_digits`` or ``max_significant_digits`` (whichever is
+/// more restrictive, and following ``keep_integer_digits``), or otherwise the
+/// last digit of the shortest round-trip representation (but never a digit
+/// right of the decimal point for integers). If ``max_width`` removes more
+/// digits than that, the last digit that fits is snapped instead.
+///
+/// Ties between two allowed digits are rounded up to the larger magnitude.
+/// Values in ``allowed`` greater than 9 are ignored, and if there are
+/// no allowed digits, this is the same as ``dtoa``.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::{dtoa_snap_last, FmtFloatConfig};
+///
+/// let config = FmtFloatConfig::default()
+///     .max_decimal_digits(2)
+///     .min_decimal_digits(2);
+///
+/// assert_eq!(dtoa_snap_last(4.93, &[0, 5], config), "4.95");
+/// assert_eq!(dtoa_snap_last(4.98, &[0, 5], config), "5.00");
+/// ```
+pub fn dtoa_snap_last(value: f64, allowed: &[u8], config: FmtFloatConfig) -> String {
+    if value.is_nan() || value.is_infinite() {
+        return dtoa(value, config);
+    }
+    let sign = value.is_sign_negative();
+    let (exact, exact_e) = f64_digits(value);
+    let (digits, e, config) = limit_digits(exact.clone(), exact_e, Some(value.abs()), config);
+    let mut position = snap_position(&digits, e, config);
+    // max_width can remove more digits than the limits, so move the snap
+    // left until the snapped digits are written without being cut
+    let (digits, e) = loop {
+        let (snapped, snapped_e) = snap_last_digit(digits.to_vec(), e, position, allowed);
+        // The number of snapped digits up to and including the snapped one,
+        // which moves with any carry
+        let len = position + snapped_e - e;
+        if position <= 1 || len <= 0 {
+            break (snapped, snapped_e);
+        }
+        // Check the width with a non-zero last digit, so a snapped 0
+        // that would be cut doesn't look like a trailing zero
+        let mut probe = snapped.clone();
+        probe.resize(len as usize, digit_to_u8(0));
+        probe[len as usize - 1] = digit_to_u8(1);
+        let probe = Digits::from_slice(&probe);
+        let (_, layout) = fit_sink::<Counter>(probe, config, |out, digits, config| {
+            format_digits(
+                out,
+                sign,
+                digits,
+                snapped_e,
+                true,
+                Some(value.abs()),
+                config,
+            )
+        });
+        if layout.significant_digits >= len as usize {
+            break (snapped, snapped_e);
+        }
+        position -= 1;
+    };
+    let lost = !same_value(&digits, e, &exact, exact_e);
+    let digits = Digits::from_slice(&digits);
+    fit_bytes(digits, config, |out, digits, config| {
+        format_digits(out, sign, digits, e, lost, Some(value.abs()), config)
+    })
+    .text
+}
+
+// The position of the last digit kept by limit_digits, for <digits> (with <e>
+// digits left of the decimal point) that it already limited
+fn snap_position(digits: &[u8], e: i32, config: FmtFloatConfig) -> i32 {
+    let sig_position = config.max_sig_digits.map(|limit| {
+        let limit = limit.max(1) as i32;
+        if config.keep_integer_digits && !needs_e_notation(e, config) {
+            limit.max(e)
+        } else {
+            limit
+        }
+    });
+    let decimal_position = config.max_decimal_digits.map(|limit| {
+        if config.decimal_digits_in_e_notation && needs_e_notation(e, config) {
+            limit.max(0) as i32 + 1
+        } else {
+            limit as i32 + e
+        }
+    });
+    match (sig_position, decimal_position) {
+        (Some(sig), Some(dec)) => sig.min(dec),
+        (Some(sig), None) => sig,
+        (None, Some(dec)) => dec,
+        (None, None) => (digits.len() as i32).max(e),
+    }
+}
+
+/// Convert a double-precision floating point value (``f64``) to a fixed-width
+/// string of 21 digits, such that comparing the strings lexicographically gives
+/// the same order as comparing the values numerically. This is useful for
+/// sortable keys, but is not meant to be read by humans.
+///
+/// The first digit is ``1`` for positive values and zero, and ``0`` for negative
+/// values. The next 3 digits are the (biased) exponent, and the last 17 digits
+/// are the shortest round-trip digits of the value, padded with zeros. For
+/// negative values, the exponent and digits are replaced with their nine's complement,
+/// so that larger magnitudes sort first.
+///
+/// ``0.0`` and ``-0.0`` give the same string. NaN values all give a string that
+/// sorts after positive infinity.
+///
+/// # Example
+///
+/// ```
+/// use pretty_dtoa::dtoa_sortable;
+///
+/// assert_eq!(dtoa_sortable(1.5), "140115000000000000000");
+/// assert_eq!(dtoa_sortable(-1.5), "059884999999999999999");
+/// assert!(dtoa_sortable(-2.0) < dtoa_sortable(-1.5));
+/// assert!(dtoa_sortable(10.0) > dtoa_sortable(9.5));
+/// ```
+pub fn dtoa_sortable(value: f64) -> String {
+    // Larger than any biased exponent of a finite value
+    const INF_EXPONENT: i32 = 999;
+    // Makes the exponent of the smallest subnormal value positive
+    const EXPONENT_BIAS: i32 = 400;
+    const DIGITS_WIDTH: usize = 17;
+    if value.is_nan() {
+        return format!("2{}", "0".repeat(20));
+    }
+    let (exponent, mut digits) = if value == 0.0 {
+        (0, Vec::new())
+    } else if value.is_infinite() {
+        (INF_EXPONENT, vec![digit_to_u8(9); DIGITS_WIDTH])
+    } else {
+        let (digits, e) = f64_digits(value);
+        (e + EXPONENT_BIAS, digits.to_vec())
+    };
+    digits.resize(DIGITS_WIDTH, digit_to_u8(0));
+    let mut res = format!("{:03}", exponent).into_bytes();
+    res.append(&mut digits);
+    if value < 0.0 {
+        for c in res.iter_mut() {
+            *c = digit_to_u8(9) - (*c - digit_to_u8(0));
+        }
+        res.insert(0, digit_to_u8(0));
+    } else {
+        res.insert(0, digit_to_u8(1));
+    }
+    String::from_utf8(res).unwrap()
+}
+
+// Check that every non-NaN f32 with bits in start..=end round trips
+// through ftoa under the default config
+#[cfg(any(test, feature = "slow-tests"))]
+fn verify_round_trip_f32_range(start: u32, end: u32) {
+    let config = FmtFloatConfig::default();
+    for bits in start..=end {
+        let val = f32::from_bits(bits);
+        if val.is_nan() {
+            continue;
+        }
+        let as_string = ftoa(val, config);
+        let round = as_string.parse::<f32>().unwrap();
+        assert!(
+            round.to_bits() == bits,
+            "Found bad example for round trip: bits {:#010x} give string '{}' which turns into value '{}'",
+            bits,
+            as_string,
+            round
+        );
+    }
+}
+
+/// Check that every non-NaN ``f32`` round trips through ``ftoa`` under the
+/// default config, panicking on the first one that doesn't. This takes a
+/// while, so it's only built with the ``slow-tests`` feature, and it runs in
+/// parallel with the ``rayon`` feature. Run it with
+/// ``cargo test --release --features slow-tests,rayon``.
+#[doc(hidden)]
+#[cfg(feature = "slow-tests")]
+pub fn verify_round_trip_exhaustive_f32() {
+    // Blocks of 2^16 bit patterns, by their top 16 bits
+    let block = |high: u32| verify_round_trip_f32_range(high << 16, (high << 16) | 0xffff);
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        (0..=0xffffu32).into_par_iter().for_each(block);
+    }
+    #[cfg(not(feature = "rayon"))]
+    (0..=0xffffu32).for_each(block);
+}
+
+#[cfg(test)]
+mod tests {
+    // Macro for checking coverage marks
+    macro_rules! check {
+        ($ident:ident) => {
+            #[no_mangle]
+            static $ident: $crate::__rt::AtomicUsize = $crate::__rt::AtomicUsize::new(0);
+            let _guard = $crate::__rt::Guard::new(&$ident, stringify!($ident));
+        };
+    }
+
+    use super::*;
+
+    use rand::Rng;
+
+    #[test]
+    fn test_widths() {
+        check!(test_widths_internal);
+        // Test random floats with several configurations, and
+        // make sure that .max_width(_) does its job
+        let mut rng = rand::thread_rng();
+
+        let configs = &[
+            FmtFloatConfig::default(),
+            FmtFloatConfig::default().force_no_e_notation(),
+            FmtFloatConfig::default().add_point_zero(true),
+            FmtFloatConfig::default()
+                .truncate()
+                .force_e_notation()
+                .min_significant_digits(5),
+        ];
+
+        for width in 5..=13 {
+            for i in 0..20000 {
+                let config = configs[i % configs.len()].max_width(width);
+                let val = f64::from_bits(rng.gen::<u64>());
+                if val.is_nan() {
+                    continue;
+                }
+                let as_string = dtoa(val, config);
+                assert!(
+                    as_string.len() <= width as usize,
+                    "Found bad example for string width: '{}' at width {} gives {}",
+                    val,
+                    width,
+                    as_string
+                );
+                if as_string.starts_with('#') {
+                    assert!(width <= 6, "Found example of a too-wide float: {}", val);
+                    hit!(test_widths_internal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_dtoa() {
+        // Make sure random floats with several configurations
+        // round trip perfectly
+        let mut rng = rand::thread_rng();
+
+        let configs = &[
+            FmtFloatConfig::default(),
+            FmtFloatConfig::default()
+                .force_no_e_notation()
+                .add_point_zero(true),
+            FmtFloatConfig::default().force_e_notation(),
+        ];
+        for _ in 0..20000 {
+            for config in configs.iter().cloned() {
+                let val = f64::from_bits(rng.gen::<u64>());
+                if val.is_nan() {
+                    continue;
+                }
+                let as_string = dtoa(val, config);
+                let round = as_string.parse::<f64>().unwrap();
+                assert!(round == val, "Found bad example for round trip: value '{}' gives string '{}' which turns into value '{}'", val, as_string, round);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_ftoa() {
+        // Test round tripping for random f32's
+        let mut rng = rand::thread_rng();
+
+        let configs = &[
+            FmtFloatConfig::default(),
+            FmtFloatConfig::default()
+                .force_no_e_notation()
+                .add_point_zero(true),
+            FmtFloatConfig::default().force_e_notation(),
+        ];
+        for _ in 0..20000 {
+            for config in configs.iter().cloned() {
+                let val = f32::from_bits(rng.gen::<u32>());
+                if val.is_nan() {
+                    continue;
+                }
+                let as_string = ftoa(val, config);
+                let round = as_string.parse::<f32>().unwrap();
+                assert!(round == val, "Found bad example for round trip: value '{}' gives string '{}' which turns into value '{}'", val, as_string, round);
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_sig_digits() {
+        let config = FmtFloatConfig::default().round().max_significant_digits(5);
+        assert_eq!(dtoa(3.111111, config), "3.1111");
+        assert_eq!(dtoa(123.4567, config), "123.46");
+        assert_eq!(dtoa(0.0001234567, config), "0.00012346");
+        assert_eq!(dtoa(22.29999, config), "22.3");
+        assert_eq!(dtoa(4.1, config), "4.1");
+        let config = FmtFloatConfig::default()
+            .truncate()
+            .max_significant_digits(4);
+        assert_eq!(dtoa(3.999999, config), "3.999");
+        assert_eq!(dtoa(555.5555, config), "555.5");
+        assert_eq!(dtoa(923.1, config), "923.1");
+    }
+
+    #[test]
+    fn test_keep_integer_digits() {
+        let config = FmtFloatConfig::default()
+            .max_significant_digits(3)
+            .keep_integer_digits(true);
+        assert_eq!(dtoa(12345.6, config.force_no_e_notation()), "12346.0");
+        assert_eq!(dtoa(123.45, config), "123.0");
+        assert_eq!(dtoa(1.2345, config), "1.23");
+        assert_eq!(dtoa(12345.6, config.force_e_notation()), "1.23e4");
+    }
+
+    #[test]
+    fn test_auto() {
+        let config = FmtFloatConfig::auto();
+        assert_eq!(dtoa(0.0, config), "0");
+        assert_eq!(dtoa(42.0, config), "42");
+        assert_eq!(dtoa(0.1 + 0.2, config), "0.3");
+        assert_eq!(dtoa(1.23456, config), "1.235");
+        assert_eq!(dtoa(-5.55555, config), "-5.556");
+        assert_eq!(dtoa(123.456, config), "123.5");
+        assert_eq!(dtoa(98765.4321, config), "98765");
+        assert_eq!(dtoa(123456.7, config), "123457");
+        assert_eq!(dtoa(1234567.0, config), "1.235e6");
+        assert_eq!(dtoa(0.0012345, config), "0.001235");
+        assert_eq!(dtoa(0.000012345, config), "1.235e-5");
+        assert_eq!(dtoa(1e100, config), "1.0e100");
+    }
+
+    #[test]
+    fn test_min_sig_digits() {
+        let config = FmtFloatConfig::default().min_significant_digits(5);
+        assert_eq!(dtoa(3.111111, config), "3.111111");
+        assert_eq!(dtoa(12.0, config), "12.000");
+        assert_eq!(dtoa(340.0, config), "340.00");
+        assert_eq!(dtoa(123.4567, config), "123.4567");
+        assert_eq!(dtoa(0.00123, config), "0.0012300");
+    }
+
+    #[test]
+    fn test_max_decimal_digits() {
+        let config = FmtFloatConfig::default().max_decimal_digits(3).round();
+        assert_eq!(dtoa(3.41214, config), "3.412");
+        assert_eq!(dtoa(3.4129, config), "3.413");
+        assert_eq!(dtoa(3.4999, config), "3.5");
+        assert_eq!(dtoa(1293.4129, config), "1293.413");
+        assert_eq!(dtoa(203.4999, config), "203.5");
+        assert_eq!(dtoa(0.002911, config), "0.003");
+        let config = FmtFloatConfig::default().max_decimal_digits(3).truncate();
+        assert_eq!(dtoa(3.41214, config), "3.412");
+        assert_eq!(dtoa(3.4129, config), "3.412");
+        assert_eq!(dtoa(3.4999, config), "3.499");
+        assert_eq!(dtoa(393.4129, config), "393.412");
+        let config = FmtFloatConfig::default()
+            .max_decimal_digits(-3)
+            .truncate()
+            .force_no_e_notation()
+            .add_point_zero(true);
+        assert_eq!(dtoa(123123.0, config), "123000.0");
+        assert_eq!(dtoa(99999.0, config), "99000.0");
+        let config = FmtFloatConfig::default()
+            .max_decimal_digits(-3)
+            .round()
+            .force_no_e_notation()
+            .add_point_zero(true);
+        assert_eq!(dtoa(123123.0, config), "123000.0");
+        assert_eq!(dtoa(123923.0, config), "124000.0");
+        assert_eq!(dtoa(99999.0, config), "100000.0");
+    }
+
+    #[test]
+    fn test_min_integer_digits() {
+        let config = FmtFloatConfig::default().min_integer_digits(3);
+        assert_eq!(dtoa(5.2, config), "005.2");
+        assert_eq!(dtoa(-5.2, config), "-005.2");
+        assert_eq!(dtoa(0.25, config), "000.25");
+        assert_eq!(dtoa(0.0, config), "000.0");
+        assert_eq!(dtoa(12.0, config), "012.0");
+        assert_eq!(dtoa(1234.5, config), "1234.5");
+        assert_eq!(dtoa(-12345.6, config.force_no_e_notation()), "-12345.6");
+        assert_eq!(dtoa(1.5e10, config), "1.5e10");
+        // Padding is grouped with the other digits
+        let config = FmtFloatConfig::default()
+            .min_integer_digits(5)
+            .group_separator(',');
+        assert_eq!(dtoa(5.2, config), "00,005.2");
+        assert_eq!(dtoa(-1234.5, config), "-01,234.5");
+        // Padding counts towards the width
+        let config = FmtFloatConfig::default().min_integer_digits(3);
+        assert_eq!(dtoa(5.25, config.max_width(5)), "005.3");
+        assert_eq!(dtoa(-5.25, config.max_width(6)), "-005.3");
+        assert_eq!(dtoa(0.0625, config.max_width(6)), "000.06");
+    }
+
+    #[test]
+    fn test_decimal_digits_in_e_notation() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .max_decimal_digits(2)
+            .decimal_digits_in_e_notation(true);
+        assert_eq!(dtoa(12345.0, config), "1.23e4");
+        assert_eq!(dtoa(0.0015678, config), "1.57e-3");
+        assert_eq!(dtoa(1.5, config), "1.5e0");
+        assert_eq!(dtoa(9.999, config), "1.0e1");
+        let config = FmtFloatConfig::default()
+            .max_decimal_digits(2)
+            .decimal_digits_in_e_notation(true);
+        // Values not using e notation are unaffected
+        assert_eq!(dtoa(2.34567, config), "2.35");
+        assert_eq!(dtoa(23456.7, config), "2.35e4");
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .max_decimal_digits(2);
+        assert_eq!(dtoa(12345.0, config), "1.2345e4");
+    }
+
+    #[test]
+    fn test_large_decimal_digits() {
+        let config = FmtFloatConfig::default()
+            .force_no_e_notation()
+            .max_decimal_digits(-150);
+        let s = dtoa(1.5e200, config);
+        assert_eq!(s.len(), 203);
+        assert!(s.starts_with("15000"));
+        assert!(s.ends_with("000.0"));
+        let config = FmtFloatConfig::default().max_decimal_digits(-199);
+        assert_eq!(dtoa(1.55e200, config), "1.6e200");
+        assert_eq!(dtoa(-1.55e200, config.truncate()), "-1.5e200");
+        let config = FmtFloatConfig::default().max_decimal_digits(152);
+        assert_eq!(dtoa(1.23456e-150, config), "1.23e-150");
+        assert_eq!(dtoa(1.23456, config), "1.23456");
+        let config = FmtFloatConfig::default()
+            .force_no_e_notation()
+            .min_decimal_digits(300);
+        let s = dtoa(1e-200, config);
+        assert_eq!(s.len(), 302);
+        assert!(s.starts_with("0.000"));
+        assert_eq!(s.matches('1').count(), 1);
+        assert_eq!(s.find('1'), Some(201));
+    }
+
+    #[test]
+    fn test_min_decimal_digits() {
+        let config = FmtFloatConfig::default().min_decimal_digits(3);
+        assert_eq!(dtoa(3.4, config), "3.400");
+        assert_eq!(dtoa(10.0, config), "10.000");
+        assert_eq!(dtoa(100.0, config), "100.000");
+        let config = FmtFloatConfig::default().min_decimal_digits(5);
+        assert_eq!(dtoa(0.023, config), "0.02300");
+        assert_eq!(dtoa(0.123, config), "0.12300");
+        assert_eq!(dtoa(0.12345678, config), "0.12345678");
+    }
+
+    #[test]
+    fn test_upper_e_break() {
+        let config = FmtFloatConfig::default().upper_e_break(3);
+        assert_eq!(dtoa(23.4, config), "23.4");
+        assert_eq!(dtoa(892.3, config), "892.3");
+        assert_eq!(dtoa(1892.3, config), "1.8923e3");
+        assert_eq!(dtoa(71892.3, config), "7.18923e4");
+    }
+
+    #[test]
+    fn test_lower_e_break() {
+        let config = FmtFloatConfig::default().lower_e_break(-3);
+        assert_eq!(dtoa(0.123, config), "0.123");
+        assert_eq!(dtoa(0.0123, config), "0.0123");
+        assert_eq!(dtoa(0.00123, config), "0.00123");
+        assert_eq!(dtoa(0.000123, config), "1.23e-4");
+        assert_eq!(dtoa(0.0000123, config), "1.23e-5");
+    }
+
+    #[test]
+    fn test_ignore_extremes() {
+        let config = FmtFloatConfig::default().ignore_extremes(3);
+        assert_eq!(dtoa(12.1992, config), "12.1992");
+        assert_eq!(dtoa(12.199921, config), "12.2");
+        assert_eq!(dtoa(12.1002, config), "12.1002");
+        assert_eq!(dtoa(12.10002, config), "12.1");
+        let config = FmtFloatConfig::default()
+            .ignore_extremes(3)
+            .add_point_zero(true);
+        assert_eq!(dtoa(99.99, config), "100.0");
+    }
+
+    #[test]
+    fn test_force_e_notation() {
+        let config = FmtFloatConfig::default().force_e_notation();
+        assert_eq!(dtoa(1.0, config), "1.0e0");
+        assert_eq!(dtoa(15.0, config), "1.5e1");
+        assert_eq!(dtoa(0.150, config), "1.5e-1");
+    }
+
+    #[test]
+    fn test_force_no_e_notation() {
+        let config = FmtFloatConfig::default()
+            .force_no_e_notation()
+            .add_point_zero(false);
+        let s = format!("{}", 1.123e20);
+        assert_eq!(dtoa(1.123e20, config), s);
+        let s = format!("{}", 1.123e-20);
+        assert_eq!(dtoa(1.123e-20, config), s);
+    }
+
+    #[test]
+    fn test_prefer_shortest_notation() {
+        let config = FmtFloatConfig::default().prefer_shortest_notation(true);
+        assert_eq!(dtoa(12345.0, config), "12345.0");
+        assert_eq!(dtoa(123456789.0, config), "123456789.0");
+        assert_eq!(dtoa(1000.0, config), "1.0e3");
+        assert_eq!(dtoa(1000.0, config.add_point_zero(false)), "1000");
+        assert_eq!(dtoa(100.0, config.add_point_zero(false)), "100");
+        assert_eq!(dtoa(-1.5e10, config), "-1.5e10");
+        assert_eq!(dtoa(0.001, config), "0.001");
+        // Ties use regular notation
+        assert_eq!(dtoa(0.0001, config), "0.0001");
+        assert_eq!(dtoa(0.00001, config), "1.0e-5");
+        assert_eq!(dtoa(0.000012345, config), "1.2345e-5");
+        // Forced notations take priority
+        assert_eq!(dtoa(1000.0, config.force_no_e_notation()), "1000.0");
+        assert_eq!(dtoa(12345.0, config.force_e_notation()), "1.2345e4");
+        // Both notations round trip
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let val = f64::from_bits(rng.gen::<u64>());
+            if val.is_nan() {
+                continue;
+            }
+            let as_string = dtoa(val, config);
+            assert!(as_string.len() <= dtoa(val, FmtFloatConfig::default()).len());
+            assert!(as_string.parse::<f64>().unwrap() == val);
+        }
+    }
+
+    #[test]
+    fn test_e_notation_if_shorter_by() {
+        let default = FmtFloatConfig::default();
+        let config = default.e_notation_if_shorter_by(3);
+        // The default upper_e_break uses e notation for these
+        assert_eq!(dtoa(12345.0, default), "1.2345e4");
+        assert_eq!(dtoa(12345.0, config), "12345.0");
+        assert_eq!(dtoa(100000.0, config), "1.0e5");
+        assert_eq!(
+            dtoa(100000.0, config.e_notation_if_shorter_by(4)),
+            "100000.0"
+        );
+        // The default lower_e_break uses regular notation for these
+        assert_eq!(dtoa(0.001, default), "0.001");
+        assert_eq!(dtoa(0.000001, config), "0.000001");
+        assert_eq!(dtoa(0.0000001, config), "1.0e-7");
+        assert_eq!(dtoa(0.00001, config), "0.00001");
+        assert_eq!(dtoa(0.00001, config.e_notation_if_shorter_by(1)), "1.0e-5");
+        // 0 uses e notation on ties
+        assert_eq!(dtoa(0.0001, config.e_notation_if_shorter_by(0)), "1.0e-4");
+        assert_eq!(
+            dtoa(0.0001, default.prefer_shortest_notation(true)),
+            "0.0001"
+        );
+        // This overrides prefer_shortest_notation
+        let both = config.prefer_shortest_notation(true);
+        assert_eq!(dtoa(0.00001, both), "0.00001");
+        assert_eq!(dtoa(0.00001, both.no_e_notation_if_shorter_by()), "1.0e-5");
+        // Forced notations take priority
+        assert_eq!(dtoa(100000.0, config.force_no_e_notation()), "100000.0");
+        assert_eq!(dtoa(12345.0, config.force_e_notation()), "1.2345e4");
+    }
+
+    #[test]
+    fn test_suppress_zero_exponent() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .suppress_zero_exponent(true);
+        assert_eq!(dtoa(1.5, config), "1.5");
+        assert_eq!(dtoa(-9.25, config), "-9.25");
+        assert_eq!(dtoa(1.0, config), "1.0");
+        assert_eq!(dtoa(0.0, config), "0.0");
+        assert_eq!(dtoa(15.0, config), "1.5e1");
+        assert_eq!(dtoa(0.15, config), "1.5e-1");
+        assert_eq!(dtoa(1.5, config.suppress_zero_exponent(false)), "1.5e0");
+        assert_eq!(dtoa(1.0, config.add_point_zero(false)), "1");
+        // A whole mantissa still reads back as a float
+        let minimal = config.trailing_point(TrailingPoint::RoundTripMinimal);
+        assert_eq!(dtoa(1.0, minimal), "1.0");
+        assert_eq!(dtoa(10.0, minimal), "1e1");
+        assert_eq!(dtoa(1.0, config.spoken_exponent(true)), "1.0");
+        assert_eq!(dtoa(1.5, config.max_width(3)), "1.5");
+        assert_eq!(dtoa_structured(1.5, config).exponent_index, None);
+        assert_eq!(
+            dtoa_columns(1.5, config),
+            ("1.5".to_string(), String::new())
+        );
+        // Round trip still holds
+        for &val in [1.5, 9.999, 1.0, -3.25, 7.0000001].iter() {
+            assert_eq!(dtoa(val, config).parse::<f64>().unwrap(), val);
+        }
+    }
+
+    #[test]
+    fn test_force_e_notation_powers_of_ten() {
+        let config = FmtFloatConfig::default().force_e_notation();
+        let never = config.trailing_point(TrailingPoint::Never);
+        let cases = [
+            (1.0, "1.0e0", "1e0"),
+            (10.0, "1.0e1", "1e1"),
+            (100.0, "1.0e2", "1e2"),
+            (1000.0, "1.0e3", "1e3"),
+            (0.1, "1.0e-1", "1e-1"),
+            (0.01, "1.0e-2", "1e-2"),
+            (1e22, "1.0e22", "1e22"),
+            (1e-300, "1.0e-300", "1e-300"),
+        ];
+        for &(value, expected, expected_never) in cases.iter() {
+            assert_eq!(dtoa(value, config), expected);
+            assert_eq!(dtoa(-value, config), format!("-{}", expected));
+            assert_eq!(dtoa(value, never), expected_never);
+            assert_eq!(dtoa(value, config).parse::<f64>().unwrap(), value);
+            assert_eq!(dtoa(value, never).parse::<f64>().unwrap(), value);
+        }
+        for &(value, expected) in [(1.0f32, "1.0e0"), (100.0, "1.0e2"), (0.01, "1.0e-2")].iter() {
+            assert_eq!(ftoa(value, config), expected);
+        }
+    }
+
+    #[test]
+    fn test_max_exponent_magnitude() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .max_exponent_magnitude(2);
+        assert_eq!(dtoa(1.5e3, config), "15.0e2");
+        assert_eq!(
+            dtoa(1.5e3, config.trailing_point(TrailingPoint::Never)),
+            "15e2"
+        );
+        assert_eq!(dtoa(1.25e5, config), "1250.0e2");
+        assert_eq!(dtoa(-1e3, config), "-10.0e2");
+        assert_eq!(dtoa(1.5e-3, config), "0.15e-2");
+        assert_eq!(dtoa(1.5e-5, config), "0.0015e-2");
+        // Exponents within the cap are unchanged
+        assert_eq!(dtoa(1.5e2, config), "1.5e2");
+        assert_eq!(dtoa(1.5e-2, config), "1.5e-2");
+        assert_eq!(dtoa(1.5e3, config.no_max_exponent_magnitude()), "1.5e3");
+        // Trailing points follow the e notation policy
+        let minimal = config.trailing_point(TrailingPoint::RoundTripMinimal);
+        assert_eq!(dtoa(1e3, minimal), "10e2");
+        assert_eq!(dtoa(1e2, minimal), "1e2");
+        let formatted = dtoa_structured(-1.5e3, config.radix_point(','));
+        assert_eq!(formatted.text, "-15,0e2");
+        assert_eq!(formatted.radix_index, Some(3));
+        assert_eq!(formatted.exponent_index, Some(5));
+        assert_eq!(dtoa(1.5e3, config.max_exponent_magnitude(0)), "1500.0e0");
+        assert_eq!(dtoa(1.5e3, config.capitalize_e(true)), "15.0E2");
+        // Round trips still hold
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let val = f64::from_bits(rng.gen::<u64>());
+            if !val.is_finite() {
+                continue;
+            }
+            assert!(dtoa(val, config).parse::<f64>().unwrap() == val);
+        }
+    }
+
+    #[test]
+    fn test_capitalize_e() {
+        let config = FmtFloatConfig::default().capitalize_e(true);
+        assert_eq!(dtoa(1.2e8, config), "1.2E8");
+    }
+
+    #[test]
+    fn test_exponent_formatter() {
+        let config = FmtFloatConfig::default().exponent_formatter(|e| format!("e{:+03}", e));
+        assert_eq!(dtoa(1.5e5, config), "1.5e+05");
+        assert_eq!(dtoa(-1.5e-7, config), "-1.5e-07");
+        assert_eq!(dtoa(2.5, config), "2.5");
+        fn roman(e: i32) -> String {
+            let numerals = ["", "I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX"];
+            let sign = if e < 0 { "-" } else { "" };
+            format!(" x 10^{}{}", sign, numerals[e.unsigned_abs() as usize % 10])
+        }
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .exponent_formatter(roman);
+        assert_eq!(dtoa(1.5e3, config), "1.5 x 10^III");
+        assert_eq!(dtoa(0.00025, config), "2.5 x 10^-IV");
+        let config = FmtFloatConfig::default()
+            .exponent_formatter(roman)
+            .capitalize_e(true)
+            .max_width(12);
+        assert_eq!(dtoa(1.2345e7, config), "1.2 x 10^VII");
+    }
+
+    #[test]
+    fn test_spoken_exponent() {
+        let config = FmtFloatConfig::default().spoken_exponent(true);
+        assert_eq!(dtoa(1.5e10, config), "1.5 times ten to the 10");
+        assert_eq!(dtoa(-2.5e-7, config), "-2.5 times ten to the -7");
+        assert_eq!(dtoa(12.5, config), "12.5");
+        assert_eq!(
+            dtoa(2.5, config.force_e_notation()),
+            "2.5 times ten to the 0"
+        );
+        // max_width is ignored
+        assert_eq!(
+            dtoa(1.2345e10, config.max_width(6)),
+            "1.2345 times ten to the 10"
+        );
+        let config = FmtFloatConfig::default()
+            .spoken_exponent(true)
+            .spoken_exponent_template(" x 10 to the power of {}")
+            .capitalize_e(true);
+        assert_eq!(dtoa(1e100, config), "1.0 x 10 to the power of 100");
+    }
+
+    #[test]
+    fn test_add_point_zero() {
+        let config = FmtFloatConfig::default().add_point_zero(true);
+        assert_eq!(dtoa(1230.0, config), "1230.0");
+        assert_eq!(dtoa(129.0, config), "129.0");
+        assert_eq!(dtoa(12.2, config), "12.2");
+        let config = FmtFloatConfig::default().add_point_zero(false);
+        assert_eq!(dtoa(1230.0, config), "1230");
+        assert_eq!(dtoa(129.0, config), "129");
+        assert_eq!(dtoa(12.2, config), "12.2");
+    }
+
+    #[test]
+    fn test_trailing_point() {
+        let always = FmtFloatConfig::default().trailing_point(TrailingPoint::Always);
+        let never = FmtFloatConfig::default().trailing_point(TrailingPoint::Never);
+        let minimal = FmtFloatConfig::default().trailing_point(TrailingPoint::RoundTripMinimal);
+        assert_eq!(always, FmtFloatConfig::default());
+        let mantissas = FmtFloatConfig::default().trailing_point(TrailingPoint::MantissasOnly);
+        assert_eq!(mantissas, FmtFloatConfig::default().add_point_zero(false));
+        assert_eq!(dtoa(12.0, always), "12.0");
+        assert_eq!(dtoa(3e10, always), "3.0e10");
+        assert_eq!(dtoa(12.0, never), "12");
+        assert_eq!(dtoa(3e10, never), "3e10");
+        assert_eq!(dtoa(12.0, minimal), "12.0");
+        assert_eq!(dtoa(3e10, minimal), "3e10");
+        assert_eq!(dtoa(12.0, mantissas), "12");
+        assert_eq!(dtoa(3e10, mantissas), "3.0e10");
+        assert_eq!(dtoa(3e10, mantissas.simplify(true)), "3e10");
+        assert_eq!(dtoa(3e10, mantissas.max_width(4)), "3e10");
+        // Values with a fractional part or multi digit mantissas are unaffected
+        for config in [always, never, minimal, mantissas].iter() {
+            assert_eq!(dtoa(12.5, *config), "12.5");
+            assert_eq!(dtoa(3.5e10, *config), "3.5e10");
+            assert_eq!(dtoa(-0.25, *config), "-0.25");
+        }
+        // With max_width, the ".0" is dropped along with the radix point
+        // when it doesn't fit, the same as with TrailingPoint::Never
+        assert_eq!(dtoa(3e10, always.max_width(6)), "3.0e10");
+        assert_eq!(dtoa(3e10, always.max_width(5)), "3e10");
+        assert_eq!(dtoa(3e10, always.max_width(4)), "3e10");
+        assert_eq!(dtoa(-3e-100, always.max_width(7)), "-3e-100");
+        assert_eq!(dtoa(3e10, minimal.max_width(6)), "3e10");
+        // Integer mantissas follow the same rule with and without a width
+        for &value in &[5e4, -5e4, 5e20, 5e-7, 12345.0] {
+            for config in [always, never, minimal].iter() {
+                let config = config.force_e_notation();
+                let wide = dtoa(value, config);
+                assert_eq!(dtoa(value, config.max_width(wide.len() as u8)), wide);
+                for width in 1..wide.len() as u8 {
+                    let narrow = dtoa(value, config.max_width(width));
+                    if !narrow.starts_with('#') && !narrow.contains('.') {
+                        assert_eq!(
+                            narrow,
+                            dtoa(value, never.force_e_notation().max_width(width))
+                        );
+                    }
+                    assert!(!narrow.contains(".e"), "{:?}", narrow);
+                }
+            }
+        }
+        // The radix index is only reported when there's a radix point
+        assert_eq!(dtoa_structured(3e10, never).radix_index, None);
+        assert_eq!(dtoa_structured(3e10, minimal).exponent_index, Some(1));
+        assert_eq!(dtoa_structured(12.0, minimal).radix_index, Some(2));
+        // RoundTripMinimal strings always read back as floats
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let val = f64::from_bits(rng.gen::<u64>());
+            if !val.is_finite() {
+                continue;
+            }
+            let as_string = dtoa(val, minimal);
+            assert!(as_string.contains(['.', 'e'].as_ref()));
+            assert!(as_string.parse::<f64>().unwrap() == val);
+        }
+    }
+
+    #[test]
+    fn test_simplify() {
+        let config = FmtFloatConfig::default()
+            .max_significant_digits(3)
+            .truncate();
+        assert_eq!(dtoa(3.0001, config), "3.00");
+        assert_eq!(dtoa(3.0001, config.simplify(true)), "3.0");
+        assert_eq!(
+            dtoa(3.0001, config.simplify(true).add_point_zero(false)),
+            "3"
+        );
+        assert_eq!(dtoa(3.1001, config.simplify(true)), "3.1");
+        assert_eq!(dtoa(30001.0, config.simplify(true)), "3.0e4");
+        assert_eq!(
+            dtoa(30001.0, config.simplify(true).add_point_zero(false)),
+            "3e4"
+        );
+        assert_eq!(
+            dtoa(3201.0, config.simplify(true).add_point_zero(false)),
+            "3200"
+        );
+        // Minimum significant digits are respected
+        let config = FmtFloatConfig::default()
+            .max_significant_digits(4)
+            .min_significant_digits(3)
+            .simplify(true);
+        assert_eq!(dtoa(1.00001, config), "1.00");
+        assert_eq!(dtoa(1.20001, config), "1.20");
+        assert_eq!(dtoa(1.23401, config), "1.234");
+        // Minimum decimal digits are respected
+        let config = FmtFloatConfig::default()
+            .max_decimal_digits(3)
+            .min_decimal_digits(1)
+            .simplify(true)
+            .add_point_zero(false);
+        assert_eq!(dtoa(2.00001, config), "2.0");
+        assert_eq!(dtoa(2.50001, config), "2.5");
+        assert_eq!(dtoa(2.0, config), "2.0");
+        let config = FmtFloatConfig::default()
+            .max_decimal_digits(3)
+            .simplify(true)
+            .add_point_zero(false);
+        assert_eq!(dtoa(2.00001, config), "2");
+        assert_eq!(dtoa(0.10001, config), "0.1");
+    }
+
+    #[test]
+    fn test_max_width_specifics() {
+        check!(e_width_case_a);
+        check!(e_width_case_b);
+        check!(e_width_case_c);
+        let config = FmtFloatConfig::default().max_width(6).force_no_e_notation();
+        assert_eq!(dtoa(123.4533, config), "123.45");
+        assert_eq!(dtoa(0.00324, config), "0.0032");
+        assert_eq!(dtoa(-0.0324, config), "-0.032");
+        assert_eq!(dtoa(3e10, config), "3.0e10");
+        assert_eq!(dtoa(3.1e10, config), "3.1e10");
+        let config = FmtFloatConfig::default().max_width(5).force_no_e_notation();
+        assert_eq!(dtoa(3e10, config), "3e10");
+        assert_eq!(dtoa(3.1e10, config), "3e10");
+        let config = FmtFloatConfig::default().max_width(8).force_no_e_notation();
+        assert_eq!(dtoa(3.24e-10, config), "3.24e-10");
+        assert_eq!(dtoa(3.24e10, config), "3.24e10");
+        let config = FmtFloatConfig::default().max_width(5);
+        assert_eq!(dtoa(-3e100, config), "#####");
+    }
+
+    #[test]
+    fn test_width_notation_choice() {
+        // Make sure e notation is only forced by max_width when fixed
+        // notation can't fit, and that values which fit are untouched
+        let mut rng = rand::thread_rng();
+
+        let configs = &[
+            FmtFloatConfig::default().force_no_e_notation(),
+            FmtFloatConfig::default()
+                .force_no_e_notation()
+                .add_point_zero(false),
+            FmtFloatConfig::default().group_separator(','),
+            FmtFloatConfig::default(),
+        ];
+        for width in 3..=24 {
+            for i in 0..4000 {
+                let config = configs[i % configs.len()];
+                let val = f64::from_bits(rng.gen::<u64>()) / 10f64.powi(rng.gen::<u8>() as i32);
+                if val.is_nan() {
+                    continue;
+                }
+                let as_string = dtoa(val, config.max_width(width));
+                assert!(as_string.chars().count() <= width as usize);
+                if as_string.starts_with('#') {
+                    continue;
+                }
+                let sign = val.is_sign_negative();
+                let (digits, e) = f64_digits(val);
+                let fits_fixed = min_width_fixed(&digits, e, sign, config) <= width as i32;
+                if !needs_e_notation(e, config) && !val.is_infinite() {
+                    assert_eq!(
+                        as_string.contains('e'),
+                        !fits_fixed,
+                        "Wrong notation for {} at width {}: {}",
+                        val,
+                        width,
+                        as_string
+                    );
+                }
+                let full = dtoa(val, config);
+                if full.chars().count() <= width as usize {
+                    assert_eq!(as_string, full);
+                    assert!(as_string.replace(',', "").parse::<f64>().unwrap() == val);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_widths() {
+        let config = FmtFloatConfig::default();
+        let (digits, e) = f64_digits(123.456);
+        assert_eq!(min_width_fixed(&digits, e, false, config), 5);
+        assert_eq!(min_width_fixed(&digits, e, true, config), 6);
+        assert_eq!(
+            min_width_fixed(&digits, e, false, config.add_point_zero(false)),
+            3
+        );
+        let (digits, e) = f64_digits(999.9);
+        assert_eq!(min_width_fixed(&digits, e, false, config), 6);
+        assert_eq!(min_width_fixed(&digits, e, false, config.truncate()), 5);
+        let (digits, e) = f64_digits(0.0096);
+        assert_eq!(min_width_fixed(&digits, e, false, config), 4);
+        assert_eq!(min_width_fixed(&digits, e, false, config.truncate()), 5);
+        assert_eq!(min_width_scientific(e, false, config), 4);
+        assert_eq!(min_width_scientific(e, true, config), 5);
+        let (_, e) = f64_digits(1.5e100);
+        assert_eq!(min_width_scientific(e, false, config), 5);
+        let config = FmtFloatConfig::default().force_no_e_notation().max_width(7);
+        // Rounding up to 100000.0 doesn't fit, but 1.0e5 does
+        assert_eq!(dtoa(99999.99, config), "1.0e5");
+        assert_eq!(dtoa(99999.99, config.truncate()), "99999.9");
+    }
+
+    #[test]
+    fn test_radix_point() {
+        let config = FmtFloatConfig::default()
+            .radix_point(',')
+            .add_point_zero(true);
+        assert_eq!(dtoa(123.4, config), "123,4");
+        assert_eq!(dtoa(1.3e10, config), "1,3e10");
+        assert_eq!(dtoa(123.0, config), "123,0");
+    }
+
+    #[test]
+    fn test_snap_last() {
+        let config = FmtFloatConfig::default().max_decimal_digits(2);
+        assert_eq!(dtoa_snap_last(1.23, &[0, 5], config), "1.25");
+        assert_eq!(dtoa_snap_last(1.22, &[0, 5], config), "1.2");
+        assert_eq!(dtoa_snap_last(1.28, &[0, 5], config), "1.3");
+        assert_eq!(dtoa_snap_last(1.98, &[0, 5], config), "2.0");
+        assert_eq!(dtoa_snap_last(9.98, &[0, 5], config), "10.0");
+        assert_eq!(dtoa_snap_last(-1.23, &[0, 5], config), "-1.25");
+        assert_eq!(dtoa_snap_last(1.2, &[5], config), "1.25");
+        assert_eq!(dtoa_snap_last(0.01, &[0, 5], config), "0.0");
+        assert_eq!(dtoa_snap_last(0.03, &[0, 5], config), "0.05");
+        // Ties round up
+        assert_eq!(dtoa_snap_last(1.22, &[0, 4], config), "1.24");
+        // Borrowing from earlier digits
+        assert_eq!(dtoa_snap_last(2.01, &[9], config), "1.99");
+        let config = FmtFloatConfig::default().add_point_zero(false);
+        assert_eq!(dtoa_snap_last(123.0, &[0, 5], config), "125");
+        assert_eq!(dtoa_snap_last(120.0, &[0, 5], config), "120");
+        assert_eq!(dtoa_snap_last(12.34, &[0, 5], config), "12.35");
+        assert_eq!(dtoa_snap_last(12.34, &[], config), "12.34");
+        let config = FmtFloatConfig::default().max_significant_digits(2);
+        assert_eq!(dtoa_snap_last(1234.0, &[0, 5], config), "1000.0");
+        assert_eq!(dtoa_snap_last(1284.0, &[0, 5], config), "1500.0");
+        // Integer digits kept by keep_integer_digits aren't changed
+        let config = FmtFloatConfig::auto();
+        assert_eq!(dtoa(98765.4, config), "98765");
+        assert_eq!(dtoa_snap_last(98765.4, &[0, 5], config), "98765");
+        assert_eq!(dtoa_snap_last(98763.4, &[0, 5], config), "98765");
+        // A limit of 0 significant digits is treated as 1, like in dtoa
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            dtoa_snap_last(7.0, &[0, 5], config.max_significant_digits(0)),
+            dtoa_snap_last(7.0, &[0, 5], config.max_significant_digits(1))
+        );
+        assert_eq!(
+            dtoa_snap_last(7.0, &[0, 5], config.max_significant_digits(0)),
+            "5.0"
+        );
+        // The last digit that fits in max_width is snapped
+        let config = FmtFloatConfig::default().max_width(4);
+        assert_eq!(dtoa_snap_last(1.2345, &[0, 5], config), "1.25");
+        assert_eq!(dtoa_snap_last(1.2185, &[0, 5], config), "1.2");
+        assert_eq!(dtoa_snap_last(-1.2345, &[0, 5], config), "-1.0");
+    }
+
+    #[test]
+    fn test_sortable() {
+        // Make sure sorting random floats by their sortable strings
+        // is the same as sorting them numerically
+        use rand::seq::SliceRandom;
+        let mut rng = rand::thread_rng();
+
+        let mut values = vec![
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            1.5,
+            -1.5,
+            1.55,
+            -1.55,
+            10.0,
+            9.99,
+            f64::MAX,
+            f64::MIN,
+            f64::MIN_POSITIVE,
+            -f64::MIN_POSITIVE,
+            5e-324,
+            -5e-324,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for _ in 0..2000 {
+            let val = f64::from_bits(rng.gen::<u64>());
+            if !val.is_nan() {
+                values.push(val);
+            }
+        }
+        values.shuffle(&mut rng);
+        let mut by_string = values.clone();
+        by_string.sort_by_key(|val| dtoa_sortable(*val));
+        let mut by_value = values;
+        by_value.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (a, b) in by_string.iter().zip(by_value.iter()) {
+            assert!(a == b, "Found bad sort order: {} and {}", a, b);
+        }
+        for val in by_value.iter() {
+            assert_eq!(dtoa_sortable(*val).len(), 21);
+        }
+        assert_eq!(dtoa_sortable(0.0), dtoa_sortable(-0.0));
+        assert!(dtoa_sortable(f64::NAN) > dtoa_sortable(f64::INFINITY));
+        assert_eq!(dtoa_sortable(f64::NAN).len(), 21);
+    }
+
+    #[test]
+    fn test_structured() {
+        let config = FmtFloatConfig::default();
+        let formatted = dtoa_structured(123.45, config);
+        assert_eq!(formatted.text, "123.45");
+        assert_eq!(formatted.radix_index, Some(3));
+        assert_eq!(formatted.exponent_index, None);
+        assert_eq!(formatted.sign_len, 0);
+        let formatted = dtoa_structured(-0.0025, config);
+        assert_eq!(formatted.text, "-0.0025");
+        assert_eq!(formatted.radix_index, Some(2));
+        assert_eq!(formatted.sign_len, 1);
+        let formatted = dtoa_structured(-1.5e10, config);
+        assert_eq!(formatted.text, "-1.5e10");
+        assert_eq!(formatted.radix_index, Some(2));
+        assert_eq!(formatted.exponent_index, Some(4));
+        let formatted = dtoa_structured(12.0, config.add_point_zero(false));
+        assert_eq!(formatted.text, "12");
+        assert_eq!(formatted.radix_index, None);
+        // Multibyte radix points
+        let config = FmtFloatConfig::default().radix_point('\u{b7}');
+        let formatted = dtoa_structured(-12.5e20, config);
+        assert_eq!(formatted.text, "-1\u{b7}25e21");
+        assert_eq!(
+            &formatted.text[formatted.radix_index.unwrap()..],
+            "\u{b7}25e21"
+        );
+        assert_eq!(&formatted.text[formatted.exponent_index.unwrap()..], "e21");
+        let formatted = dtoa_structured(1200.0, config);
+        assert_eq!(formatted.text, "1200\u{b7}0");
+        assert_eq!(&formatted.text[formatted.radix_index.unwrap()..], "\u{b7}0");
+        // Special values
+        let formatted = dtoa_structured(f64::NEG_INFINITY, config);
+        assert_eq!(formatted.text, "-inf");
+        assert_eq!(formatted.sign_len, 1);
+        assert_eq!(formatted.radix_index, None);
+        let formatted = dtoa_structured(-3e100, config.max_width(5));
+        assert_eq!(formatted.text, "#####");
+        assert_eq!(formatted.sign_len, 0);
+        assert_eq!(formatted.exponent_index, None);
+    }
+
+    #[test]
+    fn test_group_separator() {
+        let config = FmtFloatConfig::default().group_separator(',');
+        assert_eq!(dtoa(1234.5, config), "1,234.5");
+        assert_eq!(dtoa(1234567.5, config.force_no_e_notation()), "1,234,567.5");
+        assert_eq!(dtoa(-123456.0, config.force_no_e_notation()), "-123,456.0");
+        assert_eq!(dtoa(1e9, config.force_no_e_notation()), "1,000,000,000.0");
+        assert_eq!(dtoa(999.0, config), "999.0");
+        assert_eq!(dtoa(0.00012, config), "0.00012");
+        assert_eq!(dtoa(1.5e10, config), "1.5e10");
+        let config = FmtFloatConfig::default()
+            .group_separator('\u{2009}')
+            .force_no_e_notation();
+        assert_eq!(dtoa(1234567.891, config), "1\u{2009}234\u{2009}567.891");
+        assert_eq!(
+            dtoa(1234567.891, config.max_width(12)),
+            "1\u{2009}234\u{2009}567.89"
+        );
+        assert_eq!(dtoa(-1234.5, config.max_width(8)), "-1\u{2009}234.5");
+        assert_eq!(dtoa(-1234.5, config.max_width(7)), "-1.23e3");
+        assert_eq!(dtoa(1234567.891, config.max_width(8)), "1.2346e6");
+    }
+
+    #[test]
+    fn test_group_separator_widths() {
+        // The thin space is 3 bytes, but should only count as one
+        // character towards the width
+        let mut rng = rand::thread_rng();
+
+        for width in 7..=16 {
+            let config = FmtFloatConfig::default()
+                .force_no_e_notation()
+                .group_separator('\u{2009}')
+                .max_width(width);
+            for _ in 0..5000 {
+                let val = f64::from_bits(rng.gen::<u64>());
+                if val.is_nan() {
+                    continue;
+                }
+                let as_string = dtoa(val, config);
+                assert!(
+                    as_string.chars().count() <= width as usize,
+                    "Found bad example for string width: '{}' at width {} gives {}",
+                    val,
+                    width,
+                    as_string
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_separators() {
+        assert_eq!(FmtFloatConfig::default().validate(), Ok(()));
+        let config = FmtFloatConfig::default()
+            .radix_point(',')
+            .group_separator('.');
+        assert_eq!(config.validate(), Ok(()));
+        let config = FmtFloatConfig::default().group_separator('\u{2009}');
+        assert_eq!(config.validate(), Ok(()));
+        let config = FmtFloatConfig::default()
+            .radix_point(',')
+            .group_separator(',');
+        assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+        let config = FmtFloatConfig::default().group_separator('.');
+        assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+        let config = FmtFloatConfig::default().radix_point('0');
+        assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+        let config = FmtFloatConfig::default().radix_point('-');
+        assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+        let config = FmtFloatConfig::default().group_separator('7');
+        assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+        let config = FmtFloatConfig::default().group_separator('-');
+        assert_eq!(config.validate(), Err(ConfigError::AmbiguousSeparators));
+    }
+
+    #[test]
+    fn test_validate_e_breaks() {
+        // Crossed breaks use scientific notation for every value
+        let crossed = FmtFloatConfig::default().upper_e_break(-5).lower_e_break(5);
+        assert_eq!(dtoa(1.5, crossed), "1.5e0");
+        assert_eq!(dtoa(0.001, crossed), "1.0e-3");
+        assert_eq!(crossed.validate(), Err(ConfigError::CrossedEBreaks));
+        let equal = FmtFloatConfig::default().upper_e_break(2).lower_e_break(2);
+        assert_eq!(dtoa(12.0, equal), "1.2e1");
+        assert_eq!(equal.validate(), Err(ConfigError::CrossedEBreaks));
+        // Only exponents of 1 use regular notation
+        let config = FmtFloatConfig::default().upper_e_break(2).lower_e_break(1);
+        assert_eq!(dtoa(15.0, config), "15.0");
+        assert_eq!(dtoa(1.5, config), "1.5e0");
+        assert_eq!(config.validate(), Ok(()));
+        // The breaks don't matter when they don't pick the notation
+        assert_eq!(crossed.force_e_notation().validate(), Ok(()));
+        assert_eq!(crossed.force_no_e_notation().validate(), Ok(()));
+        assert_eq!(crossed.prefer_shortest_notation(true).validate(), Ok(()));
+        assert_eq!(crossed.e_notation_if_shorter_by(2).validate(), Ok(()));
+        assert_eq!(FmtFloatConfig::machine().validate(), Ok(()));
+        assert_eq!(FmtFloatConfig::display().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_format_fixed() {
+        // Outputs of printf("%.*f", decimals, value) from glibc
+        let table: &[(f64, u32, &str)] = &[
+            (0.5, 0, "0"),
+            (1.5, 0, "2"),
+            (2.5, 0, "2"),
+            (3.5, 0, "4"),
+            (-2.5, 0, "-2"),
+            (2.675, 2, "2.67"),
+            (1.005, 2, "1.00"),
+            (0.125, 2, "0.12"),
+            (0.375, 2, "0.38"),
+            (0.1, 1, "0.1"),
+            (0.1, 20, "0.10000000000000000555"),
+            (0.3, 17, "0.29999999999999999"),
+            (-0.0, 3, "-0.000"),
+            (-0.01, 1, "-0.0"),
+            (1e22, 1, "10000000000000000000000.0"),
+            (1e23, 0, "99999999999999991611392"),
+            (123456.789, 3, "123456.789"),
+            (0.000123456, 5, "0.00012"),
+            (5e-324, 3, "0.000"),
+            (9.995, 2, "9.99"),
+            (99.5, 0, "100"),
+            (0.05, 1, "0.1"),
+            (1.45, 1, "1.4"),
+            (9.5367431640625e-7, 25, "0.0000009536743164062500000"),
+            (f64::MAX, 0, "179769313486231570814527423731704356798070567525844996598917476803157260780028538760589558632766878171540458953514382464234321326889464182768467546703537516986049910576551282076245490090389328944075868508455133942304583236903222948165808559332123348274797826204144723168738177180919299881250404026184124858368"),
+        ];
+        for (value, decimals, expected) in table.iter() {
+            assert_eq!(format_fixed(*value, *decimals), *expected);
+        }
+        assert_eq!(format_fixed(f64::NAN, 2), "nan");
+        assert_eq!(format_fixed(-f64::NAN, 2), "-nan");
+        assert_eq!(format_fixed(f64::INFINITY, 2), "inf");
+        assert_eq!(format_fixed(f64::NEG_INFINITY, 2), "-inf");
+        assert_eq!(format_fixed(0.0, 0), "0");
+        assert_eq!(format_fixed(-0.5, 0), "-0");
+        assert_eq!(format_fixed(0.0005, 3), "0.001");
+        assert_eq!(format_fixed(0.00049, 3), "0.000");
+        assert_eq!(format_fixed(0.0625, 3), "0.062");
+        // Decimals are capped where every double is exact
+        let smallest = format_fixed(5e-324, u32::MAX);
+        assert_eq!(smallest.len(), 2 + MAX_FIXED_DECIMALS as usize);
+        assert!(smallest.starts_with("0.000") && smallest.ends_with("625"));
+        assert_eq!(
+            format_fixed(1.5, u32::MAX).len(),
+            2 + MAX_FIXED_DECIMALS as usize
+        );
+        // The same as the standard library, which rounds ties to even too
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let value =
+                (rng.gen::<f64>() - 0.5) * 10f64.powi((rng.gen::<f64>() * 40.0) as i32 - 20);
+            let decimals = (rng.gen::<f64>() * 25.0) as u32;
+            assert_eq!(
+                format_fixed(value, decimals),
+                format!("{:.*}", decimals as usize, value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sigfig_uncertainty() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            dtoa_sigfig_uncertainty(3.14259, 0.0031, 1, config),
+            "(3.143 ± 0.003)"
+        );
+        assert_eq!(
+            dtoa_sigfig_uncertainty(3.14259, 0.0031, 2, config),
+            "(3.1426 ± 0.0031)"
+        );
+        assert_eq!(
+            dtoa_sigfig_uncertainty(9.81, 0.123, 2, config),
+            "(9.81 ± 0.12)"
+        );
+        // The uncertainty rounds up to a different decimal place
+        assert_eq!(
+            dtoa_sigfig_uncertainty(2.34567, 0.0096, 1, config),
+            "(2.35 ± 0.01)"
+        );
+        assert_eq!(
+            dtoa_sigfig_uncertainty(-1.5, 0.25, 1, config),
+            "(-1.5 ± 0.3)"
+        );
+        assert_eq!(
+            dtoa_sigfig_uncertainty(-1.5, 0.25, 1, config.truncate()),
+            "(-1.5 ± 0.2)"
+        );
+        // Significant trailing zeros are kept
+        assert_eq!(
+            dtoa_sigfig_uncertainty(2.0, 0.05, 1, config),
+            "(2.00 ± 0.05)"
+        );
+        assert_eq!(dtoa_sigfig_uncertainty(12.0, 1.0, 1, config), "(12 ± 1)");
+        // The value rounds to fewer decimals than it has
+        assert_eq!(
+            dtoa_sigfig_uncertainty(1234.5678, 31.0, 1, config),
+            "(1230 ± 30)"
+        );
+        assert_eq!(
+            dtoa_sigfig_uncertainty(1234.5678, 31.0, 2, config),
+            "(1235 ± 31)"
+        );
+        assert_eq!(
+            dtoa_sigfig_uncertainty(0.0004, 0.2, 1, config),
+            "(0.0 ± 0.2)"
+        );
+        assert_eq!(dtoa_sigfig_uncertainty(1.5, 0.0, 1, config), "(1.5 ± 0.0)");
+    }
+
+    #[test]
+    fn test_fraction() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_fraction(0.5, 100, 1e-9, config), "1/2");
+        assert_eq!(dtoa_fraction(1.0 / 3.0, 100, 1e-9, config), "1/3");
+        assert_eq!(dtoa_fraction(0.1, 100, 1e-9, config), "1/10");
+        assert_eq!(dtoa_fraction(22.0 / 7.0, 100, 1e-9, config), "22/7");
+        assert_eq!(dtoa_fraction(-0.75, 100, 1e-9, config), "-3/4");
+        assert_eq!(dtoa_fraction(2.0, 100, 1e-9, config), "2");
+        assert_eq!(dtoa_fraction(0.0, 100, 1e-9, config), "0");
+        // The tolerance and denominator bound are configurable
+        let pi = std::f64::consts::PI;
+        assert_eq!(dtoa_fraction(pi, 100, 1e-2, config), "22/7");
+        assert_eq!(dtoa_fraction(pi, 1000, 1e-6, config), "355/113");
+        assert_eq!(dtoa_fraction(0.5, 1, 1e-9, config), "0.5");
+        // No good fraction
+        assert_eq!(
+            dtoa_fraction(2.0f64.sqrt(), 100, 1e-9, config),
+            "1.4142135623730951"
+        );
+        assert_eq!(dtoa_fraction(1e300, 100, 1e-9, config), "1.0e300");
+        assert_eq!(dtoa_fraction(f64::NAN, 100, 1e-9, config), "NaN");
+        assert_eq!(dtoa_fraction(f64::NEG_INFINITY, 100, 1e-9, config), "-inf");
+    }
+
+    #[test]
+    fn test_number_format() {
+        let format = NumberFormat::new(
+            FmtFloatConfig::default()
+                .radix_point(',')
+                .group_separator('.')
+                .force_no_e_notation(),
+        );
+        assert_eq!(format.format_f64(1234567.5), "1.234.567,5");
+        assert_eq!(format.format_f64(1234567.0), "1.234.567,0");
+        assert_eq!(format.format_f32(1234.5), "1.234,5");
+        assert_eq!(format.format_i64(1234567), "1.234.567");
+        assert_eq!(format.format_i64(-42), "-42");
+        assert_eq!(format.format_i64(0), "0");
+        assert_eq!(format.format_i64(i64::MIN), "-9.223.372.036.854.775.808");
+        // Digit limits apply to integers too
+        let format: NumberFormat = FmtFloatConfig::default()
+            .group_separator(',')
+            .min_decimal_digits(2)
+            .max_decimal_digits(2)
+            .into();
+        assert_eq!(format.format_f64(1234.5), "1,234.50");
+        assert_eq!(format.format_i64(1234), "1,234.00");
+        assert_eq!(
+            format.config(),
+            FmtFloatConfig::default()
+                .group_separator(',')
+                .min_decimal_digits(2)
+                .max_decimal_digits(2)
+        );
+    }
+
+    #[test]
+    fn test_max_sig_digits_zero() {
+        let config = FmtFloatConfig::default().max_significant_digits(0);
+        assert_eq!(dtoa(1.5, config), "2.0");
+        assert_eq!(dtoa(5.5, config), "6.0");
+        assert_eq!(dtoa(9.5, config), "10.0");
+        assert_eq!(dtoa(0.0123, config), "0.01");
+        assert_eq!(dtoa(0.0, config), "0.0");
+        assert_eq!(dtoa(-2.5, config), "-3.0");
+        assert_eq!(dtoa(123456.0, config), "1.0e5");
+        assert_eq!(dtoa(1.5e-10, config), "2.0e-10");
+        assert_eq!(dtoa(1.5, config.truncate()), "1.0");
+        assert_eq!(dtoa(123.0, config.keep_integer_digits(true)), "123.0");
+        assert_eq!(dtoa(123.0, config.max_width(5)), "100.0");
+        assert_eq!(ftoa(7.25, config), "7.0");
+    }
+
+    #[test]
+    fn test_machine_and_display() {
+        let values = [
+            0.1 + 0.2,
+            1234567.5,
+            -98765.4321,
+            1e16,
+            1e17,
+            123456789012345680.0,
+            1.5e-7,
+            0.00000123,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            5e-324,
+            0.0,
+        ];
+        for &value in values.iter() {
+            let (machine, _) = format_both(value);
+            assert!(!machine.contains(','));
+            assert_eq!(machine.parse::<f64>().unwrap(), value);
+        }
+        assert_eq!(
+            format_both(1234567.5),
+            ("1234567.5".to_string(), "1.235e6".to_string())
+        );
+        assert_eq!(
+            format_both(-98765.4321),
+            ("-98765.4321".to_string(), "-98,765".to_string())
+        );
+        assert_eq!(format_both(1e16).0, "10000000000000000.0");
+        assert_eq!(format_both(0.00000123).0, "0.00000123");
+        assert_eq!(FmtFloatConfig::for_machine(true), FmtFloatConfig::machine());
+        assert_eq!(
+            FmtFloatConfig::for_machine(false),
+            FmtFloatConfig::display()
+        );
+    }
+
+    #[test]
+    fn test_dtoa_delta() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_delta(2.5, false, config), "+2.5");
+        assert_eq!(dtoa_delta(-2.5, false, config), "-2.5");
+        assert_eq!(dtoa_delta(0.0, false, config), "0.0");
+        assert_eq!(dtoa_delta(-0.0, false, config), "0.0");
+        assert_eq!(dtoa_delta(1e10, false, config), "+1.0e10");
+        assert_eq!(dtoa_delta(f64::INFINITY, false, config), "+inf");
+        assert_eq!(dtoa_delta(f64::NAN, false, config), "NaN");
+        assert_eq!(dtoa_delta(2.5, true, config), "▲ +2.5");
+        assert_eq!(dtoa_delta(-2.5, true, config), "▼ -2.5");
+        assert_eq!(dtoa_delta(0.0, true, config), "0.0");
+        assert_eq!(dtoa_delta(-0.0, true, config), "0.0");
+        assert_eq!(dtoa_delta(f64::NAN, true, config), "NaN");
+        let config = FmtFloatConfig::auto();
+        assert_eq!(dtoa_delta(0.1 + 0.2, true, config), "▲ +0.3");
+        assert_eq!(dtoa_delta(-0.0, true, config), "0");
+        let config = FmtFloatConfig::default().sign_column(true);
+        assert_eq!(dtoa_delta(2.5, false, config), "+2.5");
+        assert_eq!(dtoa_delta(0.0, false, config), " 0.0");
+    }
+
+    #[test]
+    fn test_dtoa_columns() {
+        let config = FmtFloatConfig::default();
+        let columns = |value, config| {
+            let (mantissa, exponent) = dtoa_columns(value, config);
+            assert_eq!(format!("{}{}", mantissa, exponent), dtoa(value, config));
+            (mantissa, exponent)
+        };
+        assert_eq!(
+            columns(3e10, config),
+            ("3.0".to_string(), "e10".to_string())
+        );
+        assert_eq!(
+            columns(-3.5e-10, config),
+            ("-3.5".to_string(), "e-10".to_string())
+        );
+        assert_eq!(columns(-12.5, config), ("-12.5".to_string(), String::new()));
+        assert_eq!(columns(0.0, config), ("0.0".to_string(), String::new()));
+        assert_eq!(
+            columns(
+                3e10,
+                config
+                    .trailing_point(TrailingPoint::Never)
+                    .capitalize_e(true)
+            ),
+            ("3".to_string(), "E10".to_string())
+        );
+        assert_eq!(
+            columns(12.5, config.force_e_notation()),
+            ("1.25".to_string(), "e1".to_string())
+        );
+        assert_eq!(
+            columns(f64::NAN, config),
+            ("NaN".to_string(), String::new())
+        );
+        assert_eq!(
+            columns(-1e100, config.max_width(5)),
+            ("#####".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn test_zero() {
+        // Zero is a single 0 digit left of the decimal point, and goes
+        // through every option like any other value
+        let d = FmtFloatConfig::default();
+        let cases = [
+            (d, "0.0"),
+            (FmtFloatConfig::auto(), "0"),
+            (FmtFloatConfig::machine(), "0.0"),
+            (FmtFloatConfig::display(), "0"),
+            (d.max_significant_digits(0), "0.0"),
+            (d.max_significant_digits(1), "0.0"),
+            (d.max_significant_digits(1).keep_integer_digits(true), "0.0"),
+            (d.min_significant_digits(3), "0.00"),
+            (d.max_decimal_digits(0), "0.0"),
+            (d.max_decimal_digits(-2), "0.0"),
+            (d.min_decimal_digits(3), "0.000"),
+            (d.min_integer_digits(3), "000.0"),
+            (d.upper_e_break(0), "0.0e0"),
+            (d.lower_e_break(1), "0.0e0"),
+            (d.ignore_extremes(1), "0.0"),
+            (d.truncate(), "0.0"),
+            (d.force_e_notation(), "0.0e0"),
+            (d.force_no_e_notation(), "0.0"),
+            (d.prefer_shortest_notation(true), "0.0"),
+            (d.e_notation_if_shorter_by(0), "0.0"),
+            (d.force_e_notation().capitalize_e(true), "0.0E0"),
+            (
+                d.force_e_notation().spoken_exponent(true),
+                "0.0 times ten to the 0",
+            ),
+            (
+                d.force_e_notation()
+                    .max_decimal_digits(1)
+                    .decimal_digits_in_e_notation(true),
+                "0.0e0",
+            ),
+            (d.add_point_zero(false), "0"),
+            (
+                d.force_e_notation()
+                    .trailing_point(TrailingPoint::RoundTripMinimal),
+                "0e0",
+            ),
+            (d.simplify(true).min_decimal_digits(2), "0.00"),
+            (d.max_width(3), "0.0"),
+            (d.max_width(3).force_e_notation(), "0e0"),
+            (d.max_width(4).force_e_notation(), "0e0"),
+            (d.radix_point(','), "0,0"),
+            (d.group_separator(',').min_integer_digits(5), "00,000.0"),
+        ];
+        for (config, expected) in cases.iter() {
+            assert_eq!(dtoa(0.0, *config), *expected);
+            assert_eq!(ftoa(0.0, *config), *expected);
+            if config.max_width.is_none() {
+                assert_eq!(dtoa(-0.0, *config), format!("-{}", expected));
+                assert_eq!(ftoa(-0.0, *config), format!("-{}", expected));
+            }
+        }
+        assert_eq!(dtoa(-0.0, d.max_width(4).force_e_notation()), "-0e0");
+        assert_eq!(dtoa(-0.0, d.max_width(3)), "###");
+    }
+
+    #[test]
+    fn test_dtoa_aligned() {
+        let values = [1.5, -12.25, 1234.0, 0.000123, -0.5, 3e10, 99999.0, 0.0];
+        let configs = [
+            FmtFloatConfig::default(),
+            FmtFloatConfig::default().force_no_e_notation(),
+            FmtFloatConfig::default()
+                .radix_point('·')
+                .group_separator(' '),
+            FmtFloatConfig::auto(),
+        ];
+        for config in configs.iter() {
+            let aligned = dtoa_aligned(&values, *config);
+            assert_eq!(aligned.len(), values.len());
+            let width = aligned[0].chars().count();
+            let radix_column = aligned[1].chars().position(|c| c == config.radix_point);
+            for (value, s) in values.iter().zip(aligned.iter()) {
+                assert_eq!(s.chars().count(), width);
+                assert_eq!(s.trim(), dtoa(*value, *config));
+                if dtoa(*value, *config).contains(config.radix_point) {
+                    assert_eq!(
+                        s.chars().position(|c| c == config.radix_point),
+                        radix_column
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            dtoa_aligned(
+                &[12.0, 1.25, 3e10],
+                FmtFloatConfig::default().trailing_point(TrailingPoint::Never)
+            ),
+            vec!["12   ", " 1.25", " 3e10"]
+        );
+        assert!(dtoa_aligned(&[], FmtFloatConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_ignore_extremes_precedence() {
+        let config = FmtFloatConfig::default()
+            .ignore_extremes(3)
+            .min_significant_digits(6);
+        // Ignored digits aren't padded back
+        assert_eq!(dtoa(12.2000001, config), "12.2");
+        assert_eq!(dtoa(12.1999999, config), "12.2");
+        assert_eq!(dtoa(0.30000000000000004, config), "0.3");
+        // Values without extremes are still padded
+        assert_eq!(dtoa(12.2, config), "12.2000");
+        assert_eq!(dtoa(12.345, config), "12.3450");
+        // min_decimal_digits still pads
+        assert_eq!(dtoa(12.2000001, config.min_decimal_digits(2)), "12.20");
+        // Trailing zeros from other limits are padded as usual
+        let config = config.max_significant_digits(5);
+        assert_eq!(dtoa(1.2000004, config), "1.20000");
+        assert_eq!(dtoa(1.201, config), "1.20100");
+    }
+
+    #[test]
+    fn test_dtoa_with_sig_count() {
+        let count = |value, config| dtoa_with_sig_count(value, config).1;
+        let lossless = FmtFloatConfig::default();
+        assert_eq!(count(3.25, lossless), 3);
+        assert_eq!(count(0.000125, lossless), 3);
+        assert_eq!(count(1200.0, lossless), 2);
+        assert_eq!(count(1.25e100, lossless), 3);
+        assert_eq!(count(1e100, lossless), 1);
+        assert_eq!(count(0.1 + 0.2, lossless), 17);
+        assert_eq!(count(0.0, lossless), 1);
+        assert_eq!(count(f64::NAN, lossless), 0);
+        assert_eq!(count(f64::NEG_INFINITY, lossless), 0);
+        let lossy = FmtFloatConfig::default().max_significant_digits(3);
+        assert_eq!(dtoa_with_sig_count(1.201, lossy), ("1.20".to_string(), 3));
+        assert_eq!(
+            dtoa_with_sig_count(0.1 + 0.2, lossy),
+            ("0.300".to_string(), 3)
+        );
+        assert_eq!(count(0.1 + 0.2, FmtFloatConfig::auto()), 1);
+        assert_eq!(count(123456.0, FmtFloatConfig::auto()), 6);
+        // Padded zeros are significant
+        let padded = FmtFloatConfig::default().min_significant_digits(5);
+        assert_eq!(dtoa_with_sig_count(1.5, padded), ("1.5000".to_string(), 5));
+        assert_eq!(count(0.0, padded), 5);
+        assert_eq!(
+            count(2.5, FmtFloatConfig::default().min_decimal_digits(3)),
+            4
+        );
+        assert_eq!(
+            count(7.0, FmtFloatConfig::default().min_integer_digits(4)),
+            1
+        );
+        // Digits cut by max_width aren't counted
+        let narrow = FmtFloatConfig::default().max_width(5);
+        assert_eq!(
+            dtoa_with_sig_count(3.14259, narrow),
+            ("3.143".to_string(), 4)
+        );
+        assert_eq!(
+            dtoa_with_sig_count(1.2345e10, narrow),
+            ("1e10".to_string(), 1)
+        );
+        assert_eq!(count(-1e100, narrow), 0);
+    }
+
+    #[test]
+    fn test_round_with() {
+        // Round half to odd
+        fn half_to_odd(digits: &[u8], cut: usize) -> bool {
+            if digits[cut] != b'5' {
+                return digits[cut] > b'5';
+            }
+            if digits[cut + 1..].iter().any(|digit| *digit != b'0') {
+                return true;
+            }
+            // Exactly half way. If every digit is removed, the last digit
+            // kept is a leading 0
+            cut == 0 || matches!(digits[cut - 1], b'0' | b'2' | b'4' | b'6' | b'8')
+        }
+        let config = FmtFloatConfig::default()
+            .max_decimal_digits(1)
+            .round_with(half_to_odd);
+        assert_eq!(dtoa(2.25, config), "2.3");
+        assert_eq!(dtoa(2.35, config), "2.3");
+        assert_eq!(dtoa(2.45, config), "2.5");
+        assert_eq!(dtoa(2.251, config), "2.3");
+        assert_eq!(dtoa(2.349, config), "2.3");
+        assert_eq!(dtoa(9.95, config), "9.9");
+        assert_eq!(dtoa(9.85, config), "9.9");
+        assert_eq!(dtoa(-0.25, config), "-0.3");
+        let config = FmtFloatConfig::default()
+            .max_significant_digits(1)
+            .round_with(half_to_odd);
+        assert_eq!(dtoa(2.5, config), "3.0");
+        assert_eq!(dtoa(3.5, config), "3.0");
+        assert_eq!(dtoa(0.5, config.max_decimal_digits(0)), "1.0");
+        // Custom functions can match the built in modes
+        let round_like = FmtFloatConfig::default()
+            .max_significant_digits(3)
+            .round_with(|digits, cut| digits[cut] >= b'5');
+        let round = FmtFloatConfig::default().max_significant_digits(3);
+        for &value in [1.2345, 9.9951, 0.0012355, 123456.0].iter() {
+            assert_eq!(dtoa(value, round_like), dtoa(value, round));
+        }
+        let truncate_like = round.round_with(|_, _| false);
+        assert_eq!(dtoa(9.9951, truncate_like), dtoa(9.9951, round.truncate()));
+    }
+
+    #[test]
+    fn test_format_percent_change() {
+        let config = FmtFloatConfig::default().max_decimal_digits(2);
+        assert_eq!(format_percent_change(50.0, 75.0, "-", config), "+50.0%");
+        assert_eq!(format_percent_change(3.0, 4.0, "-", config), "+33.33%");
+        assert_eq!(format_percent_change(75.0, 50.0, "-", config), "-33.33%");
+        assert_eq!(format_percent_change(-10.0, -5.0, "-", config), "-50.0%");
+        assert_eq!(format_percent_change(12.5, 12.5, "-", config), "0.0%");
+        assert_eq!(format_percent_change(0.0, 0.0, "-", config), "0.0%");
+        assert_eq!(format_percent_change(0.0, 5.0, "-", config), "-");
+        assert_eq!(
+            format_percent_change(-0.0, -5.0, "undefined", config),
+            "undefined"
+        );
+        assert_eq!(format_percent_change(f64::NAN, 5.0, "-", config), "-");
+        assert_eq!(format_percent_change(5.0, f64::INFINITY, "-", config), "-");
+        assert_eq!(
+            format_percent_change(f64::INFINITY, f64::INFINITY, "-", config),
+            "-"
+        );
+        let config = FmtFloatConfig::auto();
+        assert_eq!(format_percent_change(1.0, 1.1, "-", config), "+10%");
+        assert_eq!(format_percent_change(1.0, 1.0, "-", config), "0%");
+    }
+
+    #[test]
+    fn test_sign_column() {
+        let config = FmtFloatConfig::default().sign_column(true);
+        assert_eq!(dtoa(3.5, config), " 3.5");
+        assert_eq!(dtoa(-3.5, config), "-3.5");
+        assert_eq!(dtoa(0.0, config), " 0.0");
+        assert_eq!(dtoa(-0.0, config), "-0.0");
+        assert_eq!(dtoa(3e10, config), " 3.0e10");
+        assert_eq!(dtoa(-3e10, config), "-3.0e10");
+        // The sign stays outside the zero padding
+        let padded = config.min_integer_digits(4);
+        assert_eq!(dtoa(3.5, padded), " 0003.5");
+        assert_eq!(dtoa(-3.5, padded), "-0003.5");
+        assert_eq!(dtoa(-0.25, padded), "-0000.25");
+        assert_eq!(ftoa(3.5, padded), " 0003.5");
+        let formatted = dtoa_structured(3.5, padded);
+        assert_eq!(formatted.sign_len, 1);
+        assert_eq!(formatted.radix_index, Some(5));
+        // The sign column counts towards max_width
+        let narrow = config.max_width(5);
+        assert_eq!(dtoa(3.14259, narrow), " 3.14");
+        assert_eq!(dtoa(-3.14259, narrow), "-3.14");
+        assert_eq!(dtoa(12345.0, narrow), " 1e4");
+        assert_eq!(
+            dtoa(123.0, config.add_point_zero(false).max_width(4)),
+            " 123"
+        );
+        // Non-negative values line up with negative ones
+        let values = [1.25, -1.25, 9.75, -0.75];
+        let lengths: Vec<usize> = values.iter().map(|v| dtoa(*v, padded).len()).collect();
+        assert!(lengths.iter().all(|l| *l == lengths[0]));
+    }
+
+    #[test]
+    fn test_max_decimal_digits_first_digit() {
+        // The cut lands exactly at the first digit
+        let config = FmtFloatConfig::default().max_decimal_digits(0);
+        assert_eq!(dtoa(0.6, config), "1.0");
+        assert_eq!(dtoa(0.5, config), "1.0");
+        assert_eq!(dtoa(-0.6, config), "-1.0");
+        assert_eq!(dtoa(0.4, config), "0.0");
+        assert_eq!(dtoa(0.6, config.truncate()), "0.0");
+        assert_eq!(dtoa(9.6, config), "10.0");
+        assert_eq!(dtoa(0.96, config.max_decimal_digits(1)), "1.0");
+        assert_eq!(dtoa(0.096, config.max_decimal_digits(2)), "0.1");
+        assert_eq!(dtoa(56.0, config.max_decimal_digits(-1)), "60.0");
+        assert_eq!(dtoa(96.0, config.max_decimal_digits(-1)), "100.0");
+        assert_eq!(dtoa(6.0, config.max_decimal_digits(-1)), "10.0");
+        // The cut lands left of the first digit
+        assert_eq!(dtoa(0.06, config), "0.0");
+        assert_eq!(dtoa(0.0006, config.max_decimal_digits(2)), "0.0");
+        assert_eq!(dtoa(4.0, config.max_decimal_digits(-1)), "0.0");
+        assert_eq!(dtoa(0.6, config.max_decimal_digits(-1)), "0.0");
+        assert_eq!(dtoa(60.0, config.max_decimal_digits(-3)), "0.0");
+        assert_eq!(dtoa(0.06, config.round_with(|_, _| true)), "1.0");
+        assert_eq!(
+            dtoa(0.6, config.max_decimal_digits(-2).round_with(|_, _| true)),
+            "100.0"
+        );
+    }
+
+    #[test]
+    fn test_dtoa_strict_lossless() {
+        let configs = [
+            FmtFloatConfig::default(),
+            FmtFloatConfig::machine(),
+            FmtFloatConfig::default()
+                .radix_point(',')
+                .group_separator('.'),
+            FmtFloatConfig::default()
+                .force_e_notation()
+                .capitalize_e(true),
+            FmtFloatConfig::default()
+                .spoken_exponent(true)
+                .force_e_notation(),
+            FmtFloatConfig::default().exponent_formatter(|e| format!(" x 10^{}", e)),
+            FmtFloatConfig::default()
+                .sign_column(true)
+                .min_integer_digits(3),
+            FmtFloatConfig::default()
+                .add_point_zero(false)
+                .min_significant_digits(20),
+            FmtFloatConfig::default().unit("m"),
+            FmtFloatConfig::default()
+                .unit("°")
+                .unit_space(false)
+                .max_width(30),
+        ];
+        for config in configs.iter() {
+            for &value in [0.1 + 0.2, -1234.5678, 1e300, 5e-324, 0.0, f64::NAN].iter() {
+                assert_eq!(dtoa_strict(value, *config), dtoa(value, *config));
+            }
+        }
+        // Values that happen to fit a lossy config pass
+        let config = FmtFloatConfig::default().max_significant_digits(3);
+        assert_eq!(dtoa_strict(1.25, config), "1.25");
+        // The string that's checked is the one dtoa writes, with the sign
+        // column and exponent formatter taking up their real widths
+        let column = FmtFloatConfig::default().sign_column(true);
+        assert!(round_trips(3.25, column.max_width(5)));
+        assert!(!round_trips(3.25, column.max_width(4)));
+        let spelled = FmtFloatConfig::default().exponent_formatter(|e| format!(" x 10^{}", e));
+        assert_eq!(dtoa(1.2345e10, spelled.max_width(10)), "1 x 10^10");
+        assert!(!round_trips(1.2345e10, spelled.max_width(10)));
+        assert!(round_trips(1.2345e10, spelled.max_width(14)));
+        // Markers and units after the digits aren't read
+        let marked = FmtFloatConfig::default().approx_marker('~').unit("m2");
+        assert!(round_trips(2.5, marked));
+        assert!(!round_trips(2.25, marked.max_significant_digits(2)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dtoa_strict_sign_column() {
+        let config = FmtFloatConfig::default().sign_column(true).max_width(4);
+        dtoa_strict(3.25, config);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dtoa_strict_lossy() {
+        let config = FmtFloatConfig::default().max_significant_digits(3);
+        dtoa_strict(1.2345, config);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dtoa_strict_width() {
+        let config = FmtFloatConfig::default().max_width(6).group_separator(',');
+        dtoa_strict(1234.5678, config);
+    }
+
+    #[test]
+    fn test_truncation_marker() {
+        let config = FmtFloatConfig::default()
+            .max_significant_digits(3)
+            .truncation_marker('…');
+        assert_eq!(dtoa(2.34567, config), "2.35…");
+        assert_eq!(dtoa(-1234.5, config), "-1230.0…");
+        assert_eq!(dtoa(-123456.0, config), "-1.23e5…");
+        // Values that fit aren't marked
+        assert_eq!(dtoa(2.34, config), "2.34");
+        assert_eq!(dtoa(2.5, config), "2.5");
+        assert_eq!(dtoa(1200.0, config), "1200.0");
+        assert_eq!(dtoa(0.0, config), "0.0");
+        assert_eq!(dtoa(2.34, config.min_significant_digits(5)), "2.3400");
+        assert_eq!(dtoa(2.34567, config.min_significant_digits(5)), "2.3500…");
+        assert_eq!(dtoa(2.34567, config.no_truncation_marker()), "2.35");
+        // Other lossy options
+        let config = FmtFloatConfig::default().truncation_marker('*');
+        assert_eq!(dtoa(0.1 + 0.2, config.ignore_extremes(3)), "0.3*");
+        assert_eq!(dtoa(0.3, config.ignore_extremes(3)), "0.3");
+        assert_eq!(dtoa(2.5, config.max_decimal_digits(0)), "3.0*");
+        assert_eq!(dtoa(2.0, config.max_decimal_digits(0)), "2.0");
+        assert_eq!(
+            dtoa(1.25, config.max_significant_digits(2).truncate()),
+            "1.2*"
+        );
+        assert_eq!(
+            dtoa_snap_last(1.23, &[0, 5], config.max_decimal_digits(2)),
+            "1.25*"
+        );
+        assert_eq!(
+            dtoa_snap_last(1.25, &[0, 5], config.max_decimal_digits(2)),
+            "1.25"
+        );
+        assert_eq!(
+            dtoa(
+                123456.0,
+                config
+                    .force_e_notation()
+                    .max_exponent_magnitude(2)
+                    .max_significant_digits(2)
+            ),
+            "1200.0e2*"
+        );
+        // Room is left for the marker with max_width
+        let narrow = config.max_width(6);
+        assert_eq!(dtoa(2.34567, narrow), "2.346*");
+        assert_eq!(dtoa(2.34, narrow), "2.34");
+        assert_eq!(dtoa(3.25e10, narrow), "3e10*");
+        assert_eq!(dtoa(3.25e10, narrow.max_width(7)), "3.3e10*");
+        assert_eq!(dtoa(3e10, narrow), "3e10");
+        assert_eq!(dtoa(12.5, narrow.max_width(5)), "12.5");
+    }
+
+    #[test]
+    fn test_dtoa_uniform_notation() {
+        let config = FmtFloatConfig::default();
+        let mixed = [0.5, -12.25, 3e10, 0.0, f64::NAN, 1.5e-7];
+        assert_eq!(
+            dtoa_uniform_notation(&mixed, config),
+            vec!["5.0e-1", "-1.225e1", "3.0e10", "0.0e0", "NaN", "1.5e-7"]
+        );
+        let small = [0.5, -12.25, 1234.0, 0.0, f64::INFINITY];
+        assert_eq!(
+            dtoa_uniform_notation(&small, config),
+            vec!["0.5", "-12.25", "1234.0", "0.0", "inf"]
+        );
+        // The breaks come from the config
+        assert_eq!(
+            dtoa_uniform_notation(&small, config.upper_e_break(3)),
+            vec!["5.0e-1", "-1.225e1", "1.234e3", "0.0e0", "inf"]
+        );
+        assert_eq!(
+            dtoa_uniform_notation(&mixed[..3], config.force_no_e_notation()),
+            vec!["0.5", "-12.25", "30000000000.0"]
+        );
+        assert!(dtoa_uniform_notation(&[], config).is_empty());
+    }
+
+    #[test]
+    fn test_unit() {
+        let config = FmtFloatConfig::default().unit("kg");
+        assert_eq!(dtoa(5.0, config), "5.0 kg");
+        assert_eq!(dtoa(-2.5, config), "-2.5 kg");
+        assert_eq!(dtoa(5e10, config), "5.0e10 kg");
+        assert_eq!(dtoa(5.0, config.unit_space(false)), "5.0kg");
+        assert_eq!(dtoa(5.0, config.no_unit()), "5.0");
+        assert_eq!(dtoa(f64::NAN, config), "NaN");
+        assert_eq!(dtoa(f64::NEG_INFINITY, config), "-inf");
+        let degrees = FmtFloatConfig::auto().unit("°").unit_space(false);
+        assert_eq!(dtoa(90.0, degrees), "90°");
+        assert_eq!(dtoa(-45.5, degrees), "-45.5°");
+        assert_eq!(dtoa(1.5e7, degrees), "1.5e7°");
+        assert_eq!(ftoa(90.0, degrees), "90°");
+        // The unit counts towards max_width
+        let narrow = config.max_width(7);
+        assert_eq!(dtoa(2.34567, narrow), "2.35 kg");
+        assert_eq!(dtoa(12345.0, narrow), "1e4 kg");
+        assert_eq!(dtoa(-12345.0, narrow), "-1e4 kg");
+        assert_eq!(dtoa(-1e100, narrow), "#######");
+        assert_eq!(dtoa(2.34567, narrow.unit_space(false)), "2.346kg");
+        assert_eq!(dtoa(2.5, config.max_width(3)), "###");
+        // The unit goes after the truncation marker and exponent
+        let marked = config.truncation_marker('~').max_significant_digits(2);
+        assert_eq!(dtoa(2.34567, marked), "2.3~ kg");
+        assert_eq!(
+            dtoa(1.5e5, config.force_e_notation().max_exponent_magnitude(2)),
+            "1500.0e2 kg"
+        );
+        let formatted = dtoa_structured(-2.5, config);
+        assert_eq!(formatted.radix_index, Some(2));
+        assert_eq!(formatted.sign_len, 1);
+    }
+
+    #[test]
+    fn test_printed_exponent() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(printed_exponent(1.5e10, config), Some(10));
+        assert_eq!(printed_exponent(-1.5e-7, config), Some(-7));
+        assert_eq!(printed_exponent(12345.0, config), Some(4));
+        assert_eq!(printed_exponent(1234.0, config), None);
+        assert_eq!(printed_exponent(0.0, config), None);
+        assert_eq!(printed_exponent(0.0, config.force_e_notation()), Some(0));
+        assert_eq!(printed_exponent(f64::NAN, config), None);
+        assert_eq!(printed_exponent(1.5e10, config.force_no_e_notation()), None);
+        // Rounding can change the exponent
+        assert_eq!(
+            printed_exponent(9.96e9, config.max_significant_digits(2)),
+            Some(10)
+        );
+        // Adjusted exponents
+        let capped = config.force_e_notation().max_exponent_magnitude(3);
+        assert_eq!(printed_exponent(1.5e10, capped), Some(3));
+        assert_eq!(printed_exponent(1.5e-10, capped), Some(-3));
+        assert_eq!(printed_exponent(1.5e2, capped), Some(2));
+        let suppressed = config.force_e_notation().suppress_zero_exponent(true);
+        assert_eq!(printed_exponent(1.5, suppressed), None);
+        assert_eq!(printed_exponent(15.0, suppressed), Some(1));
+        // Exponents forced by max_width
+        assert_eq!(
+            printed_exponent(123456.0, config.upper_e_break(10).max_width(5)),
+            Some(5)
+        );
+        let formatted = dtoa_structured(2.5e-3, config.force_e_notation());
+        assert_eq!(formatted.exponent, Some(-3));
+        assert_eq!(&formatted.text[formatted.exponent_index.unwrap()..], "e-3");
+    }
+
+    #[test]
+    fn test_max_width_ties() {
+        let config = FmtFloatConfig::default().max_width(4);
+        // The shortest digits end in 5, but the floats are below them
+        assert_eq!(dtoa(2.675, config), "2.67");
+        assert_eq!(dtoa(1.005, config), "1.0");
+        assert_eq!(dtoa(0.285, config), "0.28");
+        // The floats are above the digits
+        assert_eq!(dtoa(2.665, config), "2.67");
+        assert_eq!(dtoa(0.135, config), "0.14");
+        assert_eq!(dtoa(0.155, config), "0.15");
+        // Exact ties round up
+        assert_eq!(dtoa(0.125, config), "0.13");
+        assert_eq!(dtoa(-0.375, config), "-0.4");
+        // Cuts in scientific notation round the same way, carrying into
+        // the exponent
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa(1.96e30, config.max_width(6)), "2.0e30");
+        assert_eq!(dtoa(2.6789e-10, config.max_width(7)), "2.7e-10");
+        assert_eq!(dtoa(2.6489e-10, config.max_width(7)), "2.6e-10");
+        assert_eq!(dtoa(9.9999e9, config.max_width(6)), "1.0e10");
+        assert_eq!(dtoa(9.9999e-10, config.max_width(6)), "1.0e-9");
+        assert_eq!(dtoa(1.96e30, config.max_width(6).truncate()), "1.9e30");
+        let config = FmtFloatConfig::default().max_width(4);
+        assert_eq!(ftoa(2.675, config), "2.67");
+        assert_eq!(ftoa(2.665, config), "2.66");
+        // Other rounding modes aren't changed
+        assert_eq!(dtoa(2.675, config.truncate()), "2.67");
+        assert_eq!(dtoa(2.665, config.truncate()), "2.66");
+        // Cuts from max_width give the closest decimal of their length
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let val = rng.gen::<f64>() * 100.0;
+            for width in 4..12 {
+                let decimals = if val < 10.0 { width - 2 } else { width - 3 };
+                let expected = format!("{:.*}", decimals, val);
+                let expected = expected.trim_end_matches('0');
+                let expected = if expected.ends_with('.') {
+                    format!("{}0", expected)
+                } else {
+                    expected.to_string()
+                };
+                let s = dtoa(val, FmtFloatConfig::default().max_width(width as u8));
+                if expected.len() <= width && !expected.starts_with("100") && !s.contains('e') {
+                    assert_eq!(s, expected, "{} {}", val, width);
+                }
             }
-            stripped_string.push(*digit);
         }
-        digits = stripped_string;
     }
-    if let Some(limit) = config.min_sig_digits {
-        // Pad 0's to get enough significant digits
-        let mut curr = digits.len() as u8;
-        while curr < limit {
-            digits.push(digit_to_u8(0));
-            curr += 1;
+
+    #[test]
+    fn test_format_compact() {
+        let config = FmtFloatConfig::default().add_point_zero(false);
+        assert_eq!(format_compact(0.0, false, config), "0");
+        assert_eq!(format_compact(999.0, false, config), "999");
+        assert_eq!(format_compact(-12.5, false, config), "-13");
+        assert_eq!(format_compact(1000.0, false, config), "1.0K");
+        assert_eq!(format_compact(1500.0, false, config), "1.5K");
+        assert_eq!(format_compact(12_345.0, false, config), "12K");
+        assert_eq!(format_compact(123_456.0, false, config), "123K");
+        assert_eq!(format_compact(2_300_000.0, false, config), "2.3M");
+        assert_eq!(format_compact(4_560_000_000.0, false, config), "4.6B");
+        assert_eq!(format_compact(7e12, false, config), "7.0T");
+        assert_eq!(format_compact(2.5e15, false, config), "2500T");
+        assert_eq!(format_compact(-1500.0, true, config), "-1.5k");
+        // The suffix and unit count towards max_width
+        assert_eq!(format_compact(1500.0, false, config.max_width(4)), "1.5K");
+        assert_eq!(format_compact(1500.0, false, config.max_width(3)), "2K");
+        assert_eq!(format_compact(1500.0, false, config.max_width(1)), "#");
+        assert_eq!(
+            format_compact(1500.0, false, config.unit("m").max_width(4)),
+            "2K m"
+        );
+        assert_eq!(
+            format_compact(1500.0, false, config.unit("m").max_width(3)),
+            "###"
+        );
+        assert_eq!(
+            format_compact(1500.0, false, config.unit("m").max_width(6)),
+            "1.5K m"
+        );
+        assert_eq!(
+            format_compact(42.0, false, config.unit("m").max_width(3)),
+            "###"
+        );
+        assert_eq!(
+            format_compact(42.0, false, config.unit("m").max_width(4)),
+            "42 m"
+        );
+        for width in 0..10 {
+            let s = format_compact(-123_456.0, false, config.unit("kg").max_width(width));
+            assert!(s.chars().count() <= width as usize, "{:?}", s);
         }
+        // Rounding up across a boundary uses the next suffix
+        assert_eq!(format_compact(999.5, false, config), "1.0K");
+        assert_eq!(format_compact(999_500.0, false, config), "1.0M");
+        assert_eq!(format_compact(999_499.0, false, config), "999K");
+        assert_eq!(format_compact(999_999_999.0, true, config), "1.0b");
+        assert_eq!(format_compact(999_500.0, false, config.truncate()), "999K");
+        // Significant digits and padding come from the config
+        let three = config.max_significant_digits(3);
+        assert_eq!(format_compact(1500.0, false, three), "1.50K");
+        assert_eq!(format_compact(1234.0, false, three), "1.23K");
+        assert_eq!(format_compact(999_950.0, false, three), "1.00M");
+        assert_eq!(
+            format_compact(1500.0, false, three.min_significant_digits(1)),
+            "1.5K"
+        );
+        assert_eq!(
+            format_compact(1500.0, false, config.unit("views")),
+            "1.5K views"
+        );
+        assert_eq!(format_compact(f64::NAN, false, config), "NaN");
     }
-    if let Some(limit) = config.min_decimal_digits {
-        // Pad 0's to get enough decimal digits
-        let adjusted_limit_position = limit as i32 + e;
-        while (digits.len() as i32) < adjusted_limit_position {
-            digits.push(digit_to_u8(0));
+
+    #[test]
+    fn test_min_sig_digits_in_fraction() {
+        let config = FmtFloatConfig::default()
+            .min_significant_digits(5)
+            .add_point_zero(false);
+        assert_eq!(dtoa(12.0, config), "12.000");
+        assert_eq!(dtoa(340.0, config), "340.00");
+        assert_eq!(dtoa(1234.0, config), "1234.0");
+        let config = config.min_sig_digits_in_fraction(false);
+        assert_eq!(dtoa(12.0, config), "12");
+        assert_eq!(dtoa(340.0, config), "340");
+        assert_eq!(dtoa(-7.0, config), "-7");
+        assert_eq!(dtoa(1234.0, config), "1234");
+        assert_eq!(dtoa(0.0, config), "0");
+        // Values with a fractional part are still padded
+        assert_eq!(dtoa(12.5, config), "12.500");
+        assert_eq!(dtoa(0.25, config), "0.25000");
+        assert_eq!(dtoa(340.0, config.add_point_zero(true)), "340.0");
+    }
+
+    #[test]
+    fn test_dtoa_abs() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_abs(-1.5, config), "1.5");
+        assert_eq!(dtoa_abs(1.5, config), "1.5");
+        assert_eq!(dtoa_abs(-1e300, config), "1.0e300");
+        assert_eq!(dtoa_abs(-0.0, config), "0.0");
+        assert_eq!(dtoa_abs(f64::NEG_INFINITY, config), "inf");
+        assert_eq!(dtoa_abs(-f64::NAN, config), "NaN");
+        assert_eq!(dtoa_abs(-123.456, config.max_width(5)), "123.5");
+        assert_eq!(dtoa_abs(-2.5, config.sign_column(true)), " 2.5");
+    }
+
+    #[test]
+    fn test_min_round_trip_width() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(min_round_trip_width(0.0, config), 3);
+        assert_eq!(min_round_trip_width(-2.5, config), 4);
+        assert_eq!(min_round_trip_width(0.1 + 0.2, config), 19);
+        assert_eq!(min_round_trip_width(1e-10, config.max_width(3)), 5);
+        assert_eq!(min_round_trip_width(f64::NAN, config), 3);
+        assert_eq!(min_round_trip_width(f64::NEG_INFINITY, config), 4);
+        // A lossy config can't round-trip at any width
+        let lossy = config.max_significant_digits(2);
+        assert_eq!(min_round_trip_width(1.2345, lossy), 3);
+        // The sign column and exponent formatter count towards the width
+        let column = config.sign_column(true);
+        assert_eq!(min_round_trip_width(3.25, column), 5);
+        assert_eq!(dtoa(3.25, column.max_width(5)), " 3.25");
+        let spelled = config.exponent_formatter(|e| format!(" x 10^{}", e));
+        assert_eq!(min_round_trip_width(1.2345e10, spelled), 14);
+        assert_eq!(dtoa(1.2345e10, spelled.max_width(14)), "1.2345 x 10^10");
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let val = (rng.gen::<f64>() - 0.5) * 10f64.powi((rng.gen::<f64>() * 24.0) as i32 - 12);
+            let configs = [
+                config,
+                config.add_point_zero(false).group_separator(','),
+                column.unit("m").truncation_marker('~'),
+                spelled.force_e_notation(),
+            ];
+            for config in &configs {
+                let width = min_round_trip_width(val, *config);
+                let s = dtoa(val, config.max_width(width));
+                assert_eq!(dtoa_strict(val, config.max_width(width)), s);
+                assert!(!round_trips(val, config.max_width(width - 1)), "{}", s);
+            }
         }
     }
-    let mut use_e_notation = (e > config.upper_e_break as i32
-        || e <= config.lower_e_break as i32
-        || config.force_e_notation)
-        && !config.force_no_e_notation;
-    if let Some(max_width) = config.max_width {
-        // Check if it is needed to force using e notation for max width
-        let max_width = if sign { max_width - 1 } else { max_width };
-        // Is it impossible to represent the value without e notation?
-        if e > 0 && e + if config.add_point_zero { 2 } else { 0 } > max_width as i32 {
-            hit!(e_width_case_a);
-            use_e_notation = true;
-        } else if -e + 3 > max_width as i32 {
-            hit!(e_width_case_b);
-            use_e_notation = true;
-        } else if !use_e_notation {
-            hit!(e_width_case_c);
-            // Otherwise, prepare to not use e notation
-            let is_integer = e > digits.len() as i32;
-            let extra_length = if config.add_point_zero && is_integer {
-                2
-            } else {
-                0
-            } + if !is_integer && !(e > 0 && e as u8 == max_width) {
-                1
-            } else {
-                0
-            } + if e > 0 && digits.len() < e as usize {
-                e - digits.len() as i32
-            } else {
-                0
-            } + if e <= 0 { -e + 1 } else { 0 };
-            let total_length = digits.len() + extra_length as usize;
-            if total_length > max_width as usize {
-                let final_char = digits
-                    .drain((max_width as usize - extra_length as usize)..)
-                    .nth(0)
-                    .unwrap();
-                if config.round_mode == RoundMode::Round && final_char >= digit_to_u8(5) {
-                    // round up
-                    let mut l = digits.len() - 1;
-                    digits[l] += 1;
-                    while digits[l] == digit_to_u8(10) {
-                        if l == 0 {
-                            digits[0] = digit_to_u8(1);
-                            e += 1;
-                            break;
-                        }
-                        digits.pop();
-                        l -= 1;
-                        digits[l] += 1;
+
+    #[test]
+    fn test_templates() {
+        let config = FmtFloatConfig::default()
+            .positive_template("+{}")
+            .negative_template("({})")
+            .zero_template("—");
+        assert_eq!(dtoa(2.5, config), "+2.5");
+        assert_eq!(dtoa(-2.5, config), "(2.5)");
+        assert_eq!(dtoa(0.0, config), "—");
+        assert_eq!(dtoa(-0.0, config), "—");
+        assert_eq!(dtoa(0.001, config.max_decimal_digits(2)), "—");
+        assert_eq!(dtoa(0.0, config.zero_template("{} USD")), "0.0 USD");
+        assert_eq!(dtoa(-0.0, config.no_zero_template()), "(0.0)");
+        assert_eq!(dtoa(f64::NEG_INFINITY, config), "-inf");
+        assert_eq!(dtoa(f64::NAN, config), "NaN");
+        // Only some templates set
+        let accounting = FmtFloatConfig::default().negative_template("({})");
+        assert_eq!(dtoa(2.5, accounting), "2.5");
+        assert_eq!(dtoa(-2.5, accounting.sign_column(true)), "(2.5)");
+        assert_eq!(dtoa(2.5, accounting.sign_column(true)), " 2.5");
+        // Grouping and e notation go inside the template
+        let grouped = accounting
+            .group_separator(',')
+            .force_no_e_notation()
+            .add_point_zero(false);
+        assert_eq!(dtoa(-1234567.5, grouped), "(1,234,567.5)");
+        assert_eq!(dtoa(-1.5e-10, accounting), "(1.5e-10)");
+        assert_eq!(dtoa(-2.0, accounting.unit("kg")), "(2.0 kg)");
+        let s = dtoa_structured(-1.5e-10, accounting);
+        assert_eq!(s.radix_index, Some(2));
+        assert_eq!(s.exponent_index, Some(4));
+        assert_eq!(s.sign_len, 1);
+        // Templates count towards max_width
+        assert_eq!(dtoa(-1.23456, accounting.max_width(6)), "(1.23)");
+        assert_eq!(dtoa(-123456.0, grouped.max_width(9)), "(123,456)");
+        assert_eq!(dtoa(-123456.0, grouped.max_width(8)), "(1.23e5)");
+        assert_eq!(dtoa_strict(-0.1, accounting.max_width(5)), "(0.1)");
+        assert_eq!(min_round_trip_width(-0.1 - 0.2, accounting), 21);
+    }
+
+    #[test]
+    fn test_exponent_contract() {
+        // For values m * 10^k with 1 <= m < 10, there are k + 1 digits left
+        // of the decimal point, and the exponent written is k
+        let breaks = [
+            (4, -4),
+            (3, -3),
+            (0, 0),
+            (1, -1),
+            (8, -8),
+            (17, -7),
+            (-2, -6),
+        ];
+        for k in -20..=20 {
+            for m in &["1", "1.5", "9.99"] {
+                let val: f64 = format!("{}e{}", m, k).parse().unwrap();
+                for val in &[val, -val] {
+                    assert_eq!(f64_digits(*val).1, k + 1);
+                    let forced = FmtFloatConfig::default().force_e_notation();
+                    assert_eq!(printed_exponent(*val, forced), Some(k));
+                    assert!(dtoa(*val, forced).ends_with(&format!("e{}", k)));
+                    for (upper, lower) in &breaks {
+                        let config = FmtFloatConfig::default()
+                            .upper_e_break(*upper)
+                            .lower_e_break(*lower);
+                        let e_notation = k >= *upper as i32 || k < *lower as i32;
+                        let expected = if e_notation { Some(k) } else { None };
+                        assert_eq!(printed_exponent(*val, config), expected, "{}", val);
+                        assert_eq!(dtoa(*val, config).contains('e'), e_notation);
                     }
                 }
             }
         }
+        // The break is checked after rounding changes the exponent
+        let config = FmtFloatConfig::default().max_significant_digits(3);
+        assert_eq!(dtoa(9995.0, config), "1.0e4");
+        assert_eq!(dtoa(9994.0, config), "9990.0");
+        assert_eq!(dtoa(0.000099996, config), "0.0001");
+        assert_eq!(dtoa(0.00009994, config), "9.99e-5");
     }
-    // Final formatting stage
-    if use_e_notation {
-        let mut add_zero_after_radix_point = config.max_width.is_none();
-        if let Some(max_width) = config.max_width {
-            let mut tail_as_str: String = digits.drain(1..).map(|val| val as char).collect();
-            let e_length = format!("{}", e - 1).len();
-            let extra_length = 3 + e_length + if sign { 1 } else { 0 };
-            if extra_length >= max_width as usize {
-                tail_as_str.drain(..);
-            } else {
-                tail_as_str.truncate(max_width as usize - extra_length);
-            }
-            if tail_as_str.len() + extra_length + 1 <= max_width as usize {
-                add_zero_after_radix_point = true;
-            }
-            // Very special case: can't include a decimal point
-            // within max_width
-            if tail_as_str.len() == 0 && max_width == 7 && sign {
-                return format!(
-                    "-{}{}{}",
-                    digits[0] as char,
-                    if config.capitalize_e { "E" } else { "e" },
-                    e - 1
+
+    #[test]
+    fn test_integer_mantissa() {
+        let config = FmtFloatConfig::default().integer_mantissa(true);
+        assert_eq!(dtoa(3.24, config), "324e-2");
+        assert_eq!(dtoa(-1500.0, config), "-15e2");
+        assert_eq!(dtoa(7.0, config), "7e0");
+        assert_eq!(dtoa(0.0, config), "0e0");
+        assert_eq!(dtoa(1e-300, config), "1e-300");
+        assert_eq!(dtoa(0.1 + 0.2, config), "30000000000000004e-17");
+        assert_eq!(dtoa(7.0, config.suppress_zero_exponent(true)), "7");
+        assert_eq!(dtoa(3.24, config.capitalize_e(true)), "324E-2");
+        assert_eq!(dtoa(3.24159, config.max_significant_digits(3)), "324e-2");
+        assert_eq!(dtoa(2.5, config.min_significant_digits(3)), "250e-2");
+        assert_eq!(ftoa(0.3, config), "3e-1");
+        let s = dtoa_structured(-3.24, config);
+        assert_eq!(s.exponent_index, Some(4));
+        assert_eq!(s.exponent, Some(-2));
+        assert_eq!(s.radix_index, None);
+        // The value is exactly the mantissa times 10 to the exponent
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let val = f64::from_bits(rng.gen::<u64>());
+            if !val.is_finite() {
+                continue;
+            }
+            let s = dtoa(val, config);
+            let (mantissa, exponent) = s.split_at(s.find('e').unwrap());
+            let mantissa: i64 = mantissa.parse().unwrap();
+            let exponent: i32 = exponent[1..].parse().unwrap();
+            let (digits, e) = f64_digits(val.abs());
+            assert_eq!(mantissa.unsigned_abs().to_string().as_bytes(), &digits[..]);
+            assert_eq!(exponent, e - digits.len() as i32);
+            assert_eq!(format!("{}e{}", mantissa, exponent).parse::<f64>(), Ok(val));
+        }
+    }
+
+    #[test]
+    fn test_dtoa_csv_field() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_csv_field(-2.5, ',', config), "-2.5");
+        assert_eq!(dtoa_csv_field(f64::NAN, ',', config), "NaN");
+        let comma = config.radix_point(',');
+        assert_eq!(dtoa_csv_field(-2.5, ',', comma), "\"-2,5\"");
+        assert_eq!(dtoa_csv_field(-2.5, '\t', comma), "-2,5");
+        assert_eq!(dtoa_csv_field(2.0, ',', comma), "\"2,0\"");
+        let grouped = config.group_separator(',').force_no_e_notation();
+        assert_eq!(dtoa_csv_field(1234567.0, ',', grouped), "\"1,234,567.0\"");
+        assert_eq!(dtoa_csv_field(123.0, ',', grouped), "123.0");
+        assert_eq!(dtoa_csv_field(5.0, ',', config.unit("\"")), "\"5.0 \"\"\"");
+        assert_eq!(dtoa_csv_field(5.0, ';', config.unit("in;")), "\"5.0 in;\"");
+    }
+
+    #[test]
+    fn test_digit_ranges() {
+        let config = FmtFloatConfig::default().significant_digits(2..=4);
+        assert_eq!(config.min_sig_digits, Some(2));
+        assert_eq!(config.max_sig_digits, Some(4));
+        assert_eq!(dtoa(2.0, config), "2.0");
+        assert_eq!(dtoa(3.24159, config), "3.242");
+        assert_eq!(dtoa(0.5, config), "0.50");
+        let config = FmtFloatConfig::default().decimal_digits(2..=3);
+        assert_eq!(config.min_decimal_digits, Some(2));
+        assert_eq!(config.max_decimal_digits, Some(3));
+        assert_eq!(dtoa(2.5, config), "2.50");
+        assert_eq!(dtoa(1.23456, config), "1.235");
+        assert_eq!(dtoa(7.0, config), "7.00");
+        let config = FmtFloatConfig::default().decimal_digits(0..=0);
+        assert_eq!(dtoa(1.5, config.add_point_zero(false)), "2");
+    }
+
+    #[test]
+    fn test_min_decimal_digits_e_notation() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .min_decimal_digits(4);
+        assert_eq!(dtoa(1500.0, config), "1.5000e3");
+        assert_eq!(dtoa(-1.0, config), "-1.0000e0");
+        assert_eq!(dtoa(0.00025, config), "2.5000e-4");
+        assert_eq!(dtoa(1.234567, config), "1.234567e0");
+        assert_eq!(dtoa(1500.0, config.min_decimal_digits(0)), "1.5e3");
+        assert_eq!(dtoa(1500.0, config.min_significant_digits(7)), "1.500000e3");
+        assert_eq!(dtoa(1500.0, config.max_exponent_magnitude(2)), "15.0000e2");
+        assert_eq!(dtoa(1.5e-3, config.max_exponent_magnitude(2)), "0.1500e-2");
+        assert_eq!(dtoa(1500.0, config.max_width(7)), "1.500e3");
+        // Values that only use e notation to fit max_width
+        let config = FmtFloatConfig::default().min_decimal_digits(2);
+        assert_eq!(dtoa(1500.0, config), "1500.00");
+        assert_eq!(dtoa(150000.0, config), "1.50e5");
+        assert_eq!(dtoa(1500.0, config.max_width(6)), "1500.0");
+        assert_eq!(dtoa(15000.0, config.max_width(6)), "1.50e4");
+    }
+
+    #[test]
+    fn test_debug_decompose() {
+        assert_eq!(
+            debug_decompose(f64::MAX),
+            "sign: +, digits: 17976931348623157, e: 309, exponent: 308, \
+             notation: scientific, default: 1.7976931348623157e308"
+        );
+        assert_eq!(
+            debug_decompose(0.1 + 0.2),
+            "sign: +, digits: 30000000000000004, e: 0, exponent: -1, \
+             notation: fixed, default: 0.30000000000000004"
+        );
+        assert_eq!(
+            debug_decompose(-0.0),
+            "sign: -, digits: 0, e: 1, exponent: 0, notation: fixed, default: -0.0"
+        );
+        assert_eq!(debug_decompose(f64::NEG_INFINITY), "non-finite: -inf");
+        assert_eq!(debug_decompose(f64::NAN), "non-finite: NaN");
+    }
+
+    #[test]
+    fn test_max_mantissa_digits() {
+        let config = FmtFloatConfig::default().max_mantissa_digits(3);
+        assert_eq!(dtoa(1234.56, config.force_e_notation()), "1.23e3");
+        assert_eq!(dtoa(1235.56, config.force_e_notation()), "1.24e3");
+        assert_eq!(dtoa(-9996.0, config.force_e_notation()), "-1.0e4");
+        assert_eq!(
+            dtoa(1234.56, config.force_e_notation().truncate()),
+            "1.23e3"
+        );
+        assert_eq!(dtoa(1.5, config.force_e_notation()), "1.5e0");
+        // Only scientific notation is affected
+        assert_eq!(dtoa(1234.56, config), "1234.56");
+        assert_eq!(dtoa(0.0000123456, config), "1.23e-5");
+        assert_eq!(dtoa(123456.0, config), "1.23e5");
+        assert_eq!(dtoa(99999.0, config.max_mantissa_digits(0)), "1.0e5");
+        // The mantissa limit overrides the minimums
+        let padded = config.force_e_notation().min_decimal_digits(4);
+        assert_eq!(dtoa(1500.0, padded), "1.50e3");
+        assert_eq!(dtoa(1500.0, padded.no_max_mantissa_digits()), "1.5000e3");
+        assert_eq!(dtoa(1.23456e3, config.max_exponent_magnitude(2)), "1234.56");
+        assert_eq!(
+            dtoa(1.23456e5, config.max_exponent_magnitude(2)),
+            "1230.0e2"
+        );
+        assert_eq!(
+            dtoa(1234.56, config.force_e_notation().truncation_marker('~')),
+            "1.23e3~"
+        );
+    }
+
+    #[test]
+    fn test_nice_ticks() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            nice_ticks(0.0, 10.0, 5, config),
+            vec!["0.0", "2.0", "4.0", "6.0", "8.0", "10.0"]
+        );
+        assert_eq!(nice_ticks(1.0, 0.0, 3, config), vec!["0.0", "0.5", "1.0"]);
+        assert_eq!(
+            nice_ticks(0.001, 0.0042, 4, config),
+            vec!["0.001", "0.002", "0.003", "0.004", "0.005"]
+        );
+        assert_eq!(
+            nice_ticks(0.3, 0.71, 5, config),
+            vec!["0.3", "0.4", "0.5", "0.6", "0.7", "0.8"]
+        );
+        assert_eq!(
+            nice_ticks(0.3, 0.5, 5, config),
+            vec!["0.30", "0.35", "0.40", "0.45", "0.50"]
+        );
+        assert_eq!(
+            nice_ticks(-0.25, 0.25, 3, config.add_point_zero(false)),
+            vec!["-0.4", "-0.2", "0.0", "0.2", "0.4"]
+        );
+        assert_eq!(
+            nice_ticks(0.0, 5e6, 6, config),
+            vec!["0.0e0", "1.0e6", "2.0e6", "3.0e6", "4.0e6", "5.0e6"]
+        );
+        assert_eq!(nice_ticks(2.5, 2.5, 5, config), vec!["2.5"]);
+        assert_eq!(nice_ticks(0.0, f64::NAN, 5, config), Vec::<String>::new());
+        // Ranges too narrow for their magnitude only get their ends
+        let next_up = f64::from_bits(1e300f64.to_bits() + 1);
+        assert_eq!(
+            nice_ticks(1e300, next_up, 1_000_000, config),
+            dtoa_uniform_notation(&[1e300, next_up], config)
+        );
+        assert_eq!(nice_ticks(1.0, 1.0 + f64::EPSILON, 5000, config).len(), 2);
+        assert_eq!(nice_ticks(-1e20, -1e20 + 16384.0, 3000, config).len(), 2);
+        // Ticks are increasing, evenly spaced, and cover the range
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let scale = 10f64.powi((rng.gen::<f64>() * 20.0) as i32 - 10);
+            let a = (rng.gen::<f64>() - 0.5) * scale;
+            let b = (rng.gen::<f64>() - 0.5) * scale;
+            let count = 2 + (rng.gen::<f64>() * 10.0) as usize;
+            let ticks = nice_ticks(a, b, count, config.force_no_e_notation());
+            let values: Vec<f64> = ticks.iter().map(|tick| tick.parse().unwrap()).collect();
+            assert!(values[0] <= a.min(b) && values[values.len() - 1] >= a.max(b));
+            assert!(values.len() <= 2 * count + 2, "{:?}", ticks);
+            let decimals = |tick: &String| tick.len() - tick.find('.').unwrap();
+            for pair in ticks.windows(2) {
+                assert_eq!(decimals(&pair[0]), decimals(&pair[1]), "{:?}", ticks);
+            }
+            let step = values[1] - values[0];
+            for pair in values.windows(2) {
+                assert!(pair[0] < pair[1]);
+                assert!(
+                    ((pair[1] - pair[0]) - step).abs() <= step * 1e-9,
+                    "{:?}",
+                    ticks
                 );
             }
-            // Defer to the generic e-notation case
-            for c in tail_as_str.chars() {
-                digits.push(c as u8);
+        }
+    }
+
+    #[test]
+    fn test_dtoa_precision() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_precision(0.1, 4, config), "0.1000");
+        assert_eq!(dtoa_precision(2.0, 3, config), "2.00");
+        assert_eq!(dtoa_precision(-1.23456, 3, config), "-1.23");
+        assert_eq!(dtoa_precision(0.0, 2, config), "0.0");
+        assert_eq!(dtoa_precision(100.0, 3, config), "100");
+        assert_eq!(dtoa_precision(100.0, 1, config), "1e2");
+        assert_eq!(dtoa_precision(5.0, 0, config), "5");
+        assert_eq!(dtoa_precision(0.000123456, 2, config), "0.00012");
+        assert_eq!(dtoa_precision(0.0000123456, 2, config), "1.2e-5");
+        // Notation follows the breaks, unless integer zeros would be ambiguous
+        assert_eq!(dtoa_precision(1234.0, 4, config), "1234");
+        assert_eq!(
+            dtoa_precision(1234.0, 4, config.upper_e_break(3)),
+            "1.234e3"
+        );
+        assert_eq!(dtoa_precision(1234.0, 6, config), "1234.00");
+        assert_eq!(dtoa_precision(12345.0, 3, config), "1.23e4");
+        assert_eq!(dtoa_precision(999.6, 3, config), "1.00e3");
+        assert_eq!(dtoa_precision(999.6, 3, config.truncate()), "999");
+        assert_eq!(
+            dtoa_precision(12345.0, 3, config.force_no_e_notation()),
+            "12300"
+        );
+        // Options that would change the number of digits are ignored
+        let noisy = config
+            .max_decimal_digits(1)
+            .min_decimal_digits(5)
+            .simplify(true)
+            .keep_integer_digits(true);
+        assert_eq!(dtoa_precision(3.24159, 4, noisy), "3.242");
+        assert_eq!(dtoa_precision(0.5, 3, noisy), "0.500");
+        assert_eq!(dtoa_precision(f64::NAN, 3, noisy), "NaN");
+    }
+
+    #[test]
+    fn test_dtoa_factored() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            dtoa_factored(&[0.0, -0.0, 12.5, f64::NAN], config),
+            (
+                1,
+                ["0.0", "-0.0", "1.25", "NaN"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            )
+        );
+        assert_eq!(
+            dtoa_factored(&[0.002, -0.00031], config),
+            (-3, vec!["2.0".to_string(), "-0.31".to_string()])
+        );
+        assert_eq!(dtoa_factored(&[0.0], config), (0, vec!["0.0".to_string()]));
+        assert_eq!(dtoa_factored(&[], config), (0, vec![]));
+        assert_eq!(
+            dtoa_factored(&[123456.0, 1.0], config.max_significant_digits(3)),
+            (5, vec!["1.23".to_string(), "0.00001".to_string()])
+        );
+        // The strings times 10 to the exponent give back the values
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let values: Vec<f64> = (0..5)
+                .map(|_| {
+                    (rng.gen::<f64>() - 0.5) * 10f64.powi((rng.gen::<f64>() * 40.0) as i32 - 20)
+                })
+                .collect();
+            let (exponent, strings) = dtoa_factored(&values, config);
+            for (value, s) in values.iter().zip(&strings) {
+                assert_eq!(format!("{}e{}", s, exponent).parse::<f64>(), Ok(*value));
+            }
+            let rounded = config.max_significant_digits(4);
+            let (exponent, strings) = dtoa_factored(&values, rounded);
+            for (value, s) in values.iter().zip(&strings) {
+                let parsed: f64 = format!("{}e{}", s, exponent).parse().unwrap();
+                assert!(
+                    (parsed - value).abs() <= value.abs() * 5e-4,
+                    "{} {}",
+                    s,
+                    value
+                );
             }
         }
-        // Generic e-notation case
-        let mut res = String::with_capacity(digits.len() + 5);
-        if sign {
-            res.push('-');
+    }
+
+    #[test]
+    fn test_exp_case() {
+        let config = FmtFloatConfig::default();
+        let cases = [
+            (ExpCase::Lower, ["1.5e3", "1.5e-30", "1.5e0"]),
+            (ExpCase::Upper, ["1.5E3", "1.5E-30", "1.5E0"]),
+            (ExpCase::LowerForPositive, ["1.5e3", "1.5E-30", "1.5e0"]),
+            (ExpCase::UpperForPositive, ["1.5E3", "1.5e-30", "1.5E0"]),
+        ];
+        for (case, expected) in &cases {
+            let config = config.exp_case(*case).upper_e_break(3);
+            assert_eq!(dtoa(1.5e3, config), expected[0]);
+            assert_eq!(dtoa(1.5e-30, config), expected[1]);
+            assert_eq!(dtoa(1.5, config.force_e_notation()), expected[2]);
+        }
+        assert_eq!(config.capitalize_e(true).exp_case, ExpCase::Upper);
+        assert_eq!(config.capitalize_e(false).exp_case, ExpCase::Lower);
+        let mixed = config.exp_case(ExpCase::UpperForPositive);
+        assert_eq!(dtoa(-2e-5, mixed.max_exponent_magnitude(3)), "-0.02e-3");
+        assert_eq!(
+            dtoa(2e-5, mixed.exponent_formatter(|e| format!("x{}", e))),
+            "2.0x-5"
+        );
+    }
+
+    #[test]
+    fn test_buffer() {
+        // Adapted from ryu's buffer tests
+        let config = FmtFloatConfig::default();
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.format(1.234, config), "1.234");
+        assert_eq!(buffer.format(0.3, config), "0.3");
+        assert_eq!(buffer.format(1e23, config), "1.0e23");
+        assert_eq!(buffer.format(-0.0, config), "-0.0");
+        assert_eq!(buffer.format(f64::NAN, config), "NaN");
+        assert_eq!(buffer.format(f64::INFINITY, config), "inf");
+        assert_eq!(buffer.format(f64::NEG_INFINITY, config), "-inf");
+        assert_eq!(buffer.format_finite(1.234, config), "1.234");
+        assert_eq!(
+            buffer.format_finite(f64::MAX, config),
+            "1.7976931348623157e308"
+        );
+        assert_eq!(
+            buffer.format_finite(f64::MIN_POSITIVE, config),
+            "2.2250738585072014e-308"
+        );
+        // The config is used, and the buffer can be reused
+        let rounded = config.max_significant_digits(2).radix_point(',');
+        assert_eq!(buffer.format(1.234, rounded), "1,2");
+        assert_eq!(buffer.format(1e23, config.force_no_e_notation()).len(), 26);
+        assert_eq!(buffer.format(7.0, config), "7.0");
+        assert_eq!(format!("{:?}", buffer), "Buffer(\"7.0\")");
+        // Text longer than the buffer is fit to it like with max_byte_width
+        let long = config.force_no_e_notation();
+        assert_eq!(buffer.format(1e100, long), "1.0e100");
+        assert_eq!(
+            buffer.format(1e100, long),
+            dtoa(1e100, long.max_byte_width(Buffer::CAPACITY as u16))
+        );
+        assert_eq!(buffer.format(-1e60, long.max_byte_width(4)), "####");
+        let unit = config.unit("×10⁻³ kg·m²·s⁻²").approx_marker('≈');
+        assert_eq!(buffer.format(1.0 / 3.0, unit), dtoa(1.0 / 3.0, unit));
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let val = f64::from_bits(rng.gen::<u64>());
+            assert_eq!(buffer.format(val, config), dtoa(val, config));
+            assert_eq!(buffer.format(val, unit), dtoa(val, unit));
+            assert_eq!(
+                buffer.format(val, long),
+                dtoa(val, long.max_byte_width(Buffer::CAPACITY as u16))
+            );
         }
-        res.push(digits[0] as char);
-        res.push(config.radix_point);
-        if digits.len() == 1 {
-            if add_zero_after_radix_point {
-                res.push('0');
+    }
+
+    #[test]
+    fn test_precise_tie_detection() {
+        // Only the first removed digit and whether any follow it matter
+        let remainder = |digits: &[u8], position| dropped_remainder(digits, 1, position, None);
+        assert_eq!(remainder(b"125", 2), Ordering::Equal);
+        assert_eq!(remainder(b"12500", 2), Ordering::Equal);
+        assert_eq!(remainder(b"1251", 2), Ordering::Greater);
+        assert_eq!(remainder(b"1249", 2), Ordering::Less);
+        assert_eq!(remainder(b"126", 2), Ordering::Greater);
+        assert_eq!(remainder(b"12", 2), Ordering::Less);
+        // Ties are broken by the exact value of the float
+        let exact = |value: f64, position| {
+            let (digits, e) = f64_digits(value);
+            dropped_remainder(&digits, e, position, Some(value))
+        };
+        assert_eq!(exact(0.125, 2), Ordering::Equal);
+        assert_eq!(exact(2.675, 3), Ordering::Less);
+        assert_eq!(exact(2.665, 3), Ordering::Greater);
+        assert_eq!(exact(2.6751, 3), Ordering::Greater);
+        assert_eq!(exact(0.000125, 2), Ordering::Greater);
+        assert_eq!(compare_float_to_digits(0.5, b"005", 2), Ordering::Equal);
+        assert_eq!(compare_float_to_digits(0.0, b"0", 1), Ordering::Equal);
+        assert_eq!(compare_float_to_digits(0.0, b"1", 1), Ordering::Less);
+        // Just over or under half
+        let config = FmtFloatConfig::default().max_decimal_digits(2);
+        let precise = config.precise_tie_detection(true);
+        assert_eq!(dtoa(2.675, config), "2.68");
+        assert_eq!(dtoa(2.675, precise), "2.67");
+        assert_eq!(dtoa(2.665, precise), "2.67");
+        assert_eq!(dtoa(-1.005, precise), "-1.00");
+        assert_eq!(ftoa(2.665, precise), "2.66");
+        // Exactly half still rounds up
+        assert_eq!(dtoa(0.125, precise), "0.13");
+        assert_eq!(dtoa(0.375, precise.max_decimal_digits(1)), "0.4");
+        // Ties made by an earlier cut are decided by the float too
+        let twice = precise.max_significant_digits(4).max_decimal_digits(2);
+        assert_eq!(dtoa(2.67498, twice), "2.67");
+        assert_eq!(dtoa(2.67498, twice.precise_tie_detection(false)), "2.68");
+        // Only round() is affected
+        assert_eq!(dtoa(2.665, precise.truncate()), "2.66");
+        let precise_sig = FmtFloatConfig::default().max_significant_digits(3);
+        assert_eq!(dtoa(2.675, precise_sig.precise_tie_detection(true)), "2.67");
+        // Cuts of inexact ties match Rust's formatting, which rounds the exact value
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let val = (rng.gen::<f64>() * 1000.0).round() / 1000.0 + 0.0005;
+            let (digits, e) = f64_digits(val);
+            if compare_float_to_digits(val, &digits, e) == Ordering::Equal {
+                // Rust rounds exact ties to even
+                continue;
             }
-        } else {
-            for c in &digits[1..] {
-                res.push(*c as char);
+            let expected = format!("{:.3}", val);
+            let s = dtoa(val, precise.max_decimal_digits(3).min_decimal_digits(3));
+            assert_eq!(s, expected);
+        }
+    }
+
+    #[test]
+    fn test_dtoa_latex() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            dtoa_latex(1.5e3, "\\times", config.upper_e_break(3)),
+            "1.5 \\times 10^{3}"
+        );
+        assert_eq!(
+            dtoa_latex(-1.5e-30, "\\times", config),
+            "-1.5 \\times 10^{-30}"
+        );
+        assert_eq!(dtoa_latex(6.02e23, "\\cdot", config), "6.02 \\cdot 10^{23}");
+        assert_eq!(dtoa_latex(-0.25, "\\times", config), "-0.25");
+        assert_eq!(dtoa_latex(f64::INFINITY, "\\times", config), "\\infty");
+        assert_eq!(dtoa_latex(f64::NEG_INFINITY, "\\times", config), "-\\infty");
+        assert_eq!(dtoa_latex(f64::NAN, "\\times", config), "\\text{NaN}");
+        // Options that change the exponent text
+        let fancy = config
+            .radix_point(',')
+            .capitalize_e(true)
+            .exponent_formatter(|e| format!("x{}", e));
+        assert_eq!(dtoa_latex(2.5e10, "\\times", fancy), "2.5 \\times 10^{10}");
+        assert_eq!(
+            dtoa_latex(2.5e10, "\\times", config.spoken_exponent(true)),
+            "2.5 \\times 10^{10}"
+        );
+        assert_eq!(
+            dtoa_latex(
+                1.5e3,
+                "\\times",
+                config.max_exponent_magnitude(2).force_e_notation()
+            ),
+            "15.0 \\times 10^{2}"
+        );
+        assert_eq!(
+            dtoa_latex(
+                2.5e10,
+                "\\times",
+                config.unit("\\,\\mathrm{m}").unit_space(false)
+            ),
+            "2.5 \\times 10^{10}\\,\\mathrm{m}"
+        );
+        assert_eq!(
+            dtoa_latex(
+                1.5,
+                "\\times",
+                config.force_e_notation().suppress_zero_exponent(true)
+            ),
+            "1.5"
+        );
+    }
+
+    #[test]
+    fn test_max_width_min_decimal_conflict() {
+        let config = FmtFloatConfig::default().min_decimal_digits(5);
+        assert_eq!(dtoa(1.5, config), "1.50000");
+        assert_eq!(dtoa(1.5, config.max_width(6)), "1.5000");
+        assert_eq!(dtoa(1.5, config.max_width(5)), "1.500");
+        assert_eq!(dtoa(-1.5, config.max_width(5)), "-1.50");
+        assert_eq!(dtoa(1.5, config.max_width(3)), "1.5");
+        assert_eq!(dtoa(1.25, config.max_width(4)), "1.25");
+        assert_eq!(dtoa(1.25, config.max_width(3)), "1.3");
+        assert_eq!(dtoa(123.0, config.max_width(6)), "123.00");
+        assert_eq!(
+            dtoa(1.5e20, config.force_e_notation().max_width(6)),
+            "1.5e20"
+        );
+        assert_eq!(
+            dtoa(1.5e2, config.force_e_notation().max_width(6)),
+            "1.50e2"
+        );
+        let sig = FmtFloatConfig::default().min_significant_digits(6);
+        assert_eq!(dtoa(1.5, sig.max_width(4)), "1.50");
+        // The width is never exceeded
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let value: f64 = (rng.gen::<f64>() - 0.5) * 1e6;
+            for width in 1..10 {
+                for limit in 0..8 {
+                    let config = FmtFloatConfig::default()
+                        .min_decimal_digits(limit)
+                        .max_width(width);
+                    assert!(dtoa(value, config).chars().count() <= width as usize);
+                }
             }
         }
-        if config.capitalize_e {
-            res.push('E');
-        } else {
-            res.push('e');
+    }
+
+    #[test]
+    fn test_dtoa_fit() {
+        assert_eq!(dtoa_fit(3.24159265358979, 5), "3.242");
+        assert_eq!(dtoa_fit(-3.24159265358979, 5), "-3.24");
+        assert_eq!(dtoa_fit(1234567.0, 6), "1.23e6");
+        assert_eq!(dtoa_fit(1e300, 5), "1e300");
+        assert_eq!(dtoa_fit(-1e300, 5), "#####");
+        assert_eq!(dtoa_fit(0.0, 5), "0");
+        assert_eq!(dtoa_fit(f64::NAN, 5), "NaN");
+        let mut rng = rand::thread_rng();
+        for _ in 0..300 {
+            let value = (rng.gen::<f64>() - 0.5)
+                * [1e-8, 1.0, 1e5, 1e15][(rng.gen::<f64>() * 4.0) as usize % 4];
+            for width in 5..=12 {
+                let fit = dtoa_fit(value, width);
+                assert!(fit.chars().count() <= width as usize, "{} {}", value, fit);
+                // At least as precise as cutting the default notation down,
+                // where pound signs are infinitely far off
+                let error = match fit.parse::<f64>() {
+                    Ok(parsed) => (parsed - value).abs(),
+                    Err(_) => f64::INFINITY,
+                };
+                let cut = dtoa(value, FmtFloatConfig::default().max_width(width).round());
+                if let Ok(parsed) = cut.parse::<f64>() {
+                    assert!(error <= (parsed - value).abs(), "{} {} {}", value, fit, cut);
+                }
+                // And no more significant digits fit in either notation
+                let config = FmtFloatConfig::default().add_point_zero(false);
+                for notation in [config.force_no_e_notation(), config.force_e_notation()] {
+                    for sig_digits in 1..=17 {
+                        let text = dtoa(value, notation.max_significant_digits(sig_digits).round());
+                        if text.chars().count() <= width as usize {
+                            let other = (text.parse::<f64>().unwrap() - value).abs();
+                            assert!(error <= other, "{} {} {}", value, fit, text);
+                        }
+                    }
+                }
+            }
         }
-        res.push_str(format!("{}", e - 1).as_ref());
-        return res;
     }
-    // Non-e-notation case
-    let mut as_str = String::with_capacity(digits.len() + 3);
-    if sign {
-        as_str.push('-');
+
+    #[test]
+    fn test_mantissa_digits() {
+        assert_eq!(mantissa_digits(0.3), b"3");
+        assert_eq!(mantissa_digits(-2.5e-300), b"25");
+        assert_eq!(mantissa_digits(1e23), b"1");
+        assert_eq!(mantissa_digits(100.0), b"1");
+        assert_eq!(mantissa_digits(0.0), b"0");
+        assert_eq!(mantissa_digits(-0.0), b"0");
+        // Long runs of nines and zeros
+        assert_eq!(
+            mantissa_digits(1.0 - f64::EPSILON / 2.0),
+            b"9999999999999999"
+        );
+        assert_eq!(mantissa_digits(0.7999999999999999), b"7999999999999999");
+        assert_eq!(mantissa_digits(1.0 + f64::EPSILON), b"10000000000000002");
+        assert_eq!(mantissa_digits(1.0000000000000007e20), b"10000000000000007");
+        assert_eq!(mantissa_digits(f64::MAX), b"17976931348623157");
+        assert_eq!(mantissa_digits(f64::INFINITY), b"");
+        assert_eq!(mantissa_digits(f64::NAN), b"");
     }
-    let mut curr = 0;
-    if e <= 0 {
-        as_str.push('0');
-        as_str.push(config.radix_point);
-        for _ in 0..-e {
-            as_str.push('0');
+
+    #[test]
+    fn test_rounding_across_e_break() {
+        let config = FmtFloatConfig::default();
+        // Rounding up past upper_e_break switches to e notation
+        let sig = config.max_significant_digits(4).round();
+        assert_eq!(dtoa(9999.4, sig), "9999.0");
+        assert_eq!(dtoa(9999.7, sig), "1.0e4");
+        assert_eq!(dtoa(-9999.7, sig), "-1.0e4");
+        let decimal = config.upper_e_break(5).max_decimal_digits(0).round();
+        assert_eq!(dtoa(99999.4, decimal), "99999.0");
+        assert_eq!(dtoa(99999.7, decimal), "1.0e5");
+        // Including when max_width does the rounding
+        let width = config.upper_e_break(5);
+        assert_eq!(dtoa(99999.7, width.max_width(7)), "99999.7");
+        assert_eq!(dtoa(99999.7, width.max_width(6)), "1.0e5");
+        let width = width.trailing_point(TrailingPoint::Never);
+        assert_eq!(dtoa(99999.7, width.max_width(6)), "1e5");
+        assert_eq!(dtoa(99.7, config.max_width(4)), "99.7");
+        // And rounding up past lower_e_break leaves e notation
+        let lower = config.lower_e_break(-2);
+        assert_eq!(dtoa(0.0099997, lower), "9.9997e-3");
+        assert_eq!(
+            dtoa(0.0099997, lower.max_significant_digits(4).round()),
+            "0.01"
+        );
+        assert_eq!(dtoa(0.0099997, lower.max_decimal_digits(3).round()), "0.01");
+        // Truncating never crosses the break
+        assert_eq!(dtoa(9999.7, sig.truncate()), "9999.0");
+    }
+
+    #[test]
+    fn test_float_format() {
+        let fixed = FmtFloatConfig::from(FloatFormat::Fixed { decimals: 2 });
+        assert_eq!(dtoa(1234.5, fixed), "1234.50");
+        assert_eq!(dtoa(-0.005, fixed), "-0.01");
+        assert_eq!(dtoa(1.5e20, fixed), "150000000000000000000.00");
+        let whole = FmtFloatConfig::from(FloatFormat::Fixed { decimals: 0 });
+        assert_eq!(dtoa(12.7, whole), "13");
+        let scientific = FmtFloatConfig::from(FloatFormat::Scientific { sig: 3 });
+        assert_eq!(dtoa(1234.5, scientific), "1.23e3");
+        assert_eq!(dtoa(0.5, scientific), "5.00e-1");
+        assert_eq!(dtoa(-2.0, scientific), "-2.00e0");
+        let one = FmtFloatConfig::from(FloatFormat::Scientific { sig: 1 });
+        assert_eq!(dtoa(1234.5, one), "1e3");
+        let general = FmtFloatConfig::from(FloatFormat::General { sig: 3 });
+        assert_eq!(dtoa(1234.5, general), "1.23e3");
+        assert_eq!(dtoa(123.4, general), "123");
+        assert_eq!(dtoa(0.5, general), "0.5");
+        assert_eq!(dtoa(0.0001234, general), "0.000123");
+        assert_eq!(dtoa(0.00001234, general), "1.23e-5");
+        let shortest = FmtFloatConfig::from(FloatFormat::Shortest);
+        assert_eq!(shortest, FmtFloatConfig::default());
+        assert_eq!(dtoa(1234.5, shortest), "1234.5");
+        let percent = FmtFloatConfig::from(FloatFormat::Percent { decimals: 1 });
+        assert_eq!(dtoa(12.5, percent), "12.5%");
+        assert_eq!(dtoa(-3.0, percent), "-3.0%");
+        // Application code can match on the format
+        let describe = |format: FloatFormat| match format {
+            FloatFormat::Fixed { decimals } | FloatFormat::Percent { decimals } => decimals,
+            FloatFormat::Scientific { sig } | FloatFormat::General { sig } => sig,
+            FloatFormat::Shortest => 0,
+        };
+        assert_eq!(describe(FloatFormat::General { sig: 4 }), 4);
+    }
+
+    #[test]
+    fn test_pretty_scientific() {
+        let config = FmtFloatConfig::pretty_scientific();
+        // Inside the prefix range
+        assert_eq!(dtoa(1500.0, config), "1.5k");
+        assert_eq!(dtoa(15000.0, config), "15k");
+        assert_eq!(dtoa(-150000.0, config), "-150k");
+        assert_eq!(dtoa(1.5e-5, config), "15µ");
+        assert_eq!(dtoa(0.25, config), "250m");
+        assert_eq!(dtoa(2.5, config), "2.5");
+        assert_eq!(dtoa(1.5e-24, config), "1.5y");
+        assert_eq!(dtoa(9.5e26, config), "950Y");
+        // Outside of it
+        assert_eq!(dtoa(1.5e-30, config), "1.5×10⁻³⁰");
+        assert_eq!(dtoa(1.5e27, config), "1.5×10²⁷");
+        assert_eq!(dtoa(-2.0e-25, config), "-2×10⁻²⁵");
+        // Rounding into the next prefix
+        assert_eq!(dtoa(999.7, config.max_significant_digits(3)), "1k");
+        // The prefix is the exponent, before the unit
+        let structured = dtoa_structured(1500.0, config.unit("m").unit_space(false));
+        assert_eq!(structured.text, "1.5km");
+        assert_eq!(structured.exponent_index, Some(3));
+        assert_eq!(structured.exponent, Some(3));
+        assert_eq!(dtoa_structured(2.5, config).exponent, None);
+        // Each feature on its own
+        let prefixes = FmtFloatConfig::default()
+            .force_e_notation()
+            .si_prefixes(true);
+        assert_eq!(dtoa(1500.0, prefixes), "1.5k");
+        assert_eq!(dtoa(2000.0, prefixes), "2.0k");
+        assert_eq!(dtoa(1.5e30, prefixes), "1.5e30");
+        assert_eq!(dtoa(1500.0, prefixes.min_decimal_digits(3)), "1.500k");
+        assert_eq!(
+            dtoa(1500.0, FmtFloatConfig::default().si_prefixes(true)),
+            "1500.0"
+        );
+        assert_eq!(superscript_exponent(0), "×10⁰");
+        assert_eq!(superscript_exponent(-308), "×10⁻³⁰⁸");
+        assert_eq!(dtoa_latex(1500.0, "\\times", config), "1.5 \\times 10^{3}");
+    }
+
+    #[test]
+    fn test_omit_radix_point() {
+        let config = FmtFloatConfig::default().omit_radix_point(true);
+        let two = config.min_decimal_digits(2).max_decimal_digits(2);
+        assert_eq!(dtoa(3.24159, two), "324");
+        assert_eq!(dtoa(-3.24159, two), "-324");
+        assert_eq!(dtoa(12.0, two), "1200");
+        // Leading zeros below 1 are kept
+        assert_eq!(dtoa(0.05, two), "005");
+        assert_eq!(dtoa(0.5, two), "050");
+        assert_eq!(dtoa(0.0, two), "000");
+        assert_eq!(dtoa(0.00123, config.force_no_e_notation()), "000123");
+        assert_eq!(dtoa(12.0, config), "120");
+        assert_eq!(dtoa(12.0, config.add_point_zero(false)), "12");
+        let formatted = dtoa_structured(3.25, config);
+        assert_eq!(formatted.text, "325");
+        assert_eq!(formatted.radix_index, None);
+        // Scientific notation
+        assert_eq!(dtoa(1.5e10, config), "15e10");
+        assert_eq!(dtoa(1e10, config), "10e10");
+        // The missing point doesn't count towards max_width
+        assert_eq!(dtoa(3.24159, config.max_width(3)), "324");
+        assert_eq!(dtoa(0.25, config.max_width(3)), "025");
+        assert_eq!(dtoa(12.0, config.max_width(3)), "120");
+        assert_eq!(dtoa(1.25e10, config.max_width(5)), "13e10");
+        assert_eq!(dtoa(1.25e10, config.max_width(6)), "125e10");
+        assert_eq!(dtoa(1e10, config.max_width(4)), "1e10");
+        assert_eq!(dtoa(1e10, config.max_width(5)), "10e10");
+        // Grouping still works
+        assert_eq!(
+            dtoa(1234.5, config.group_separator(',').force_no_e_notation()),
+            "1,2345"
+        );
+    }
+
+    #[test]
+    fn test_dtoa_with_mode() {
+        let config = FmtFloatConfig::default().max_decimal_digits(1);
+        assert_eq!(dtoa(2.36, config.truncate()), "2.3");
+        assert_eq!(
+            dtoa_with_mode(2.36, config.truncate(), RoundMode::Round),
+            "2.4"
+        );
+        assert_eq!(
+            dtoa_with_mode(2.36, config.round(), RoundMode::Truncate),
+            "2.3"
+        );
+        assert_eq!(
+            dtoa_with_mode(-2.36, config.round(), RoundMode::Truncate),
+            "-2.3"
+        );
+        let always_up = RoundMode::Custom(|_, _| true);
+        assert_eq!(dtoa_with_mode(2.31, config, always_up), "2.4");
+        // Only the round mode changes
+        let config = config.radix_point(',').max_width(4);
+        for value in [2.36, -2.36, 1234.5678] {
+            assert_eq!(
+                dtoa_with_mode(value, config.round(), RoundMode::Truncate),
+                dtoa(value, config.truncate())
+            );
         }
     }
-    for digit in digits {
-        if e > 0 && curr == e {
-            as_str.push(config.radix_point);
+
+    #[test]
+    fn test_dtoa_checked() {
+        // Whether a string of digits ending in a check digit passes Luhn
+        fn luhn_valid(digits: &[u32]) -> bool {
+            let mut sum = 0;
+            for (i, digit) in digits.iter().rev().enumerate() {
+                let mut digit = *digit;
+                if i % 2 == 1 {
+                    digit *= 2;
+                    if digit > 9 {
+                        digit = digit / 10 + digit % 10;
+                    }
+                }
+                sum += digit;
+            }
+            sum % 10 == 0
+        }
+        let config = FmtFloatConfig::default();
+        let plain = config.force_no_e_notation().add_point_zero(false);
+        assert_eq!(
+            dtoa_checked(7992739871.0, CheckDigit::Luhn, '-', plain),
+            "7992739871-3"
+        );
+        assert_eq!(dtoa_checked(0.0, CheckDigit::Luhn, '-', plain), "0-0");
+        assert_eq!(
+            dtoa_checked(-1.5e-7, CheckDigit::DigitSum, ' ', config),
+            "-1.5e-7 3"
+        );
+        assert_eq!(dtoa_checked(f64::NAN, CheckDigit::Luhn, '-', config), "NaN");
+        assert_eq!(
+            dtoa_checked(f64::NEG_INFINITY, CheckDigit::Luhn, '-', config),
+            "-inf"
+        );
+        for value in [3.24159, -2.5e100, 1234.5678, 0.001, 7.0, 98765.4321] {
+            let formatted = dtoa(value, config);
+            let digits: Vec<u32> = formatted.chars().filter_map(|c| c.to_digit(10)).collect();
+            let luhn = dtoa_checked(value, CheckDigit::Luhn, '|', config);
+            let (text, check) = luhn.split_at(luhn.len() - 2);
+            assert_eq!(text, formatted);
+            let mut with_check = digits.clone();
+            with_check.push(check[1..].parse().unwrap());
+            assert!(luhn_valid(&with_check), "{}", luhn);
+            let sum = dtoa_checked(value, CheckDigit::DigitSum, '|', config);
+            let expected = digits.iter().sum::<u32>() % 10;
+            assert_eq!(sum, format!("{}|{}", formatted, expected));
         }
-        as_str.push(digit as char);
-        curr += 1;
     }
-    let is_integer = curr <= e;
-    while e > 0 && curr < e {
-        as_str.push('0');
-        curr += 1;
+
+    #[test]
+    fn test_will_lose_precision() {
+        let config = FmtFloatConfig::default();
+        assert!(!will_lose_precision(0.1 + 0.2, config));
+        assert!(!will_lose_precision(1.5e300, config));
+        assert!(!will_lose_precision(
+            f64::NAN,
+            config.max_significant_digits(1)
+        ));
+        let sig = config.max_significant_digits(4);
+        assert!(will_lose_precision(3.24159, sig));
+        assert!(will_lose_precision(-3.24159, sig));
+        assert!(will_lose_precision(123456.0, sig.force_no_e_notation()));
+        assert!(!will_lose_precision(3.5, sig));
+        assert!(!will_lose_precision(3.125, sig));
+        // Padding with zeros and rounding to the same value lose nothing
+        assert!(!will_lose_precision(3.5, sig.min_significant_digits(6)));
+        assert!(will_lose_precision(2.55, config.max_decimal_digits(1)));
+        assert!(!will_lose_precision(2.5, config.max_decimal_digits(1)));
+        // Every way of removing digits counts
+        assert!(will_lose_precision(1234.5678, config.max_width(6)));
+        assert!(will_lose_precision(
+            1.2345e20,
+            config.max_mantissa_digits(2)
+        ));
+        assert!(will_lose_precision(1.2345e20, config.max_width(6)));
+        assert!(will_lose_precision(-1e-300, config.max_width(4)));
+        let capped = config.force_e_notation().max_exponent_magnitude(1);
+        assert!(!will_lose_precision(1.2345e5, capped));
+        assert!(will_lose_precision(
+            1.2345e5,
+            capped.max_significant_digits(2)
+        ));
+        // Even when the string can't be parsed back
+        let comma = config.radix_point(',');
+        assert!(!will_lose_precision(3.24159, comma));
+        assert!(will_lose_precision(
+            3.24159,
+            comma.max_significant_digits(2)
+        ));
+        // And it matches the truncation marker
+        let marked = config.truncation_marker('~');
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let value = (rng.gen::<f64>() - 0.5) * 1e6;
+            for config in [
+                marked.max_significant_digits(5),
+                marked.max_decimal_digits(2),
+            ] {
+                let lost = will_lose_precision(value, config);
+                assert_eq!(lost, dtoa(value, config).ends_with('~'));
+                assert_eq!(lost, dtoa(value, config).parse::<f64>() != Ok(value));
+            }
+        }
     }
-    if is_integer && config.add_point_zero {
-        as_str.push(config.radix_point);
-        as_str.push('0');
+
+    #[test]
+    fn test_approx_marker() {
+        let config = FmtFloatConfig::default().approx_marker('≈');
+        let sig = config.max_significant_digits(3);
+        assert_eq!(dtoa(3.24159, sig), "≈3.24");
+        assert_eq!(dtoa(-3.24159, sig), "-≈3.24");
+        assert_eq!(dtoa(3.25, sig), "3.25");
+        assert_eq!(dtoa(-3.25, sig), "-3.25");
+        assert_eq!(dtoa(3.24159, config), "3.24159");
+        assert_eq!(dtoa(1.23456e10, sig), "≈1.23e10");
+        assert_eq!(dtoa(-1.23456e10, sig.truncation_marker('…')), "-≈1.23e10…");
+        assert_eq!(dtoa(f64::NAN, sig), "NaN");
+        // The indices move past the marker
+        let formatted = dtoa_structured(-1.23456e10, sig);
+        assert_eq!(formatted.text, "-≈1.23e10");
+        assert_eq!(formatted.sign_len, 1);
+        assert_eq!(&formatted.text[formatted.radix_index.unwrap()..], ".23e10");
+        assert_eq!(&formatted.text[formatted.exponent_index.unwrap()..], "e10");
+        // Inside templates
+        let parens = sig.negative_template("({})");
+        assert_eq!(dtoa(-3.24159, parens), "(≈3.24)");
+        assert_eq!(dtoa(-3.25, parens), "(3.25)");
+        // Room is always left for it
+        assert_eq!(dtoa(3.24159, config.max_width(5)), "≈3.24");
+        assert_eq!(dtoa(3.25, config.max_width(5)), "3.25");
+        assert_eq!(dtoa(-3.24159, config.max_width(5)), "-≈3.2");
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let value = (rng.gen::<f64>() - 0.5) * 1e6;
+            let s = dtoa(value, config.max_width(7));
+            assert!(s.chars().count() <= 7, "{}", s);
+            assert_eq!(
+                s.contains('≈'),
+                will_lose_precision(value, config.max_width(7))
+            );
+        }
     }
 
-    as_str
-}
+    #[test]
+    fn test_dtoa_repeating() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_repeating(1.0 / 3.0, 6, config), "0.(3)");
+        assert_eq!(dtoa_repeating(2.0 / 3.0, 1, config), "0.(6)");
+        assert_eq!(dtoa_repeating(-4.0 / 3.0, 6, config), "-1.(3)");
+        assert_eq!(dtoa_repeating(1.0 / 7.0, 6, config), "0.(142857)");
+        assert_eq!(dtoa_repeating(22.0 / 7.0, 6, config), "3.(142857)");
+        assert_eq!(dtoa_repeating(1.0 / 12.0, 6, config), "0.08(3)");
+        assert_eq!(dtoa_repeating(1.0 / 11.0, 6, config), "0.(09)");
+        assert_eq!(
+            dtoa_repeating(1.0 / 3.0, 6, config.radix_point(',')),
+            "0,(3)"
+        );
+        // Periods that are too long
+        assert_eq!(
+            dtoa_repeating(1.0 / 7.0, 5, config),
+            dtoa(1.0 / 7.0, config)
+        );
+        assert_eq!(
+            dtoa_repeating(1.0 / 17.0, 6, config),
+            dtoa(1.0 / 17.0, config)
+        );
+        assert_eq!(
+            dtoa_repeating(1.0 / 3.0, 0, config),
+            dtoa(1.0 / 3.0, config)
+        );
+        // Values that don't repeat
+        assert_eq!(dtoa_repeating(0.1, 6, config), "0.1");
+        assert_eq!(dtoa_repeating(-2.5, 6, config), "-2.5");
+        assert_eq!(dtoa_repeating(3.0, 6, config), "3.0");
+        assert_eq!(dtoa_repeating(0.0, 6, config), "0.0");
+        assert_eq!(
+            dtoa_repeating(2.0f64.sqrt(), 6, config),
+            "1.4142135623730951"
+        );
+        assert_eq!(dtoa_repeating(1e300, 6, config), "1.0e300");
+        assert_eq!(dtoa_repeating(f64::NAN, 6, config), "NaN");
+        assert_eq!(dtoa_repeating(f64::NEG_INFINITY, 6, config), "-inf");
+    }
 
-/// Convert a double-precision floating point value (``f64``) to a string
-/// using a given configuration
-///
-/// # Example
-///
-/// ```
-/// use pretty_dtoa::{dtoa, FmtFloatConfig};
-///
-/// let config = FmtFloatConfig::default()
-///     .force_no_e_notation()      // Don't use scientific notation
-///     .add_point_zero(true)       // Add .0 to the end of integers
-///     .max_significant_digits(4)  // Stop after the first 4 non-zero digits
-///     .radix_point(',')           // Use a ',' instead of a '.'
-///     .round();                   // Round after removing non-significant digits
-///
-/// assert_eq!(dtoa(12459000.0, config), "12460000,0");
-/// ```
-pub fn dtoa(value: f64, config: FmtFloatConfig) -> String {
-    if value.is_nan() {
-        return "NaN".to_string();
-    } else if value.is_infinite() {
-        if value.is_sign_positive() {
-            return "inf".to_string();
-        } else {
-            return "-inf".to_string();
+    #[test]
+    fn test_huge_integers() {
+        let config = FmtFloatConfig::default().force_no_e_notation();
+        let plain = config.add_point_zero(false);
+        let grouped = plain.group_separator(',');
+        for k in 16..=30 {
+            let value: f64 = format!("1e{}", k).parse().unwrap();
+            let digits = format!("1{}", "0".repeat(k));
+            assert_eq!(dtoa(value, plain), digits);
+            assert_eq!(dtoa(value, config), format!("{}.0", digits));
+            assert_eq!(dtoa(-value, plain), format!("-{}", digits));
+            // Every digit is grouped, including the zeros from extending
+            // the short mantissa
+            let grouped_digits = dtoa(value, grouped);
+            assert_eq!(grouped_digits.replace(',', ""), digits);
+            assert!(grouped_digits
+                .split(',')
+                .skip(1)
+                .all(|group| group.len() == 3));
         }
+        assert_eq!(dtoa(1e21, plain), "1000000000000000000000");
+        assert_eq!(dtoa(1e21, grouped), "1,000,000,000,000,000,000,000");
+        assert_eq!(
+            dtoa(1e21, config.group_separator(',')),
+            "1,000,000,000,000,000,000,000.0"
+        );
+        // Near powers of ten use their shortest digits, zero extended
+        assert_eq!(dtoa(1.5e21, plain), "1500000000000000000000");
+        assert_eq!(
+            dtoa(999999999999999900000.0, plain),
+            "999999999999999900000"
+        );
+        assert_eq!(
+            dtoa(1.0000000000000002e22, plain),
+            "10000000000000002000000"
+        );
+        assert_eq!(dtoa(9007199254740993.0, plain), "9007199254740992");
+        assert_eq!(
+            dtoa(123456789012345680000000.0, grouped),
+            "123,456,789,012,345,690,000,000"
+        );
+        assert_eq!(dtoa(f64::MAX, plain).len(), 309);
+        assert!(dtoa(f64::MAX, plain).starts_with("17976931348623157000"));
+        // Widths that fit don't switch to e notation
+        assert_eq!(dtoa(1e21, config.max_width(24)), "1000000000000000000000.0");
+        assert_eq!(dtoa(1e21, plain.max_width(22)), "1000000000000000000000");
+        assert_eq!(
+            dtoa(1e21, config.upper_e_break(100)),
+            "1000000000000000000000.0"
+        );
     }
-    let sign = value.is_sign_negative();
-    let (s, exp) = if value == 0.0 {
-        (String::from("0"), 1)
-    } else {
-        let rad_10 = d2d(value);
-        let s = format!("{}", rad_10.mantissa);
-        let exp = rad_10.exponent + s.len() as i32;
-        (s, exp)
-    };
-    let s = digits_to_a(sign, s.into_bytes(), exp, config);
-    if let Some(limit) = config.max_width {
-        if s.len() > limit as usize {
-            return std::iter::repeat('#').take(limit as usize).collect();
+
+    #[test]
+    fn test_mantissa_width() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .mantissa_width(6);
+        assert_eq!(dtoa(1200.0, config), "1.2000e3");
+        assert_eq!(dtoa(3.24159265, config), "3.2416e0");
+        assert_eq!(dtoa(-3.24159265, config), "-3.2416e0");
+        assert_eq!(dtoa(1.5, config.sign_column(true)), " 1.5000e0");
+        assert_eq!(dtoa(7.0, config), "7.0000e0");
+        assert_eq!(dtoa(0.000123456789, config), "1.2346e-4");
+        assert_eq!(dtoa(9.99996e10, config), "1.0000e11");
+        assert_eq!(dtoa(1.23456789e10, config.truncate()), "1.2345e10");
+        // Every mantissa has the same width
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let value = (rng.gen::<f64>() - 0.5) * 1e10;
+            let formatted = dtoa_structured(value, config);
+            let mantissa = &formatted.text[formatted.sign_len..formatted.exponent_index.unwrap()];
+            assert_eq!(mantissa.len(), 6, "{}", formatted.text);
         }
+        // It overrides the other mantissa limits
+        assert_eq!(dtoa(1200.0, config.max_mantissa_digits(2)), "1.2000e3");
+        assert_eq!(dtoa(1200.0, config.min_decimal_digits(8)), "1.2000e3");
+        // Short widths
+        let config = config.trailing_point(TrailingPoint::Never);
+        assert_eq!(dtoa(1200.0, config.mantissa_width(1)), "1e3");
+        assert_eq!(dtoa(1600.0, config.mantissa_width(2)), "2e3");
+        assert_eq!(dtoa(1600.0, config.mantissa_width(3)), "1.6e3");
+        assert_eq!(dtoa(1600.0, config.mantissa_width(0)), "2e3");
+        // Values without scientific notation aren't affected
+        let auto = FmtFloatConfig::default().mantissa_width(6);
+        assert_eq!(dtoa(12.5, auto), "12.5");
+        assert_eq!(dtoa(1.25e10, auto), "1.2500e10");
+        // Capped exponents keep the width
+        let capped = config.max_exponent_magnitude(2);
+        assert_eq!(dtoa(1.5e4, capped), "150.00e2");
     }
-    s
-}
 
-/// Convert a single-precision floating point value (``f32``) to a string
-/// using a given configuration
-pub fn ftoa(value: f32, config: FmtFloatConfig) -> String {
-    if value.is_nan() {
-        return "NaN".to_string();
-    } else if value.is_infinite() {
-        if value.is_sign_positive() {
-            return "inf".to_string();
-        } else {
-            return "-inf".to_string();
+    #[test]
+    fn test_config_error_display() {
+        assert_eq!(
+            ConfigError::AmbiguousSeparators.to_string(),
+            "radix_point and group_separator must be different, and can't be digits or '-'"
+        );
+        assert_eq!(
+            ConfigError::CrossedEBreaks.to_string(),
+            "upper_e_break must be greater than lower_e_break"
+        );
+        // Errors from validate can be passed along with ?
+        fn check(config: FmtFloatConfig) -> Result<(), Box<dyn std::error::Error>> {
+            config.validate()?;
+            Ok(())
         }
+        let crossed = FmtFloatConfig::default().upper_e_break(-5);
+        assert_eq!(
+            check(crossed).unwrap_err().to_string(),
+            "upper_e_break must be greater than lower_e_break"
+        );
+        assert!(check(FmtFloatConfig::default()).is_ok());
     }
-    let (s, exp) = if value == 0.0 {
-        (String::from("0"), 1)
-    } else {
-        let rad_10 = f2d(value);
-        let s = format!("{}", rad_10.mantissa);
-        let exp = rad_10.exponent + s.len() as i32;
-        (s, exp)
-    };
-    let sign = value.is_sign_negative();
-    let s = digits_to_a(sign, s.into_bytes(), exp, config);
-    if let Some(limit) = config.max_width {
-        if s.len() > limit as usize {
-            return std::iter::repeat('#').take(limit as usize).collect();
+
+    #[test]
+    fn test_dtoa_diff() {
+        let config = FmtFloatConfig::default();
+        let pair = |a, b| dtoa_diff(a, b, config);
+        assert_eq!(pair(3.24159, 3.24259), ("3.24159".into(), "3.24259".into()));
+        assert_eq!(pair(1.5, 1.25), ("1.50".into(), "1.25".into()));
+        assert_eq!(pair(-1.5, 123.0), (" -1.5".into(), "123.0".into()));
+        assert_eq!(pair(100.0, 99.999), ("100.000".into(), " 99.999".into()));
+        assert_eq!(
+            pair(1.5e-10, 1.25e-10),
+            ("1.50e-10".into(), "1.25e-10".into())
+        );
+        assert_eq!(pair(0.0, 2.5e10), ("0.0e0 ".into(), "2.5e10".into()));
+        assert_eq!(pair(f64::NAN, 1.5), ("NaN  ".into(), "  1.5".into()));
+        // Same length and radix point
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let a = (rng.gen::<f64>() - 0.5) * 1e4;
+            let b = a * (1.0 + (rng.gen::<f64>() - 0.5) * 1e-6);
+            let (x, y) = dtoa_diff(a, b, config);
+            assert_eq!(x.len(), y.len(), "{} {}", x, y);
+            assert_eq!(x.find('.'), y.find('.'), "{} {}", x, y);
+            assert_eq!(x.trim().parse::<f64>(), Ok(a));
+            assert_eq!(y.trim().parse::<f64>(), Ok(b));
         }
     }
-    s
-}
 
-#[cfg(test)]
-mod tests {
-    // Macro for checking coverage marks
-    macro_rules! check {
-        ($ident:ident) => {
-            #[no_mangle]
-            static $ident: $crate::__rt::AtomicUsize = $crate::__rt::AtomicUsize::new(0);
-            let _guard = $crate::__rt::Guard::new(&$ident, stringify!($ident));
+    #[test]
+    fn test_dtoa_words() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_words(3.0, 10, config), "three");
+        assert_eq!(dtoa_words(0.0, 10, config), "zero");
+        assert_eq!(dtoa_words(9.0, 10, config), "nine");
+        assert_eq!(dtoa_words(10.0, 10, config), "10.0");
+        assert_eq!(dtoa_words(11.0, 10, config), "11.0");
+        assert_eq!(dtoa_words(2.5, 10, config), "2.5");
+        assert_eq!(dtoa_words(-3.0, 10, config), "-3.0");
+        assert_eq!(dtoa_words(-0.0, 10, config), "-0.0");
+        assert_eq!(dtoa_words(f64::NAN, 10, config), "NaN");
+        assert_eq!(dtoa_words(f64::INFINITY, u64::MAX, config), "inf");
+        assert_eq!(dtoa_words(3.0, 0, config), "3.0");
+        let all = u64::MAX;
+        assert_eq!(dtoa_words(15.0, all, config), "fifteen");
+        assert_eq!(dtoa_words(40.0, all, config), "forty");
+        assert_eq!(dtoa_words(99.0, all, config), "ninety-nine");
+        assert_eq!(dtoa_words(100.0, all, config), "one hundred");
+        assert_eq!(dtoa_words(101.0, all, config), "one hundred one");
+        assert_eq!(dtoa_words(1000.0, all, config), "one thousand");
+        assert_eq!(dtoa_words(2021.0, all, config), "two thousand twenty-one");
+        assert_eq!(
+            dtoa_words(1_000_300_017.0, all, config),
+            "one billion three hundred thousand seventeen"
+        );
+        assert_eq!(dtoa_words(1e18, all, config), "one quintillion");
+        assert_eq!(dtoa_words(18e18, all, config), "eighteen quintillion");
+        assert_eq!(dtoa_words(2e19, all, config), "2.0e19");
+    }
+
+    #[test]
+    fn test_nan_sign_and_payload() {
+        let quiet = f64::NAN;
+        let signaling = f64::from_bits(0x7ff0_0000_0000_0001);
+        let negative = f64::from_bits(0xfff8_0000_0000_0000);
+        let config = FmtFloatConfig::default();
+        for value in [quiet, signaling, negative] {
+            assert_eq!(dtoa(value, config), "NaN");
+        }
+        let sign = config.show_nan_sign(true);
+        assert_eq!(dtoa(quiet, sign), "NaN");
+        assert_eq!(dtoa(signaling, sign), "NaN");
+        assert_eq!(dtoa(negative, sign), "-NaN");
+        let payload = config.show_nan_payload(true);
+        assert_eq!(dtoa(quiet, payload), "NaN(0x8000000000000)");
+        assert_eq!(dtoa(signaling, payload), "NaN(0x1)");
+        assert_eq!(dtoa(negative, payload), "NaN(0x8000000000000)");
+        let both = sign.show_nan_payload(true);
+        assert_eq!(dtoa(negative, both), "-NaN(0x8000000000000)");
+        assert_eq!(
+            dtoa(f64::from_bits(0xfff4_0000_dead_beef), both),
+            "-NaN(0x40000deadbeef)"
+        );
+        let structured = dtoa_structured(negative, both);
+        assert_eq!(structured.sign_len, 1);
+        // The longer text is fitted to max_width
+        assert_eq!(dtoa(quiet, payload.max_width(3)), "###");
+        assert_eq!(dtoa(quiet, payload.max_width(20)), "NaN(0x8000000000000)");
+        assert_eq!(dtoa(negative, both.max_width(4)), "####");
+        assert_eq!(dtoa(negative, sign.max_width(4)), "-NaN");
+        assert_eq!(dtoa_structured(quiet, payload.max_width(3)).sign_len, 0);
+        // Single-precision floats
+        assert_eq!(ftoa(f32::NAN, both), "NaN(0x400000)");
+        assert_eq!(ftoa(f32::from_bits(0xff80_0001), both), "-NaN(0x1)");
+        assert_eq!(ftoa(-f32::NAN, config), "NaN");
+        // Other values are unchanged
+        assert_eq!(dtoa(-1.5, both), "-1.5");
+        assert_eq!(dtoa(f64::NEG_INFINITY, both), "-inf");
+    }
+
+    #[test]
+    fn test_mantissa_field_width() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .mantissa_field_width(6);
+        assert_eq!(dtoa(1500.0, config), "1.5   e3");
+        assert_eq!(dtoa(12340.0, config), "1.234 e4");
+        assert_eq!(dtoa(-1.5e-7, config), "-1.5  e-7");
+        assert_eq!(dtoa(1.2345678e10, config), "1.2345678e10");
+        // The exponents line up
+        let values = [1.0, -2.5, 3.75e10, 1.125e-100, 0.0, 12.5];
+        let columns: Vec<_> = values
+            .iter()
+            .map(|value| dtoa(*value, config).find('e'))
+            .collect();
+        assert!(columns.iter().all(|column| *column == Some(6)));
+        let structured = dtoa_structured(1500.0, config);
+        assert_eq!(structured.exponent_index, Some(6));
+        // Other exponent styles
+        let prefixes = config.si_prefixes(true);
+        assert_eq!(dtoa(1500.0, prefixes), "1.5   k");
+        assert_eq!(dtoa(1.5, prefixes), "1.5");
+        let capped = config.max_exponent_magnitude(2);
+        assert_eq!(dtoa(1.5e4, capped), "150.0 e2");
+        // Without an exponent there's no padding
+        let auto = FmtFloatConfig::default().mantissa_field_width(6);
+        assert_eq!(dtoa(12.5, auto), "12.5");
+        assert_eq!(dtoa(1.5, config.suppress_zero_exponent(true)), "1.5");
+        // The padding counts towards max_width
+        assert_eq!(dtoa(1500.0, config.max_width(8)), "1.5   e3");
+        assert_eq!(dtoa(1500.0, config.max_width(7)), "#######");
+    }
+
+    #[test]
+    fn test_scientific_total_width() {
+        let config = FmtFloatConfig::default()
+            .force_e_notation()
+            .scientific_total_width(8);
+        assert_eq!(dtoa(1500.0, config), "1.5000e3");
+        assert_eq!(dtoa(-1.5e-7, config), "-1.50e-7");
+        assert_eq!(dtoa(1e100, config), "1.00e100");
+        assert_eq!(dtoa(3.24159e-100, config), "3.2e-100");
+        assert_eq!(dtoa(0.0, config), "0.0000e0");
+        // Rounding that carries into a longer exponent is redone
+        assert_eq!(dtoa(9.99999e9, config), "1.000e10");
+        assert_eq!(dtoa(9.99996e-10, config), "1.000e-9");
+        // The exponent is padded when a mantissa can't fill its width
+        assert_eq!(dtoa(-1.5e-100, config), "-2e-0100");
+        assert_eq!(dtoa(1.5e-10, config.scientific_total_width(6)), "2e-010");
+        // A carry whose exponent changes back when rounded again keeps
+        // the first rounding
+        assert_eq!(dtoa(-9.94e-100, config), "-1.0e-99");
+        assert_eq!(
+            dtoa(-9.94e-100, config.scientific_total_width(9)),
+            "-9.9e-100"
+        );
+        // Values that can't fit are longer
+        assert_eq!(dtoa(-1.5e-100, config.scientific_total_width(5)), "-2e-100");
+        // The sign column counts once
+        let column = config.sign_column(true);
+        assert_eq!(dtoa(1.5, column), " 1.500e0");
+        assert_eq!(dtoa(-2.25e-10, column), "-2.3e-10");
+        assert_eq!(dtoa(1.5e-100, column), " 2e-0100");
+        // Every value has the same width, with as many mantissa digits as
+        // fit, rounded from the value
+        let mut rng = rand::thread_rng();
+        for width in 8..20 {
+            for config in &[config, column] {
+                let config = config.scientific_total_width(width);
+                for _ in 0..500 {
+                    let value = f64::from_bits(rng.gen::<u64>());
+                    if !value.is_finite() {
+                        continue;
+                    }
+                    let s = dtoa(value, config);
+                    assert_eq!(s.chars().count(), width as usize, "{}", s);
+                    let (mantissa, exponent) = s.split_at(s.find('e').unwrap());
+                    let mantissa_digits: Vec<char> =
+                        mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+                    let sig_digits = mantissa_digits
+                        .iter()
+                        .rposition(|c| *c != '0')
+                        .map_or(1, |index| index + 1);
+                    let exponent_digits = exponent.trim_start_matches(['e', '-']);
+                    if exponent_digits.len() > 1 && exponent_digits.starts_with('0') {
+                        // Only a single digit mantissa leaves room for exponent zeros
+                        assert_eq!(sig_digits, 1, "{}", s);
+                    }
+                    let rounded = dtoa(
+                        value,
+                        FmtFloatConfig::default()
+                            .force_e_notation()
+                            .max_significant_digits(sig_digits as u8),
+                    );
+                    assert_eq!(
+                        s.trim_start().parse::<f64>(),
+                        rounded.parse::<f64>(),
+                        "{}",
+                        s
+                    );
+                }
+            }
+        }
+        // It overrides mantissa_width, and values without scientific
+        // notation aren't affected
+        assert_eq!(dtoa(1500.0, config.mantissa_width(2)), "1.5000e3");
+        let auto = FmtFloatConfig::default().scientific_total_width(8);
+        assert_eq!(dtoa(12.5, auto), "12.5");
+        assert_eq!(dtoa(1.5e10, auto), "1.500e10");
+        // Other exponent styles
+        assert_eq!(dtoa(1.5e4, config.max_exponent_magnitude(2)), "150.00e2");
+        assert_eq!(dtoa(1.5e4, config.si_prefixes(true)), "15.0000k");
+        assert_eq!(dtoa(1500.0, config.max_width(6)), "1.50e3");
+    }
+
+    #[test]
+    fn test_preset_consts() {
+        const NARROW: FmtFloatConfig = FmtFloatConfig::HUMAN.max_width(6);
+        assert_eq!(FmtFloatConfig::PRECISE, FmtFloatConfig::default());
+        assert_eq!(FmtFloatConfig::MACHINE, FmtFloatConfig::machine());
+        assert_eq!(FmtFloatConfig::AUTO, FmtFloatConfig::auto());
+        assert_eq!(FmtFloatConfig::HUMAN, FmtFloatConfig::display());
+        assert_eq!(
+            FmtFloatConfig::PRETTY_SCIENTIFIC,
+            FmtFloatConfig::pretty_scientific()
+        );
+        let value = 12345.678901;
+        assert_eq!(dtoa(value, FmtFloatConfig::PRECISE), "1.2345678901e4");
+        assert_eq!(dtoa(value, FmtFloatConfig::MACHINE), "12345.678901");
+        assert_eq!(dtoa(value, FmtFloatConfig::AUTO), "12346");
+        assert_eq!(dtoa(value, FmtFloatConfig::HUMAN), "12,346");
+        assert_eq!(dtoa(value, FmtFloatConfig::SCIENTIFIC), "1.2345678901e4");
+        assert_eq!(dtoa(12.5, FmtFloatConfig::SCIENTIFIC), "1.25e1");
+        assert_eq!(
+            dtoa(value, FmtFloatConfig::PRETTY_SCIENTIFIC),
+            "12.345678901k"
+        );
+        assert_eq!(dtoa(1234567.0, NARROW), "1.23e6");
+        // Consts can be matched on
+        let name = |config| match config {
+            FmtFloatConfig::PRECISE => "precise",
+            FmtFloatConfig::HUMAN => "human",
+            _ => "other",
         };
+        assert_eq!(name(FmtFloatConfig::display()), "human");
+        assert_eq!(name(FmtFloatConfig::default().radix_point(',')), "other");
     }
 
-    use super::*;
+    #[test]
+    fn test_round_trip_f32_ranges() {
+        // Subnormals, the neighbourhood of 1.0, and the top of the range,
+        // for both signs
+        for &sign in &[0, 1u32 << 31] {
+            verify_round_trip_f32_range(sign, sign + 0xffff);
+            verify_round_trip_f32_range(sign + 0x3f7f_8000, sign + 0x3f80_8000);
+            verify_round_trip_f32_range(sign + 0x7f7f_0000, sign + 0x7f80_0000);
+        }
+    }
 
-    use rand;
-    use rand::Rng;
+    #[test]
+    fn test_point_aligned() {
+        let config = FmtFloatConfig::default();
+        // The radix point is always at column int_width
+        for &value in &[0.5, 1.0, -1.25, 99.125, -999.0, 0.001, 123.456] {
+            let s = dtoa_point_aligned(value, 4, 3, config);
+            assert_eq!(s.chars().count(), 8, "{:?}", s);
+            assert_eq!(s.find('.'), Some(4), "{:?}", s);
+        }
+        assert_eq!(dtoa_point_aligned(99.125, 4, 3, config), "  99.125");
+        assert_eq!(dtoa_point_aligned(0.001, 4, 3, config), "   0.001");
+        assert_eq!(dtoa_point_aligned(123.456, 4, 2, config), " 123.46");
+        assert_eq!(dtoa_point_aligned(0.0004, 4, 3, config), "   0.0  ");
+        assert_eq!(dtoa_point_aligned(7.0, 2, 0, config), " 7 ");
+        // A tighter max_decimal_digits from the config is kept
+        assert_eq!(
+            dtoa_point_aligned(1.25, 2, 3, config.max_decimal_digits(1)),
+            " 1.3  "
+        );
+        // Other radix points and a missing radix point
+        assert_eq!(
+            dtoa_point_aligned(1.5, 2, 2, config.radix_point(',')),
+            " 1,5 "
+        );
+        assert_eq!(
+            dtoa_point_aligned(7.0, 2, 2, config.add_point_zero(false)),
+            " 7   "
+        );
+        // Values that don't fit overflow to #'s
+        assert_eq!(dtoa_point_aligned(1000.0, 3, 1, config), "#####");
+        assert_eq!(dtoa_point_aligned(-100.0, 3, 1, config), "#####");
+        assert_eq!(dtoa_point_aligned(1e20, 3, 1, config), "#####");
+        assert_eq!(dtoa_point_aligned(f64::NAN, 3, 1, config), "NaN  ");
+        assert_eq!(dtoa_point_aligned(f64::NEG_INFINITY, 3, 1, config), "#####");
+    }
 
     #[test]
-    fn test_widths() {
-        check!(test_widths_internal);
-        // Test random floats with several configurations, and
-        // make sure that .max_width(_) does its job
+    fn test_short_long() {
+        check!(short_long_shared_digits);
         let mut rng = rand::thread_rng();
-
         let configs = &[
+            FmtFloatConfig::default().max_significant_digits(3).round(),
             FmtFloatConfig::default(),
-            FmtFloatConfig::default().force_no_e_notation(),
-            FmtFloatConfig::default().add_point_zero(true),
-            FmtFloatConfig::default()
-                .truncate()
-                .force_e_notation()
-                .min_significant_digits(5),
+            FmtFloatConfig::default().max_width(6),
+            FmtFloatConfig::default().force_e_notation().truncate(),
         ];
-
-        for width in 5..=13 {
-            for i in 0..20000 {
-                let config = configs[i % configs.len()].max_width(width);
-                let val = f64::from_bits(rng.gen::<u64>());
-                if val.is_nan() {
-                    continue;
-                }
-                let as_string = dtoa(val, config);
-                assert!(
-                    as_string.len() <= width as usize,
-                    "Found bad example for string width: '{}' at width {} gives {}",
-                    val,
-                    width,
-                    as_string
-                );
-                if as_string.chars().nth(0) == Some('#') {
-                    assert!(width <= 6, "Found example of a too-wide float: {}", val);
-                    hit!(test_widths_internal);
+        let specials = [0.0, -0.0, 1.5, -1234.5678, 1e300, f64::NAN, f64::INFINITY];
+        for i in 0..2000 {
+            let value = match specials.get(i) {
+                Some(value) => *value,
+                None => f64::from_bits(rng.gen::<u64>()),
+            };
+            for &short in configs {
+                for &long in configs {
+                    assert_eq!(
+                        dtoa_short_long(value, short, long),
+                        (dtoa(value, short), dtoa(value, long))
+                    );
                 }
             }
         }
     }
 
     #[test]
-    fn test_round_trip_dtoa() {
-        // Make sure random floats with several configurations
-        // round trip perfectly
-        let mut rng = rand::thread_rng();
+    fn test_max_byte_width() {
+        // '·' is two bytes in UTF-8, so the byte width is one more than the
+        // char width whenever there's a radix point
+        let config = FmtFloatConfig::default().radix_point('·');
+        assert_eq!(dtoa(1.25, config), "1·25");
+        assert_eq!(dtoa(1.25, config.max_width(4)), "1·25");
+        assert_eq!(dtoa(1.25, config.max_byte_width(4)), "1·3");
+        assert_eq!(dtoa(1.25, config.max_byte_width(5)), "1·25");
+        assert_eq!(dtoa(1.25, config.max_byte_width(2)), "##");
+        assert_eq!(dtoa(123456.0, config.max_byte_width(6)), "1·2e5");
+        assert_eq!(dtoa(1e300, config.max_byte_width(3)), "###");
+        assert_eq!(ftoa(0.5, config.max_byte_width(4)), "0·5");
+        assert_eq!(dtoa_structured(2.25, config.max_byte_width(4)).text, "2·3");
+        // A smaller max_width still applies
+        assert_eq!(dtoa(1.25, config.max_byte_width(10).max_width(3)), "1·3");
+        // Text that fits neither cap is as wide as the smaller one
+        let dash = FmtFloatConfig::default().zero_template("—");
+        assert_eq!(dtoa(0.0, dash.max_width(1).max_byte_width(2)), "#");
+        assert_eq!(dtoa(0.0, dash.max_width(3).max_byte_width(2)), "##");
+        // Including NaN and infinities
+        let payload = config.show_nan_payload(true);
+        assert_eq!(dtoa(f64::NAN, payload.max_byte_width(4)), "####");
+        assert_eq!(
+            dtoa(f64::NAN, payload.max_byte_width(20)),
+            "NaN(0x8000000000000)"
+        );
+        assert_eq!(ftoa(f32::NAN, payload.max_byte_width(4)), "####");
+        assert_eq!(dtoa(f64::NEG_INFINITY, config.max_byte_width(2)), "##");
+        assert_eq!(dtoa(f64::NEG_INFINITY, config.max_byte_width(4)), "-inf");
+        assert_eq!(ftoa(f32::INFINITY, config.max_byte_width(2)), "##");
+        assert_eq!(shortest_len(f64::INFINITY, config.max_byte_width(2)), 2);
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.format(f64::NAN, payload.max_byte_width(3)), "###");
 
+        // Both caps are always respected
+        let mut rng = rand::thread_rng();
         let configs = &[
-            FmtFloatConfig::default(),
-            FmtFloatConfig::default()
-                .force_no_e_notation()
-                .add_point_zero(true),
-            FmtFloatConfig::default().force_e_notation(),
+            config,
+            config.group_separator('\u{2009}').force_no_e_notation(),
+            config.force_e_notation(),
         ];
-        for _ in 0..20000 {
-            for config in configs.iter().cloned() {
-                let val = f64::from_bits(rng.gen::<u64>());
-                if val.is_nan() {
-                    continue;
+        for _ in 0..2000 {
+            let value = f64::from_bits(rng.gen::<u64>());
+            if !value.is_finite() {
+                continue;
+            }
+            for config in configs {
+                for bytes in 1..30 {
+                    let s = dtoa(value, config.max_byte_width(bytes));
+                    assert!(s.len() <= bytes as usize, "{:?} for {}", s, bytes);
+                    let width = bytes as u8 / 2 + 1;
+                    let s = dtoa(value, config.max_byte_width(bytes).max_width(width));
+                    assert!(s.len() <= bytes as usize, "{:?} for {}", s, bytes);
+                    assert!(s.chars().count() <= width as usize, "{:?} for {}", s, width);
                 }
-                let as_string = dtoa(val, config);
-                let round = as_string.parse::<f64>().unwrap();
-                assert!(round == val, "Found bad example for round trip: value '{}' gives string '{}' which turns into value '{}'", val, as_string, round);
             }
         }
     }
 
     #[test]
-    fn test_round_trip_ftoa() {
-        // Test round tripping for random f32's
-        let mut rng = rand::thread_rng();
-
-        let configs = &[
-            FmtFloatConfig::default(),
-            FmtFloatConfig::default()
-                .force_no_e_notation()
-                .add_point_zero(true),
-            FmtFloatConfig::default().force_e_notation(),
+    fn test_dtoa_base() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(dtoa_base(0.5, 2, config), "0.1");
+        assert_eq!(dtoa_base(0.5, 12, config), "0.6");
+        assert_eq!(dtoa_base(0.5, 16, config), "0.8");
+        assert_eq!(
+            dtoa_base(0.1, 2, config),
+            "0.0001100110011001100110011001100110011001100110011001101"
+        );
+        assert_eq!(dtoa_base(0.1, 12, config), "0.124972497249724b");
+        assert_eq!(dtoa_base(0.1, 16, config), "0.1999999999999a");
+        // Base 10 keeps enough digits to tell floats apart, not the shortest
+        assert_eq!(dtoa_base(0.1, 10, config), "0.10000000000000001");
+        assert_eq!(dtoa_base(123.456, 10, config), "123.456");
+        // Significant digits and rounding
+        assert_eq!(
+            dtoa_base(0.1, 12, config.max_significant_digits(6)),
+            "0.124972"
+        );
+        assert_eq!(dtoa_base(2.5, 10, config.max_significant_digits(1)), "3.0");
+        assert_eq!(
+            dtoa_base(2.5, 10, config.max_significant_digits(1).truncate()),
+            "2.0"
+        );
+        assert_eq!(
+            dtoa_base(35.5, 36, config.max_significant_digits(1)),
+            "10.0"
+        );
+        assert_eq!(
+            dtoa_base(1e300, 36, config.max_significant_digits(3)).len(),
+            195
+        );
+        assert!(dtoa_base(5e-324, 2, config).ends_with("01"));
+        // Ties follow the rounding mode
+        let sig1 = config.max_significant_digits(1);
+        assert_eq!(dtoa_base(2.5, 10, sig1.round_half_even()), "2.0");
+        assert_eq!(dtoa_base(3.5, 10, sig1.round_half_even()), "4.0");
+        assert_eq!(dtoa_base(2.5, 10, sig1.round_half_down()), "2.0");
+        assert_eq!(dtoa_base(2.5000001, 10, sig1.round_half_down()), "3.0");
+        let sig2 = config.max_significant_digits(2);
+        assert_eq!(dtoa_base(0.625, 2, sig2), "0.11");
+        assert_eq!(dtoa_base(0.625, 2, sig2.round_half_even()), "0.1");
+        assert_eq!(dtoa_base(0.875, 2, sig2.round_half_even()), "1.0");
+        assert_eq!(dtoa_base(0.625, 2, sig2.round_half_down()), "0.1");
+        assert_eq!(dtoa_base(4.5, 3, sig1.round_half_even()), "20.0");
+        // Values with short exact decimal digits round like dtoa in base 10,
+        // in every mode
+        let modes = [
+            sig1.round(),
+            sig1.truncate(),
+            sig1.round_half_even(),
+            sig1.round_half_down(),
+            sig1.round_with(|digits, index| digits[index] >= b'7'),
         ];
-        for _ in 0..20000 {
-            for config in configs.iter().cloned() {
-                let val = f32::from_bits(rng.gen::<u32>());
-                if val.is_nan() {
-                    continue;
+        for i in 1..400 {
+            let value = i as f64 / 16.0;
+            for sig_digits in 1..5 {
+                for mode in &modes {
+                    let config = mode.max_significant_digits(sig_digits);
+                    assert_eq!(
+                        dtoa_base(value, 10, config),
+                        dtoa(value, config),
+                        "{}",
+                        value
+                    );
                 }
-                let as_string = ftoa(val, config);
-                let round = as_string.parse::<f32>().unwrap();
-                assert!(round == val, "Found bad example for round trip: value '{}' gives string '{}' which turns into value '{}'", val, as_string, round);
             }
         }
+        // The rest of the config
+        assert_eq!(dtoa_base(-0.0, 7, config), "-0.0");
+        assert_eq!(dtoa_base(8.0, 8, config.add_point_zero(false)), "10");
+        assert_eq!(dtoa_base(1.5, 2, config.radix_point(',')), "1,1");
+        assert_eq!(dtoa_base(f64::NEG_INFINITY, 2, config), "-inf");
     }
 
     #[test]
-    fn test_max_sig_digits() {
-        let config = FmtFloatConfig::default().round().max_significant_digits(5);
-        assert_eq!(dtoa(3.111111, config), "3.1111");
-        assert_eq!(dtoa(123.4567, config), "123.46");
-        assert_eq!(dtoa(0.0001234567, config), "0.00012346");
-        assert_eq!(dtoa(22.29999, config), "22.3");
-        assert_eq!(dtoa(4.1, config), "4.1");
-        let config = FmtFloatConfig::default()
-            .truncate()
-            .max_significant_digits(4);
-        assert_eq!(dtoa(3.999999, config), "3.999");
-        assert_eq!(dtoa(555.5555, config), "555.5");
-        assert_eq!(dtoa(923.1, config), "923.1");
+    fn test_signed_series() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            dtoa_signed_series(&[5.0, 2.0, -1.5, 0.0, 2.5], config),
+            vec!["5.0", "+2.0", "-1.5", "0.0", "+2.5"]
+        );
+        // Only the later values are signed
+        assert_eq!(
+            dtoa_signed_series(&[-5.0, -2.0], config),
+            vec!["-5.0", "-2.0"]
+        );
+        assert_eq!(
+            dtoa_signed_series(&[3.0, 1e10], config.sign_column(true)),
+            vec![" 3.0", "+1.0e10"]
+        );
+        assert_eq!(dtoa_signed_series(&[7.0], config), vec!["7.0"]);
+        assert!(dtoa_signed_series(&[], config).is_empty());
     }
 
     #[test]
-    fn test_min_sig_digits() {
-        let config = FmtFloatConfig::default().min_significant_digits(5);
-        assert_eq!(dtoa(3.111111, config), "3.111111");
-        assert_eq!(dtoa(12.0, config), "12.000");
-        assert_eq!(dtoa(340.0, config), "340.00");
-        assert_eq!(dtoa(123.4567, config), "123.4567");
-        assert_eq!(dtoa(0.00123, config), "0.0012300");
+    fn test_estimated_digits() {
+        let cases: &[(f64, u8)] = &[
+            (0.0, 1),
+            (-0.0, 1),
+            (1.0, 1),
+            (1e300, 1),
+            (0.1, 1),
+            (123.456, 6),
+            (1.0 / 3.0, 16),
+            (2.0 / 3.0, 16),
+            (0.1 + 0.2, 17),
+            (f64::MAX, 17),
+            (f64::MIN_POSITIVE, 17),
+            (5e-324, 1),
+            (9007199254740993.0, 16),
+            (f64::INFINITY, 0),
+            (f64::NAN, 0),
+        ];
+        for &(value, expected) in cases {
+            assert_eq!(estimated_digits(value), expected, "{}", value);
+        }
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let value = f64::from_bits(rng.gen::<u64>());
+            assert_eq!(
+                estimated_digits(value) as usize,
+                mantissa_digits(value).len()
+            );
+        }
     }
 
     #[test]
-    fn test_max_decimal_digits() {
-        let config = FmtFloatConfig::default().max_decimal_digits(3).round();
-        assert_eq!(dtoa(3.41214, config), "3.412");
-        assert_eq!(dtoa(3.4129, config), "3.413");
-        assert_eq!(dtoa(3.4999, config), "3.5");
-        assert_eq!(dtoa(1293.4129, config), "1293.413");
-        assert_eq!(dtoa(203.4999, config), "203.5");
-        assert_eq!(dtoa(0.002911, config), "0.003");
-        let config = FmtFloatConfig::default().max_decimal_digits(3).truncate();
-        assert_eq!(dtoa(3.41214, config), "3.412");
-        assert_eq!(dtoa(3.4129, config), "3.412");
-        assert_eq!(dtoa(3.4999, config), "3.499");
-        assert_eq!(dtoa(393.4129, config), "393.412");
+    fn test_rounding_markers() {
         let config = FmtFloatConfig::default()
-            .max_decimal_digits(-3)
-            .truncate()
-            .force_no_e_notation()
-            .add_point_zero(true);
-        assert_eq!(dtoa(123123.0, config), "123000.0");
-        assert_eq!(dtoa(99999.0, config), "99000.0");
-        let config = FmtFloatConfig::default()
-            .max_decimal_digits(-3)
-            .round()
-            .force_no_e_notation()
-            .add_point_zero(true);
-        assert_eq!(dtoa(123123.0, config), "123000.0");
-        assert_eq!(dtoa(123923.0, config), "124000.0");
-        assert_eq!(dtoa(99999.0, config), "100000.0");
-    }
-
-    #[test]
-    fn test_min_decimal_digits() {
-        let config = FmtFloatConfig::default().min_decimal_digits(3);
-        assert_eq!(dtoa(3.4, config), "3.400");
-        assert_eq!(dtoa(10.0, config), "10.000");
-        assert_eq!(dtoa(100.0, config), "100.000");
-        let config = FmtFloatConfig::default().min_decimal_digits(5);
-        assert_eq!(dtoa(0.023, config), "0.02300");
-        assert_eq!(dtoa(0.123, config), "0.12300");
-        assert_eq!(dtoa(0.12345678, config), "0.12345678");
-    }
-
-    #[test]
-    fn test_upper_e_break() {
-        let config = FmtFloatConfig::default().upper_e_break(3);
-        assert_eq!(dtoa(23.4, config), "23.4");
-        assert_eq!(dtoa(892.3, config), "892.3");
-        assert_eq!(dtoa(1892.3, config), "1.8923e3");
-        assert_eq!(dtoa(71892.3, config), "7.18923e4");
+            .rounding_markers('^', 'v')
+            .max_significant_digits(3)
+            .round();
+        // Rounded down, rounded up, and exact
+        assert_eq!(dtoa(3.24159, config), "3.24v");
+        assert_eq!(dtoa(2.68828, config), "2.69^");
+        assert_eq!(dtoa(1.25, config), "1.25");
+        assert_eq!(dtoa(1500.0, config), "1500.0");
+        // The direction is of the signed value
+        assert_eq!(dtoa(-3.24159, config), "-3.24^");
+        assert_eq!(dtoa(-2.68828, config), "-2.69v");
+        // Truncation always shows a smaller magnitude
+        assert_eq!(dtoa(2.68828, config.truncate()), "2.68v");
+        // Scientific notation, max_width, and the truncation marker
+        assert_eq!(dtoa(2.68828e20, config), "2.69e20^");
+        assert_eq!(dtoa(2.68828, config.max_width(4)), "2.7^");
+        assert_eq!(dtoa(2.68828, config.truncation_marker('…')), "2.69…^");
+        // The shortest digits can round to the other side of the exact value
+        assert_eq!(dtoa(0.1, config.max_significant_digits(17)), "0.1");
+        assert_eq!(
+            dtoa(
+                0.1,
+                config.max_significant_digits(17).truncation_marker('…')
+            ),
+            "0.1"
+        );
+        // The marker matches how the shown digits compare to the exact value
+        let mut rng = rand::thread_rng();
+        for i in 0..2000 {
+            let value = (rng.gen::<f64>() - 0.5) * 1000.0;
+            let sig = (i % 5 + 1) as u8;
+            let s = dtoa(value, config.max_significant_digits(sig));
+            let shown = s.trim_end_matches(&['^', 'v'][..]);
+            let shown_value = shown.parse::<f64>().unwrap();
+            if s.ends_with('^') {
+                assert!(shown_value >= value, "{} {}", value, s);
+            } else if s.ends_with('v') {
+                assert!(shown_value <= value, "{} {}", value, s);
+            } else {
+                assert_eq!(shown_value, value);
+            }
+        }
     }
 
     #[test]
-    fn test_lower_e_break() {
-        let config = FmtFloatConfig::default().lower_e_break(-3);
-        assert_eq!(dtoa(0.123, config), "0.123");
-        assert_eq!(dtoa(0.0123, config), "0.0123");
-        assert_eq!(dtoa(0.00123, config), "0.00123");
-        assert_eq!(dtoa(0.000123, config), "1.23e-4");
-        assert_eq!(dtoa(0.0000123, config), "1.23e-5");
+    fn test_named_scale() {
+        let config = FmtFloatConfig::default();
+        let short = |value| format_named_scale(value, &SHORT_SCALE, config);
+        assert_eq!(short(1_500_000.0), "1.5 million");
+        assert_eq!(short(-2.5e9), "-2.5 billion");
+        assert_eq!(short(25e9), "25.0 billion");
+        assert_eq!(short(1.5e-5), "15.0 micro");
+        assert_eq!(short(0.0015), "1.5 milli");
+        assert_eq!(short(4.2e24), "4.2 septillion");
+        // Values from 1 to 1000 have no name
+        assert_eq!(short(1.5), "1.5");
+        assert_eq!(short(999.0), "999.0");
+        assert_eq!(short(0.0), "0.0");
+        // The billion ambiguity
+        assert_eq!(
+            format_named_scale(3e12, &SHORT_SCALE, config),
+            "3.0 trillion"
+        );
+        assert_eq!(format_named_scale(3e12, &LONG_SCALE, config), "3.0 billion");
+        // Out of range values fall back to dtoa
+        assert_eq!(short(1.5e30), dtoa(1.5e30, config));
+        assert_eq!(short(1.5e-30), dtoa(1.5e-30, config));
+        assert_eq!(short(f64::NAN), "NaN");
+        // Rounding can move to the next name, and the unit goes after it
+        let config = config.max_significant_digits(2).round();
+        assert_eq!(
+            format_named_scale(999_960.0, &SHORT_SCALE, config),
+            "1.0 million"
+        );
+        assert_eq!(
+            format_named_scale(2e6, &SHORT_SCALE, config.unit("m")),
+            "2.0 million m"
+        );
+        // The name counts towards max_width
+        let config = FmtFloatConfig::default();
+        let short = |value, width| format_named_scale(value, &SHORT_SCALE, config.max_width(width));
+        assert_eq!(short(1.5e6, 4), "####");
+        assert_eq!(short(1.5e6, 10), "##########");
+        assert_eq!(short(1.5e6, 11), "1.5 million");
+        assert_eq!(short(1.25e6, 11), "1.3 million");
+        assert_eq!(short(1.5e6, 9), "#########");
+        assert_eq!(
+            format_named_scale(
+                1.5e6,
+                &SHORT_SCALE,
+                config.trailing_point(TrailingPoint::Never).max_width(9)
+            ),
+            "2 million"
+        );
+        assert_eq!(short(1.5, 3), "1.5");
+        assert_eq!(short(1.5e30, 7), dtoa(1.5e30, config.max_width(7)));
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let value =
+                (rng.gen::<f64>() - 0.5) * 10f64.powi((rng.gen::<f64>() * 60.0) as i32 - 30);
+            for width in 1..16 {
+                let s = short(value, width);
+                assert!(s.chars().count() <= width as usize, "{}", s);
+            }
+        }
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn test_ignore_extremes() {
-        let config = FmtFloatConfig::default().ignore_extremes(3);
-        assert_eq!(dtoa(12.1992, config), "12.1992");
-        assert_eq!(dtoa(12.199921, config), "12.2");
-        assert_eq!(dtoa(12.1002, config), "12.1002");
-        assert_eq!(dtoa(12.10002, config), "12.1");
+    fn test_serde() {
+        use serde::Deserialize;
         let config = FmtFloatConfig::default()
-            .ignore_extremes(3)
-            .add_point_zero(true);
-        assert_eq!(dtoa(99.99, config), "100.0");
-    }
-
-    #[test]
-    fn test_force_e_notation() {
-        let config = FmtFloatConfig::default().force_e_notation();
-        assert_eq!(dtoa(1.0, config), "1.0e0");
-        assert_eq!(dtoa(15.0, config), "1.5e1");
-        assert_eq!(dtoa(0.150, config), "1.5e-1");
+            .max_significant_digits(5)
+            .radix_point(',')
+            .group_separator('.')
+            .rounding_markers('^', 'v')
+            .unit("kg")
+            .truncate();
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: FmtFloatConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, config);
+        assert_eq!(dtoa(1234.5678, loaded), "1.234,5v kg");
+        // Missing fields keep their defaults
+        let partial: FmtFloatConfig = serde_json::from_str(
+            r#"{"max_sig_digits": 2, "radix_point": ",", "round_mode": "Round"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            partial,
+            FmtFloatConfig::default()
+                .max_significant_digits(2)
+                .radix_point(',')
+        );
+        // Deserialized configs are validated
+        assert!(serde_json::from_str::<FmtFloatConfig>(
+            r#"{"radix_point": ",", "group_separator": ","}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<FmtFloatConfig>(r#"{"upper_e_break": -5}"#).is_err());
+        // Including when deserialize is called directly
+        let mut de =
+            serde_json::Deserializer::from_str(r#"{"radix_point": ",", "group_separator": ","}"#);
+        assert!(<FmtFloatConfig as serde::Deserialize>::deserialize(&mut de).is_err());
+        let mut de =
+            serde_json::Deserializer::from_str(r#"{"radix_point": ",", "group_separator": ","}"#);
+        assert!(FmtFloatConfig::deserialize(&mut de).is_err());
+        // Custom round modes and exponent formatters are functions
+        fn up(_: &[u8], _: usize) -> bool {
+            true
+        }
+        let custom = FmtFloatConfig::default().round_with(up);
+        assert!(serde_json::to_string(&custom).is_err());
+        let formatted = FmtFloatConfig::default().exponent_formatter(superscript_exponent);
+        let loaded: FmtFloatConfig =
+            serde_json::from_str(&serde_json::to_string(&formatted).unwrap()).unwrap();
+        assert!(loaded.exponent_formatter.is_none());
+        // Strings can be loaded from a reader, which can't lend them, and
+        // loading the same strings again reuses them instead of leaking more
+        let first: FmtFloatConfig = serde_json::from_str(&json).unwrap();
+        let read: FmtFloatConfig = serde_json::from_reader(json.as_bytes()).unwrap();
+        assert_eq!(read, config);
+        assert!(core::ptr::eq(first.unit.unwrap(), read.unit.unwrap()));
+        // Strings that are the same as the default are the default
+        assert!(core::ptr::eq(
+            first.spoken_exponent_template,
+            FmtFloatConfig::default().spoken_exponent_template
+        ));
     }
 
     #[test]
-    fn test_force_no_e_notation() {
+    fn test_round_half_even() {
         let config = FmtFloatConfig::default()
-            .force_no_e_notation()
+            .max_significant_digits(1)
             .add_point_zero(false);
-        let s = format!("{}", 1.123e20);
-        assert_eq!(dtoa(1.123e20, config), s);
-        let s = format!("{}", 1.123e-20);
-        assert_eq!(dtoa(1.123e-20, config), s);
+        let even = config.round_half_even();
+        let down = config.round_half_down();
+        let cases: &[(f64, &str, &str, &str)] = &[
+            // value, round, half even, half down
+            (2.5, "3", "2", "2"),
+            (3.5, "4", "4", "3"),
+            (-2.5, "-3", "-2", "-2"),
+            (0.5, "0.5", "0.5", "0.5"),
+            (25.0, "30", "20", "20"),
+            (35.0, "40", "40", "30"),
+            (2.51, "3", "3", "3"),
+            (2.49, "2", "2", "2"),
+            (9.5, "10", "10", "9"),
+        ];
+        for &(value, round, half_even, half_down) in cases {
+            assert_eq!(dtoa(value, config), round, "{}", value);
+            assert_eq!(dtoa(value, even), half_even, "{}", value);
+            assert_eq!(dtoa(value, down), half_down, "{}", value);
+        }
+        // Ties are found with the exact value: 0.125 is exact, but 0.135 is
+        // slightly above its digits and 0.145 slightly below
+        let even = even.max_significant_digits(2);
+        assert_eq!(dtoa(0.125, even), "0.12");
+        assert_eq!(dtoa(0.135, even), "0.14");
+        assert_eq!(dtoa(0.145, even), "0.14");
+        // Decimal digits and a cut before every digit
+        let even = FmtFloatConfig::default().round_half_even();
+        assert_eq!(dtoa(0.25, even.max_decimal_digits(1)), "0.2");
+        assert_eq!(dtoa(0.75, even.max_decimal_digits(1)), "0.8");
+        assert_eq!(dtoa(50.0, even.max_decimal_digits(-2)), "0.0");
+        assert_eq!(dtoa(150.0, even.max_decimal_digits(-2)), "200.0");
     }
 
     #[test]
-    fn test_capitalize_e() {
-        let config = FmtFloatConfig::default().capitalize_e(true);
-        assert_eq!(dtoa(1.2e8, config), "1.2E8");
+    fn test_shortest_len() {
+        let configs = &[
+            FmtFloatConfig::default(),
+            FmtFloatConfig::default().force_e_notation(),
+            FmtFloatConfig::default().prefer_shortest_notation(true),
+            FmtFloatConfig::default()
+                .radix_point('·')
+                .group_separator('\u{2009}')
+                .unit("µm"),
+            FmtFloatConfig::default()
+                .max_width(6)
+                .truncation_marker('…'),
+            FmtFloatConfig::default()
+                .max_byte_width(8)
+                .approx_marker('≈')
+                .negative_template("({})"),
+            FmtFloatConfig::default()
+                .si_prefixes(true)
+                .mantissa_field_width(8)
+                .sign_column(true),
+            FmtFloatConfig::default()
+                .max_exponent_magnitude(5)
+                .scientific_total_width(12)
+                .show_nan_payload(true),
+        ];
+        assert_eq!(shortest_len(f64::NAN, configs[0]), 3);
+        assert_eq!(shortest_len(-0.0, configs[0]), 4);
+        assert_eq!(shortest_len(1234.5, configs[3]), 10);
+        let mut rng = rand::thread_rng();
+        for _ in 0..5000 {
+            let value = f64::from_bits(rng.gen::<u64>());
+            for &config in configs {
+                assert_eq!(
+                    shortest_len(value, config),
+                    dtoa(value, config).chars().count()
+                );
+            }
+        }
     }
 
     #[test]
-    fn test_add_point_zero() {
-        let config = FmtFloatConfig::default().add_point_zero(true);
-        assert_eq!(dtoa(1230.0, config), "1230.0");
-        assert_eq!(dtoa(129.0, config), "129.0");
-        assert_eq!(dtoa(12.2, config), "12.2");
-        let config = FmtFloatConfig::default().add_point_zero(false);
-        assert_eq!(dtoa(1230.0, config), "1230");
-        assert_eq!(dtoa(129.0, config), "129");
-        assert_eq!(dtoa(12.2, config), "12.2");
+    fn test_fraction_group_separator() {
+        let config = FmtFloatConfig::default()
+            .force_no_e_notation()
+            .group_separator(',')
+            .fraction_group_separator(' ');
+        assert_eq!(dtoa(1234567.891234, config), "1,234,567.891 234");
+        assert_eq!(dtoa(0.1234567, config), "0.123 456 7");
+        assert_eq!(dtoa(0.0000123, config), "0.000 012 3");
+        assert_eq!(dtoa(-1234.5, config), "-1,234.5");
+        assert_eq!(dtoa(1000.0, config), "1,000.0");
+        assert_eq!(dtoa(0.123, config), "0.123");
+        // Group sizes on each side
+        let sized = config.group_size(4).fraction_group_size(2);
+        assert_eq!(dtoa(1234567.891234, sized), "123,4567.89 12 34");
+        assert_eq!(
+            dtoa(1234567.891234, config.group_size(0).fraction_group_size(0)),
+            "1234567.891234"
+        );
+        // Scientific notation isn't grouped
+        assert_eq!(dtoa(1.234567e20, config.force_e_notation()), "1.234567e20");
+        // max_width counts every separator
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let value = rng.gen::<f64>() * 10000.0;
+            for width in 5..20 {
+                let s = dtoa(value, config.max_width(width));
+                assert!(s.chars().count() <= width as usize, "{:?}", s);
+            }
+            let s = dtoa(value, sized);
+            let digits: String = s.chars().filter(|c| *c != ',' && *c != ' ').collect();
+            assert_eq!(digits.parse::<f64>(), Ok(value));
+        }
+        assert_eq!(dtoa(0.1234567, config.max_width(8)), "0.123 46");
+        assert_eq!(min_round_trip_width(0.1234567, config), 11);
+        // Fraction separators are checked like group separators
+        assert_eq!(
+            config.fraction_group_separator('.').validate(),
+            Err(ConfigError::AmbiguousSeparators)
+        );
+        assert_eq!(
+            config.fraction_group_separator('5').validate(),
+            Err(ConfigError::AmbiguousSeparators)
+        );
     }
 
     #[test]
-    fn test_max_width_specifics() {
-        check!(e_width_case_a);
-        check!(e_width_case_b);
-        check!(e_width_case_c);
-        let config = FmtFloatConfig::default().max_width(6).force_no_e_notation();
-        assert_eq!(dtoa(123.4533, config), "123.45");
-        assert_eq!(dtoa(0.00324, config), "0.0032");
-        assert_eq!(dtoa(-0.0324, config), "-0.032");
-        assert_eq!(dtoa(3e10, config), "3.0e10");
-        assert_eq!(dtoa(3.1e10, config), "3.1e10");
-        let config = FmtFloatConfig::default().max_width(5).force_no_e_notation();
-        assert_eq!(dtoa(3e10, config), "3.e10");
-        assert_eq!(dtoa(3.1e10, config), "3.e10");
-        let config = FmtFloatConfig::default().max_width(8).force_no_e_notation();
-        assert_eq!(dtoa(3.24e-10, config), "3.24e-10");
-        assert_eq!(dtoa(3.24e10, config), "3.24e10");
-        let config = FmtFloatConfig::default().max_width(5);
-        assert_eq!(dtoa(-3e100, config), "#####");
+    fn test_distinguish() {
+        let config = FmtFloatConfig::default();
+        assert_eq!(
+            dtoa_distinguish(1.2341, 1.2348, config),
+            ("1.234".to_string(), "1.235".to_string())
+        );
+        assert_eq!(
+            dtoa_distinguish(1.0, 2.0, config),
+            ("1.0".to_string(), "2.0".to_string())
+        );
+        assert_eq!(
+            dtoa_distinguish(100.21, 100.44, config),
+            ("100.2".to_string(), "100.4".to_string())
+        );
+        assert_eq!(
+            dtoa_distinguish(1.0, 1.0000000000000002, config),
+            ("1.0".to_string(), "1.0000000000000002".to_string())
+        );
+        // Equal values give identical strings
+        assert_eq!(
+            dtoa_distinguish(3.24159, 3.24159, config),
+            ("3.24159".to_string(), "3.24159".to_string())
+        );
+        assert_eq!(
+            dtoa_distinguish(0.1, 0.1, config.max_significant_digits(2)),
+            ("0.1".to_string(), "0.1".to_string())
+        );
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let a = f64::from_bits(rng.gen::<u64>());
+            let b = f64::from_bits(a.to_bits().wrapping_add(rng.gen::<u64>() % 4));
+            let (sa, sb) = dtoa_distinguish(a, b, config);
+            assert_eq!(sa == sb, a.to_bits() == b.to_bits() || a.is_nan());
+            if sa != sb {
+                // Every smaller significant digit limit gives equal strings
+                let limited = |sig_digits| config.max_significant_digits(sig_digits);
+                let simplified = |sig_digits| limited(sig_digits).simplify(true);
+                let used = (1..=17)
+                    .find(|&n| dtoa(a, simplified(n)) != dtoa(b, simplified(n)))
+                    .unwrap();
+                assert_eq!((sa, sb), (dtoa(a, limited(used)), dtoa(b, limited(used))));
+            }
+        }
     }
 
     #[test]
-    fn test_radix_point() {
-        let config = FmtFloatConfig::default()
-            .radix_point(',')
-            .add_point_zero(true);
-        assert_eq!(dtoa(123.4, config), "123,4");
-        assert_eq!(dtoa(1.3e10, config), "1,3e10");
-        assert_eq!(dtoa(123.0, config), "123,0");
+    fn test_max_width_empty_digits() {
+        // Widths that leave no room for digits after the sign, markers
+        // and units never panic or write a value without digits
+        let base = FmtFloatConfig::default().force_no_e_notation();
+        let configs = [
+            base,
+            base.sign_column(true),
+            base.truncation_marker('~'),
+            base.rounding_markers('+', '-'),
+            base.unit("km").unit_space(true),
+            base.unit("km").truncation_marker('~').sign_column(true),
+            base.group_separator(',').min_integer_digits(6),
+            base.min_decimal_digits(4).min_significant_digits(8),
+            base.truncate(),
+            base.ignore_extremes(1),
+            FmtFloatConfig::default().ignore_extremes(1),
+            FmtFloatConfig::default().unit("km").truncation_marker('~'),
+            FmtFloatConfig::default()
+                .force_e_notation()
+                .mantissa_width(0),
+        ];
+        let mut rng = rand::thread_rng();
+        let mut values = vec![0.0, -0.0, 0.5, -0.5, 9.99, -9.99, 999.0, 0.00999, 1e-300];
+        values.extend((0..200).map(|_| f64::from_bits(rng.gen::<u64>())));
+        values.extend((0..200).map(|_| (rng.gen::<f64>() - 0.5) * 2000.0));
+        for &config in &configs {
+            for max_width in 0..8 {
+                let config = config.max_width(max_width);
+                // NaN and infinities are always written in full
+                for &value in values.iter().filter(|value| value.is_finite()) {
+                    let s = dtoa(value, config);
+                    assert!(s.chars().count() <= max_width as usize, "{:?}", s);
+                    if !s.chars().all(|c| c == '#') {
+                        assert!(s.chars().any(|c| c.is_ascii_digit()), "{:?}", s);
+                    }
+                }
+            }
+        }
+        // Ignoring the only digit of zero leaves a 0
+        let config = base.ignore_extremes(1);
+        assert_eq!(dtoa(0.0, config.max_width(3)), "0.0");
+        assert_eq!(dtoa(-0.0, config.max_width(3)), "###");
+        assert_eq!(dtoa(-0.0, config.max_width(4)), "-0.0");
+        let config = base.unit("km").truncation_marker('~');
+        assert_eq!(dtoa(1.5, config.max_width(6)), "######");
+        assert_eq!(dtoa(1.5, config.max_width(7)), "1.5 km");
     }
 
     #[test]
@@ -894,7 +9844,7 @@ mod tests {
             .add_point_zero(true);
 
         assert_eq!(dtoa(0., config), "0.0");
-        
+
         let config = FmtFloatConfig::default()
             .max_decimal_digits(2)
             .add_point_zero(false);
@@ -0,0 +1,93 @@
+// Checks that formatting into a Buffer never allocates, with an allocator
+// that counts allocations. It's installed for this whole test binary, so
+// everything is checked in one test, to keep other tests from allocating
+// at the same time
+use pretty_dtoa::{Buffer, ExpCase, FmtFloatConfig, TrailingPoint};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The number of allocations made while running <f>
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    f();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+#[test]
+fn buffer_does_not_allocate() {
+    let values = [
+        0.0,
+        -0.0,
+        1.0,
+        0.1,
+        -1.2345,
+        2.5,
+        0.125,
+        123456789.0,
+        1.5e300,
+        -2.5e-300,
+        5e-324,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+        f64::NAN,
+        -f64::INFINITY,
+    ];
+    let configs = [
+        FmtFloatConfig::default(),
+        FmtFloatConfig::default().max_significant_digits(3).round(),
+        FmtFloatConfig::default()
+            .max_decimal_digits(2)
+            .round_half_even()
+            .precise_tie_detection(true),
+        FmtFloatConfig::default().max_width(6),
+        FmtFloatConfig::default().max_byte_width(5),
+        FmtFloatConfig::default()
+            .group_separator(',')
+            .min_decimal_digits(3),
+        FmtFloatConfig::default().force_no_e_notation(),
+        FmtFloatConfig::default()
+            .force_e_notation()
+            .exp_case(ExpCase::Upper)
+            .scientific_total_width(12),
+        FmtFloatConfig::default().si_prefixes(true).unit("m"),
+        FmtFloatConfig::default()
+            .spoken_exponent(true)
+            .trailing_point(TrailingPoint::Never),
+        FmtFloatConfig::default()
+            .max_width(5)
+            .rounding_markers('↑', '↓')
+            .approx_marker('~'),
+    ];
+    let mut buffer = Buffer::new();
+    for config in configs.iter() {
+        for value in values.iter() {
+            let count = allocations(|| {
+                buffer.format(*value, *config);
+            });
+            assert_eq!(count, 0, "formatting {:?} with {:?}", value, config);
+        }
+    }
+}
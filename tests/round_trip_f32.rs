@@ -0,0 +1,8 @@
+// Checks every f32, which is slow: run with
+// `cargo test --release --features slow-tests,rayon`
+#![cfg(feature = "slow-tests")]
+
+#[test]
+fn round_trip_f32_exhaustive() {
+    pretty_dtoa::verify_round_trip_exhaustive_f32();
+}